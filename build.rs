@@ -0,0 +1,29 @@
+/*Copyright (c) 2022 Diego da Silva Lima. All rights reserved.
+
+This work is licensed under the terms of the MIT license.  
+For a copy, see <https://opensource.org/licenses/MIT>.*/
+
+// Generates the C header of the ffi module when the header feature is enabled. Build scripts
+// should not modify the source tree (which breaks cargo package), so the header is written to
+// OUT_DIR, and only copied to PAPYRI_HEADER_DEST (e.g. include/papyri.h) when it is informed.
+#[cfg(feature="header")]
+fn main() {
+    println!("cargo:rerun-if-changed=src/ffi.rs");
+    println!("cargo:rerun-if-env-changed=PAPYRI_HEADER_DEST");
+    let out = std::path::Path::new(&std::env::var("OUT_DIR").unwrap()).join("papyri.h");
+    cbindgen::Builder::new()
+        .with_language(cbindgen::Language::C)
+        .with_include_guard("PAPYRI_H")
+        .with_src("src/ffi.rs")
+        .generate()
+        .expect("Unable to generate C header")
+        .write_to_file(&out);
+    if let Ok(dest) = std::env::var("PAPYRI_HEADER_DEST") {
+        std::fs::copy(&out, &dest).unwrap_or_else(|e| panic!("Unable to copy C header to {}: {}", dest, e) );
+    }
+}
+
+#[cfg(not(feature="header"))]
+fn main() {
+
+}
@@ -0,0 +1,126 @@
+#ifndef PAPYRI_H
+#define PAPYRI_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#define PAPYRI_OK 0
+
+#define PAPYRI_INVALID_UTF8 1
+
+#define PAPYRI_INSUFFICIENT_LEN 2
+
+#define PAPYRI_INVALID_DEFINITION 3
+
+/**
+ * A required pointer argument was NULL.
+ */
+#define PAPYRI_NULL_POINTER 4
+
+/**
+ * An argument (such as a plot or mapping position) is out of range, or the data is invalid.
+ */
+#define PAPYRI_INVALID_ARGUMENT 5
+
+/**
+ * The panel could not be rendered, or the format is not supported by this build.
+ */
+#define PAPYRI_RENDER_ERROR 6
+
+/**
+ * The library panicked. The handle should be released.
+ */
+#define PAPYRI_INTERNAL_ERROR 7
+
+#define PAPYRI_FORMAT_SVG 0
+
+/**
+ * Requires the library to be built with the cairo-rs feature.
+ */
+#define PAPYRI_FORMAT_PNG 1
+
+/**
+ * Requires the library to be built with the cairo-rs feature.
+ */
+#define PAPYRI_FORMAT_PDF 2
+
+/**
+ * Opaque handle to a panel.
+ */
+typedef struct PapyriPanel PapyriPanel;
+
+/**
+ * Writes the generic message for an error code (without a trailing NUL) to out.
+ */
+int64_t papyri_error(int64_t code, uint8_t *out, uintptr_t *out_len);
+
+/**
+ * Renders a JSON definition (not NUL-terminated) to SVG at once, without keeping a handle.
+ */
+int64_t papyri_svg(const uint8_t *model, uintptr_t model_len, uint8_t *out, uintptr_t *out_len);
+
+/**
+ * Creates a panel from a JSON definition of a panel, plot or mapping (json_len bytes,
+ * not NUL-terminated). Returns NULL only if json is NULL. If the definition is invalid,
+ * the returned handle has its error set, and all operations over it fail with
+ * PAPYRI_INVALID_DEFINITION.
+ */
+struct PapyriPanel *papyri_panel_new(const uint8_t *json, uintptr_t json_len);
+
+/**
+ * Releases a panel. Passing NULL has no effect.
+ */
+void papyri_panel_free(struct PapyriPanel *panel);
+
+/**
+ * Returns the message of the last failed operation over the panel (NUL-terminated, valid
+ * until the next call using the handle), or NULL if the last operation succeeded.
+ */
+const char *papyri_panel_last_error(const struct PapyriPanel *panel);
+
+/**
+ * Returns 1 if the panel was created from a valid definition, and 0 otherwise.
+ */
+int32_t papyri_panel_is_valid(const struct PapyriPanel *panel);
+
+/**
+ * Replaces the data of a mapping. columns points to n_columns arrays of n_rows values each
+ * (NaN for missing values). Lines, scatters and texts take x and y columns, bars and wedges a
+ * single column, and areas and intervals three columns.
+ */
+int64_t papyri_panel_update_mapping(struct PapyriPanel *panel,
+                                    uintptr_t plot,
+                                    uintptr_t mapping,
+                                    const double *const *columns,
+                                    uintptr_t n_columns,
+                                    uintptr_t n_rows);
+
+/**
+ * Replaces the labels of a text mapping with n NUL-terminated UTF-8 strings.
+ */
+int64_t papyri_panel_update_text(struct PapyriPanel *panel,
+                                 uintptr_t plot,
+                                 uintptr_t mapping,
+                                 const char *const *text,
+                                 uintptr_t n);
+
+/**
+ * Sets layout, design, scale or mapping properties from a JSON object (json_len bytes), such as
+ * {"layout": {"width": 800}, "plot": 0, "x": {"label": "Time"}, "mapping": 0, "set": {"color": "red"}}.
+ * No property is set if any of them is invalid.
+ */
+int64_t papyri_panel_set(struct PapyriPanel *panel,
+                         const uint8_t *json,
+                         uintptr_t json_len);
+
+/**
+ * Renders the panel to one of the PAPYRI_FORMAT_* formats, following the size-query convention.
+ */
+int64_t papyri_panel_render(struct PapyriPanel *panel,
+                            int32_t format,
+                            uint8_t *out,
+                            uintptr_t *out_len);
+
+#endif /* PAPYRI_H */
@@ -0,0 +1,229 @@
+/*Copyright (c) 2022 Diego da Silva Lima. All rights reserved.
+
+This work is licensed under the terms of the MIT license.  
+For a copy, see <https://opensource.org/licenses/MIT>.*/
+
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Instant;
+use serde::Deserialize;
+use serde_json::Value;
+use papyri::render::Panel;
+use papyri::model::Design;
+use crate::{CliError, RenderArgs};
+
+// A line of the JSON Lines input.
+#[derive(Deserialize)]
+struct Line {
+    spec : Value,
+    output : String,
+    format : Option<String>
+}
+
+enum Source {
+    Value(Value),
+    File(PathBuf)
+}
+
+struct Job {
+
+    // Where the definition came from (line number or file path), for reporting.
+    name : String,
+
+    source : Result<Source, String>,
+
+    output : PathBuf,
+
+    format : Result<String, String>
+
+}
+
+fn extension_for(format : &str) -> Result<&'static str, CliError> {
+    match format {
+        "svg" => Ok("svg"),
+        "png" => Ok("png"),
+        "pdf" => Ok("pdf"),
+        "eps" => Ok("eps"),
+        "html" | "interactive" => Ok("html"),
+        "terminal" => Ok("txt"),
+        other => Err(CliError::Usage(format!("Format {} cannot be used for batch rendering", other)))
+    }
+}
+
+fn jobs_from_lines(content : &str, outdir : Option<&Path>, format : Option<&str>) -> Vec<Job> {
+    let mut jobs = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let name = format!("line {}", i + 1);
+        let job = match serde_json::from_str::<Line>(line) {
+            Ok(line) => {
+                let output = match outdir {
+                    Some(dir) => dir.join(&line.output),
+                    None => PathBuf::from(&line.output)
+                };
+
+                // The format of each line takes precedence over the output extension and --format.
+                let format = match (line.format, format) {
+                    (Some(format), _) => Ok(format),
+                    (None, fallback) => crate::format_from_extension(&line.output)
+                        .map(|f| f.to_string() )
+                        .or_else(|e| fallback.map(|f| f.to_string() ).ok_or(e) )
+                        .map_err(CliError::message)
+                };
+                Job { name, source : Ok(Source::Value(line.spec)), output, format }
+            },
+            Err(e) => Job {
+                name,
+                source : Err(format!("Invalid line (expected {{\"spec\": ..., \"output\": ...}}): {}", e)),
+                output : PathBuf::new(),
+                format : Err(String::new())
+            }
+        };
+        jobs.push(job);
+    }
+    jobs
+}
+
+fn jobs_from_dir(dir : &Path, outdir : Option<&Path>, format : Option<&str>) -> Result<Vec<Job>, CliError> {
+    let format = format.unwrap_or("svg");
+    let ext = extension_for(format)?;
+    let entries = fs::read_dir(dir)
+        .map_err(|e| CliError::Io(format!("Unable to read directory {}: {}", dir.display(), e)) )?;
+    let mut paths : Vec<PathBuf> = entries.filter_map(|e| e.ok().map(|e| e.path() ) )
+        .filter(|p| p.is_file() && p.extension().map(|e| e == "json" ).unwrap_or(false) )
+        .collect();
+    paths.sort();
+    let outdir = outdir.unwrap_or(dir);
+    Ok(paths.into_iter().map(|path| {
+        let stem = path.file_stem().unwrap_or_default();
+        let output = outdir.join(stem).with_extension(ext);
+        Job { name : path.display().to_string(), source : Ok(Source::File(path)), output, format : Ok(format.to_string()) }
+    }).collect())
+}
+
+fn run_job(job : &Job, args : &RenderArgs, theme : &Option<Design>) -> Result<(), String> {
+    let source = job.source.as_ref().map_err(|e| e.clone() )?;
+    let format = job.format.as_ref().map_err(|e| e.clone() )?;
+    let panel = match source {
+        Source::Value(v) => Panel::new_from_json_value(v.clone())?,
+        Source::File(path) => {
+            let json = fs::read_to_string(path).map_err(|e| format!("Unable to read {}: {}", path.display(), e) )?;
+            Panel::new_from_json(&json)?
+        }
+    };
+    let mut panel = crate::apply_overrides(panel, args, theme.clone()).map_err(CliError::message)?;
+    let out = crate::render(&mut panel, format).map_err(CliError::message)?;
+    fs::write(&job.output, out).map_err(|e| format!("Unable to write {}: {}", job.output.display(), e) )
+}
+
+// Runs the jobs across threads, returning their results in the order of the jobs.
+fn run_jobs<F>(jobs : &[Job], threads : usize, run : F) -> Vec<Result<(), String>>
+where
+    F : Fn(&Job) -> Result<(), String> + Sync
+{
+    let next = AtomicUsize::new(0);
+    let mut results : Vec<(usize, Result<(), String>)> = thread::scope(|s| {
+        let workers : Vec<_> = (0..threads).map(|_| s.spawn(|| {
+            let mut done = Vec::new();
+            loop {
+                let ix = next.fetch_add(1, Ordering::Relaxed);
+                let job = match jobs.get(ix) {
+                    Some(job) => job,
+                    None => break done
+                };
+
+                // A panic while rendering a definition should not discard the other
+                // results of its worker.
+                let res = panic::catch_unwind(AssertUnwindSafe(|| run(job) ))
+                    .unwrap_or_else(|_| Err(String::from("Internal error while rendering the definition")) );
+                done.push((ix, res));
+            }
+        })).collect();
+        workers.into_iter().flat_map(|w| w.join().unwrap_or_default() ).collect()
+    });
+    results.sort_by_key(|(ix, _)| *ix );
+    results.into_iter().map(|(_, res)| res ).collect()
+}
+
+/// Renders all definitions of a JSON Lines file (or a directory), distributing them
+/// across threads.
+pub fn run_batch(mut args : Vec<String>) -> Result<(), CliError> {
+    let mut threads = None;
+    if let Some(ix) = args.iter().position(|a| a == "-j" || a == "--jobs" || a.starts_with("--jobs=") ) {
+        let arg = args.remove(ix);
+        let value = match arg.split_once('=') {
+            Some((_, value)) => value.to_string(),
+            None if ix < args.len() => args.remove(ix),
+            None => return Err(CliError::Usage(format!("Missing value for {}", arg)))
+        };
+        threads = Some(crate::parse_size("number of jobs", &value)? as usize);
+    }
+    let args = crate::parse_render_args(args)?;
+    let outdir = args.output.as_deref().map(Path::new);
+    if let Some(dir) = outdir {
+        fs::create_dir_all(dir)
+            .map_err(|e| CliError::Io(format!("Unable to create directory {}: {}", dir.display(), e)) )?;
+    }
+    let jobs = match args.input.as_deref() {
+        Some(dir) if Path::new(dir).is_dir() => jobs_from_dir(Path::new(dir), outdir, args.format.as_deref())?,
+        input => {
+            let content = crate::read_input(input)?;
+            jobs_from_lines(&content, outdir, args.format.as_deref())
+        }
+    };
+
+    // The theme is shared by all definitions.
+    let theme = args.theme.as_deref().map(crate::load_theme).transpose()?;
+
+    let threads = threads
+        .unwrap_or_else(|| thread::available_parallelism().map(|n| n.get() ).unwrap_or(1) )
+        .min(jobs.len().max(1));
+    let start = Instant::now();
+    let results = run_jobs(&jobs, threads, |job| run_job(job, &args, &theme) );
+
+    let mut failed = Vec::new();
+    for (job, res) in jobs.iter().zip(results) {
+        if let Err(e) = res {
+            eprintln!("papyri: {}: {}", job.name, e);
+            failed.push(&job.name[..]);
+        }
+    }
+    let elapsed = start.elapsed().as_secs_f64();
+    if failed.is_empty() {
+        eprintln!("papyri: rendered {} definitions in {:.2}s", jobs.len(), elapsed);
+        Ok(())
+    } else {
+        Err(CliError::Spec(format!(
+            "rendered {} of {} definitions in {:.2}s (failed: {})",
+            jobs.len() - failed.len(),
+            jobs.len(),
+            elapsed,
+            failed.join(", ")
+        )))
+    }
+}
+
+#[test]
+fn failed_jobs() {
+    let jobs = jobs_from_lines("{\"spec\": 1, \"output\": \"a.svg\"}\n\n{\"spec\": 2, \"output\": \"b.svg\"}\n{}\n{\"spec\": 3, \"output\": \"c.svg\"}\n", None, None);
+    let names : Vec<_> = jobs.iter().map(|j| &j.name[..] ).collect();
+    assert_eq!(names, ["line 1", "line 3", "line 4", "line 5"]);
+
+    // A definition panicking fails alone, and the others keep their results.
+    let results = run_jobs(&jobs, 2, |job| {
+        match job.source {
+            Ok(Source::Value(Value::Number(ref n))) if n.as_u64() == Some(2) => panic!("Rendering panicked"),
+            Ok(_) => Ok(()),
+            Err(ref e) => Err(e.clone())
+        }
+    });
+    assert_eq!(results.len(), 4);
+    assert!(results[0].is_ok() && results[3].is_ok());
+    assert_eq!(results[1].as_ref().unwrap_err(), "Internal error while rendering the definition");
+    assert!(results[2].as_ref().unwrap_err().starts_with("Invalid line"));
+}
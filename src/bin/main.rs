@@ -3,43 +3,558 @@
 This work is licensed under the terms of the MIT license.  
 For a copy, see <https://opensource.org/licenses/MIT>.*/
 
-fn main() -> Result<(), String> {
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::process::ExitCode;
+use papyri::render::Panel;
 
-    #[cfg(feature="gdk4")]
-    #[cfg(feature="gdk-pixbuf")]
-    #[cfg(feature="cairo-rs")]
-    {
+mod batch;
 
-        use std::io;
-        // use std::io::Read;
+mod serve;
 
-        let mut model_str = String::new();
-        // let panel : Option<papyri::render::Panel> = None;
+mod session;
 
-        // Read input lines until a plot can be parsed. Perhaps issue a
-        // timeout if no data is sent and the current input cannot be successfully parsed.
-        //loop {
-        let mut new_line = String::new();
-        io::stdin().read_line(&mut new_line).unwrap();
-        model_str += &new_line;
-        //if let Ok(mut panel) = papyri::render::Panel::new_from_json(&model_str[..]) {
-        let mut panel = papyri::render::Panel::new_from_json(&model_str[..]).map_err(|e| format!("{}",e) )?;
+mod watch;
 
-        //let svg = panel.svg().map_err(|e| format!("{}",e) )?;
-        // println!("{}", svg);
+const HELP : &str = "\
+papyri - render plot definitions written in JSON
 
-        let html = panel.html_img_tag().map_err(|e| format!("{}",e) )?;
-        println!("{}", html);
+USAGE:
+    papyri [render] [INPUT] [OPTIONS]
+    papyri validate [INPUT] [--json]
+    papyri watch INPUT [OPTIONS]
+    papyri batch [INPUT|DIR] [-j JOBS] [OPTIONS]
+    papyri session
+    papyri serve [--host ADDR] [--port PORT] [SERVER OPTIONS]
+    papyri --help | --version
 
+INPUT is the path of a JSON definition. If omitted or '-', the definition is read
+from standard input (and might span several lines).
+
+The validate subcommand checks the whole definition without rendering it, listing
+each error with its location as INPUT:LINE:COLUMN: JSON-POINTER: MESSAGE. Columns with
+missing (null) values are listed as warnings, which do not make the definition invalid.
+With --json, a report {\"valid\": bool, \"errors\": [...], \"warnings\": [...]} is written
+instead, each entry with \"pointer\", \"line\", \"column\", \"message\" and \"severity\".
+
+The watch subcommand renders INPUT (accepting the same options as render) and renders
+it again whenever it (or the --theme definition) is modified, until interrupted.
+Errors are printed without exiting. When writing to a terminal, the screen is cleared
+before each update.
+
+The batch subcommand renders many definitions in a single process, using JOBS threads
+(the number of processors by default). INPUT holds JSON Lines, each an object
+{\"spec\": definition, \"output\": path, \"format\": optional format}, and output paths are
+relative to the --output directory (if informed). If a directory DIR is informed instead,
+each DIR/NAME.json is rendered to NAME.svg (or the extension of --format) at the
+--output directory (DIR by default). A summary is printed when all are done, and
+the exit status is 1 if any definition failed.
+
+The session subcommand keeps panels in memory, reading one JSON request per line
+from standard input and writing one JSON response per line to standard output:
+    {\"op\": \"create\", \"spec\": definition, \"panel\": optional name}
+    {\"op\": \"update\", \"panel\": name, \"layout\": {...}, \"design\": {...},
+     \"plot\": index, \"x\": {...}, \"y\": {...}, \"mapping\": index, \"set\": {...}}
+    {\"op\": \"data\", \"panel\": name, \"plot\": index, \"mapping\": index,
+     \"columns\": [[...], ...], \"text\": [...]}
+    {\"op\": \"render\", \"panel\": name, \"format\": format, \"output\": optional path}
+    {\"op\": \"close\", \"panel\": name}
+    {\"op\": \"quit\"}
+Responses carry \"ok\" and either the result fields or \"error\", and echo the
+\"id\" of the request (if any). Binary renders are base64-encoded.
+
+The serve subcommand starts an HTTP/1.1 server. Definitions posted to /render are
+answered with SVG, PNG or PDF, chosen by the format query parameter
+(/render?format=png) or the Accept header (SVG by default). The width and height
+query parameters override the panel size. GET /health answers {\"status\": \"ok\"}.
+SERVER OPTIONS:
+    --host <ADDR>           Address to listen at (127.0.0.1 by default).
+    -p, --port <PORT>       Port to listen at (8080 by default).
+    --max-body <BYTES>      Largest accepted definition (10 MiB by default).
+    --timeout <SECS>        Rendering timeout (30 s by default). Renders still run
+                            to completion after it expires.
+    --max-concurrent <N>    Renders handled at the same time (the number of
+                            processors by default). Others receive 503.
+    --max-connections <N>   Connections open at the same time (256 by default).
+                            Others receive 503.
+    --max-pixels <N>        Largest panel area, in pixels (4096x4096 by default).
+                            Larger panels receive 413.
+
+OPTIONS:
+    -o, --output <PATH>     Write to PATH instead of standard output. The format is
+                            given by the extension (svg, png, eps, pdf, html, txt)
+                            unless --format is informed.
+    -f, --format <FORMAT>   Output format:
+                              svg          SVG document
+                              png, pdf, eps
+                                           Raster and vector exports (require cairo)
+                              html         <img> tag with a base64-encoded PNG (or an
+                                           inline SVG without cairo)
+                              interactive  Self-contained HTML page with tooltips,
+                                           legend and zoom
+                              terminal     Unicode braille drawing with ANSI colors
+                              sixel, kitty Inline images for terminals supporting
+                                           these graphics protocols (require cairo)
+                              inline       sixel or kitty, detected from the terminal
+                            Defaults to inline when writing to a terminal that supports
+                            images, and html otherwise.
+    -W, --width <PX>        Overrides the panel width.
+    -H, --height <PX>       Overrides the panel height.
+    -t, --theme <THEME>     Overrides the panel design: light, dark, or the path of a
+                            JSON design definition.
+    -h, --help              Print this message.
+    -V, --version           Print the version.
+
+DEFINITIONS:
+    The input is a panel, a single plot or a single mapping:
+
+    Panel    {\"plots\": [Plot, ...], \"design\": Design, \"layout\": Layout,
+              \"title\": String, \"tags\": bool}
+             Up to four plots, arranged by layout.split.
+    Plot     {\"mappings\": [Mapping, ...], \"x\": Scale, \"y\": Scale,
+              \"annotations\": [Annotation, ...], \"title\", \"subtitle\", \"caption\",
+              \"coords\": \"cartesian\" | \"polar\"}
+    Mapping  {\"kind\": \"line\" | \"scatter\" | \"area\" | \"bar\" | \"text\" | \"interval\" | \"wedge\",
+              \"map\": {\"x\": [...], \"y\": [...], \"z\": [...], \"text\": [...]},
+              \"color\", \"width\", \"spacing\", \"radius\", \"font\", ...}
+             Numeric columns accept null as missing values.
+    Scale    {\"label\", \"from\", \"to\", \"precision\", \"intervals\", \"log\", \"invert\",
+              \"offset\", \"adjust\": \"tight\" | \"round\" | \"off\", \"guide\"}
+    Annotation
+             {\"kind\": \"hline\" | \"vline\" | \"hspan\" | \"vspan\" | \"rect\" | \"arrow\",
+              \"x\", \"y\", \"x_end\", \"y_end\", \"text\", \"color\", ...}
+    Design   {\"bgcolor\", \"fgcolor\", \"width\", \"font\", \"panel_bgcolor\",
+              \"plot_bgcolor\", \"transparent\"}
+             Colors accept CSS syntax (#rrggbb, rgb(), hsl() and color names).
+    Layout   {\"width\", \"height\", \"hratio\", \"vratio\", \"split\",
+              \"unit\": \"px\" | \"mm\" | \"in\" | \"pt\", \"dpi\"}
+
+EXIT STATUS:
+    0 on success, 1 for invalid definitions or rendering errors, 2 for invalid
+    command-line arguments and 3 for errors reading the input or writing the output.
+";
+
+/// Errors reported by the command line, each with its own exit status.
+#[derive(Debug)]
+enum CliError {
+
+    // Invalid command-line arguments.
+    Usage(String),
+
+    // Invalid definition, or error while rendering it.
+    Spec(String),
+
+    // Error while reading the input or writing the output.
+    Io(String),
+
+    // Number of errors found by the validate subcommand (already reported).
+    Invalid(usize)
+}
+
+impl CliError {
+
+    // Message without the usage hint, for errors reported per definition or request.
+    fn message(self) -> String {
+        match self {
+            CliError::Usage(msg) | CliError::Spec(msg) | CliError::Io(msg) => msg,
+            other => format!("{}", other)
+        }
+    }
+
+    fn exit_code(&self) -> u8 {
+        match self {
+            CliError::Spec(_) | CliError::Invalid(_) => 1,
+            CliError::Usage(_) => 2,
+            CliError::Io(_) => 3
+        }
+    }
+
+}
+
+impl fmt::Display for CliError {
+
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CliError::Usage(msg) => write!(f, "{}\nTry 'papyri --help' for more information.", msg),
+            CliError::Spec(msg) | CliError::Io(msg) => write!(f, "{}", msg),
+            CliError::Invalid(1) => write!(f, "1 error found"),
+            CliError::Invalid(n) => write!(f, "{} errors found", n)
+        }
+    }
+
+}
+
+#[derive(Debug, Default)]
+struct RenderArgs {
+    input : Option<String>,
+    output : Option<String>,
+    format : Option<String>,
+    width : Option<u32>,
+    height : Option<u32>,
+    theme : Option<String>
+}
+
+// Splits --name=value arguments, and returns the value of options that take one.
+fn option_value(
+    name : &str,
+    inline : Option<String>,
+    args : &mut impl Iterator<Item=String>
+) -> Result<String, CliError> {
+    inline.or_else(|| args.next() )
+        .ok_or_else(|| CliError::Usage(format!("Missing value for {}", name)) )
+}
+
+fn parse_size(name : &str, value : &str) -> Result<u32, CliError> {
+    match value.parse::<u32>() {
+        Ok(v) if v > 0 => Ok(v),
+        _ => Err(CliError::Usage(format!("Invalid {}: {} (expected a positive integer)", name, value)))
+    }
+}
+
+fn parse_render_args(args : impl IntoIterator<Item=String>) -> Result<RenderArgs, CliError> {
+    let mut parsed = RenderArgs::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let (name, inline) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name.to_string(), Some(value.to_string())),
+            _ => (arg.clone(), None)
+        };
+        match &name[..] {
+            "-o" | "--output" => parsed.output = Some(option_value(&name, inline, &mut args)?),
+            "-f" | "--format" => parsed.format = Some(option_value(&name, inline, &mut args)?),
+            "-t" | "--theme" => parsed.theme = Some(option_value(&name, inline, &mut args)?),
+            "-W" | "--width" => parsed.width = Some(parse_size("width", &option_value(&name, inline, &mut args)?)?),
+            "-H" | "--height" => parsed.height = Some(parse_size("height", &option_value(&name, inline, &mut args)?)?),
+            "-" => parsed.input = Some(arg),
+            other if other.starts_with('-') => {
+                return Err(CliError::Usage(format!("Unknown option: {}", other)));
+            },
+            _ => {
+                if parsed.input.is_some() {
+                    return Err(CliError::Usage(format!("Unexpected argument: {}", arg)));
+                }
+                parsed.input = Some(arg);
+            }
+        }
+    }
+    Ok(parsed)
+}
+
+fn read_input(input : Option<&str>) -> Result<String, CliError> {
+    match input {
+        None | Some("-") => {
+            let mut content = String::new();
+            io::stdin().read_to_string(&mut content)
+                .map_err(|e| CliError::Io(format!("Unable to read standard input: {}", e)) )?;
+            Ok(content)
+        },
+        Some(path) => {
+            fs::read_to_string(path)
+                .map_err(|e| CliError::Io(format!("Unable to read {}: {}", path, e)) )
+        }
+    }
+}
+
+fn load_theme(theme : &str) -> Result<papyri::model::Design, CliError> {
+    let design = match theme {
+        "light" => papyri::model::Design::default(),
+        "dark" => papyri::model::Design::default_dark(),
+        path => {
+            let content = fs::read_to_string(path)
+                .map_err(|e| CliError::Io(format!("Unable to read theme {}: {}", path, e)) )?;
+            serde_json::from_str(&content)
+                .map_err(|e| CliError::Spec(format!("Invalid theme {}: {}", path, e)) )?
+        }
+    };
+    design.validate().map_err(|e| CliError::Spec(format!("Invalid theme {}: {}", theme, e)) )?;
+    Ok(design)
+}
+
+// Builds the panel from its JSON definition, applying the command-line overrides.
+fn load_panel(json : &str, args : &RenderArgs) -> Result<Panel, CliError> {
+    let panel = Panel::new_from_json(json).map_err(CliError::Spec)?;
+    let theme = args.theme.as_deref().map(load_theme).transpose()?;
+    apply_overrides(panel, args, theme)
+}
+
+fn apply_overrides(
+    mut panel : Panel,
+    args : &RenderArgs,
+    theme : Option<papyri::model::Design>
+) -> Result<Panel, CliError> {
+    if args.width.is_some() || args.height.is_some() {
+        let (w, h) = panel.get_dimensions();
+        panel = panel.dimensions(args.width.unwrap_or(w as u32), args.height.unwrap_or(h as u32));
+    }
+    if let Some(design) = theme {
+        panel.set_design(design).map_err(|e| CliError::Spec(format!("{}", e)) )?;
+    }
+    Ok(panel)
+}
+
+fn format_from_extension(path : &str) -> Result<&'static str, CliError> {
+    match Path::new(path).extension().and_then(|e| e.to_str() ) {
+        Some("svg") => Ok("svg"),
+        Some("png") => Ok("png"),
+        Some("eps") => Ok("eps"),
+        Some("pdf") => Ok("pdf"),
+        Some("html") | Some("htm") => Ok("interactive"),
+        Some("txt") => Ok("terminal"),
+        Some(other) => Err(CliError::Usage(format!("Unknown output extension: {} (inform --format)", other))),
+        None => Err(CliError::Usage(format!("Output {} has no extension (inform --format)", path)))
+    }
+}
+
+#[cfg(feature="cairo-rs")]
+fn default_format() -> &'static str {
+    use std::io::IsTerminal;
+    if std::io::stdout().is_terminal() && papyri::render::InlineProtocol::detect().is_some() {
+        "inline"
+    } else {
+        "html"
+    }
+}
+
+#[cfg(not(feature="cairo-rs"))]
+fn default_format() -> &'static str {
+    "html"
+}
+
+// Uses the terminal size exported by the shell, if any, leaving a line for the prompt.
+fn terminal_size() -> (usize, usize) {
+    let cols = std::env::var("COLUMNS").ok().and_then(|c| c.parse().ok() ).unwrap_or(80);
+    let rows = std::env::var("LINES").ok().and_then(|r| r.parse::<usize>().ok() ).unwrap_or(24);
+    (cols, rows.saturating_sub(1).max(1))
+}
+
+fn render(panel : &mut Panel, format : &str) -> Result<Vec<u8>, CliError> {
+    let spec_err = |e : Box<dyn std::error::Error>| CliError::Spec(format!("{}", e));
+    let out = match format {
+        "svg" => panel.svg().map_err(spec_err)?.into_bytes(),
+        "interactive" => panel.html_interactive().map_err(spec_err)?.into_bytes(),
+        "terminal" => {
+            let (cols, rows) = terminal_size();
+            panel.to_terminal(cols, rows).map_err(spec_err)?.into_bytes()
+        },
+
+        // Without cairo, plots can only be rendered to SVG.
+        #[cfg(feature="cairo-rs")]
+        "html" => format!("{}\n", panel.html_img_tag().map_err(spec_err)?).into_bytes(),
+
+        #[cfg(not(feature="cairo-rs"))]
+        "html" => panel.svg().map_err(spec_err)?.into_bytes(),
+
+        #[cfg(feature="cairo-rs")]
+        "png" => panel.png().map_err(spec_err)?,
+
+        #[cfg(feature="cairo-rs")]
+        "pdf" => panel.pdf().map_err(spec_err)?,
+
+        #[cfg(feature="cairo-rs")]
+        "eps" => panel.eps().map_err(spec_err)?,
+
+        #[cfg(feature="cairo-rs")]
+        "sixel" | "kitty" => {
+            let protocol = format.parse().map_err(|_| CliError::Usage(format!("Invalid protocol: {}", format)) )?;
+            format!("{}\n", panel.inline_image(protocol).map_err(spec_err)?).into_bytes()
+        },
+
+        #[cfg(feature="cairo-rs")]
+        "inline" => {
+            let protocol = papyri::render::InlineProtocol::detect()
+                .ok_or_else(|| CliError::Usage(String::from("Terminal does not support inline images (use --format sixel or kitty)")) )?;
+            format!("{}\n", panel.inline_image(protocol).map_err(spec_err)?).into_bytes()
+        },
+
+        #[cfg(not(feature="cairo-rs"))]
+        "png" | "pdf" | "eps" | "sixel" | "kitty" | "inline" => {
+            return Err(CliError::Usage(format!("Format {} requires papyri to be compiled with cairo", format)));
+        },
+
+        other => {
+            return Err(CliError::Usage(format!("Invalid output format: {}", other)));
+        }
+    };
+    Ok(out)
+}
+
+// The format informed by --format takes precedence over the extension of the output.
+fn output_format(args : &RenderArgs) -> Result<String, CliError> {
+    match (&args.format, &args.output) {
+        (Some(format), _) => Ok(format.clone()),
+        (None, Some(output)) => Ok(format_from_extension(output)?.to_string()),
+        (None, None) => Ok(default_format().to_string())
+    }
+}
+
+fn write_output(output : Option<&str>, out : &[u8]) -> Result<(), CliError> {
+    match output {
+        Some(path) => {
+            fs::write(path, out).map_err(|e| CliError::Io(format!("Unable to write {}: {}", path, e)) )
+        },
+        None => {
+            let mut stdout = io::stdout().lock();
+            stdout.write_all(out)
+                .and_then(|_| stdout.flush() )
+                .map_err(|e| CliError::Io(format!("Unable to write to standard output: {}", e)) )
+        }
+    }
+}
+
+fn run_render(args : RenderArgs) -> Result<(), CliError> {
+    let format = output_format(&args)?;
+    let json = read_input(args.input.as_deref())?;
+    let mut panel = load_panel(&json, &args)?;
+    let out = render(&mut panel, &format)?;
+    write_output(args.output.as_deref(), &out)
+}
+
+fn run_validate(args : Vec<String>) -> Result<(), CliError> {
+    let mut input = None;
+    let mut json = false;
+    for arg in args {
+        match &arg[..] {
+            "--json" => json = true,
+            "-" => input = Some(arg),
+            other if other.starts_with('-') => {
+                return Err(CliError::Usage(format!("Unknown option: {}", other)));
+            },
+            _ if input.is_some() => {
+                return Err(CliError::Usage(format!("Unexpected argument: {}", arg)));
+            },
+            _ => input = Some(arg)
+        }
+    }
+    let content = read_input(input.as_deref())?;
+    let errs = papyri::validate::validate_json(&content);
+    let name = match input.as_deref() {
+        None | Some("-") => "<stdin>",
+        Some(path) => path
+    };
+    let (errs, warnings) : (Vec<_>, Vec<_>) = errs.into_iter().partition(|e| e.is_error() );
+    let out = if json {
+        let report = serde_json::json!({ "valid" : errs.is_empty(), "errors" : errs, "warnings" : warnings });
+        format!("{}\n", report)
+    } else {
+        let mut out : String = errs.iter().chain(warnings.iter()).map(|e| format!("{}:{}\n", name, e) ).collect();
+        if errs.is_empty() {
+            out += &format!("{}: valid\n", name);
+        }
+        out
+    };
+    io::stdout().lock().write_all(out.as_bytes())
+        .map_err(|e| CliError::Io(format!("Unable to write to standard output: {}", e)) )?;
+    if errs.is_empty() {
+        Ok(())
+    } else {
+        Err(CliError::Invalid(errs.len()))
+    }
+}
+
+fn run(mut args : Vec<String>) -> Result<(), CliError> {
+    if args.iter().any(|a| a == "-h" || a == "--help" ) {
+        print!("{}", HELP);
         return Ok(());
-        // }
-        // }
     }
+    if args.iter().any(|a| a == "-V" || a == "--version" ) {
+        println!("papyri {}", env!("CARGO_PKG_VERSION"));
+        return Ok(());
+    }
+
+    match args.first().map(|a| &a[..] ) {
+        Some("validate") => {
+            args.remove(0);
+            run_validate(args)
+        },
+
+        Some("batch") => {
+            args.remove(0);
+            batch::run_batch(args)
+        },
+
+        Some("serve") => {
+            args.remove(0);
+            serve::run_serve(args)
+        },
+
+        Some("session") => {
+            args.remove(0);
+            session::run_session(args)
+        },
+
+        Some("watch") => {
+            args.remove(0);
+            watch::run_watch(parse_render_args(args)?)
+        },
 
-    #[cfg(not(feature="gdk4"))]
-    #[cfg(not(feature="gdk-pixbuf"))]
-    #[cfg(not(feature="cairo-rs"))]
-    Err(format!("Crate not compiled with features 'gdk4, gdk-pixbuf or cairo-rs'"))
+        // The render subcommand is the default.
+        Some("render") => {
+            args.remove(0);
+            run_render(parse_render_args(args)?)
+        },
+        _ => run_render(parse_render_args(args)?)
+    }
+}
+
+fn main() -> ExitCode {
+    match run(std::env::args().skip(1).collect()) {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("papyri: {}", e);
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
+
+#[cfg(test)]
+fn args(args : &[&str]) -> Vec<String> {
+    args.iter().map(|a| a.to_string() ).collect()
 }
 
+#[test]
+fn render_arguments() {
+    let parsed = parse_render_args(args(&["plot.json", "-o", "plot.svg", "--format=png", "-W", "400", "--height=300", "-t", "dark"])).unwrap();
+    assert_eq!(parsed.input.as_deref(), Some("plot.json"));
+    assert_eq!(parsed.output.as_deref(), Some("plot.svg"));
+    assert_eq!(parsed.format.as_deref(), Some("png"));
+    assert_eq!((parsed.width, parsed.height), (Some(400), Some(300)));
+    assert_eq!(parsed.theme.as_deref(), Some("dark"));
+    assert_eq!(output_format(&parsed).unwrap(), "png");
 
+    let parsed = parse_render_args(args(&["-", "--output", "plot.html"])).unwrap();
+    assert_eq!(parsed.input.as_deref(), Some("-"));
+    assert_eq!(output_format(&parsed).unwrap(), "interactive");
+
+    for invalid in [&["-W", "0"][..], &["--width=wide"], &["-o"], &["--bogus"], &["a.json", "b.json"]] {
+        let err = parse_render_args(args(invalid)).unwrap_err();
+        assert_eq!(err.exit_code(), 2, "{:?}", invalid);
+    }
+    let parsed = parse_render_args(args(&["-o", "plot.gif"])).unwrap();
+    assert_eq!(output_format(&parsed).unwrap_err().exit_code(), 2);
+}
+
+#[test]
+fn exit_codes() {
+    let dir = std::env::temp_dir().join(format!("papyri-cli-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = |name : &str| dir.join(name).to_str().unwrap().to_string();
+    fs::write(path("valid.json"), r#"{"kind":"line","map":{"x":[0,1],"y":[0,1]}}"#).unwrap();
+    fs::write(path("invalid.json"), r#"{"kind":"line","map":{"x":[0,1],"y":"a"}}"#).unwrap();
+    let code = |a : &[&str]| run(args(a)).err().map(|e| e.exit_code() ).unwrap_or(0);
+
+    assert_eq!(code(&["render", &path("valid.json"), "-o", &path("out.svg")]), 0);
+    assert!(fs::read_to_string(path("out.svg")).unwrap().contains("<svg"));
+    assert_eq!(code(&[&path("valid.json"), "-o", &path("out.svg")]), 0);
+    assert_eq!(code(&["validate", &path("valid.json")]), 0);
+    assert_eq!(code(&["validate", &path("invalid.json")]), 1);
+    assert_eq!(code(&[&path("invalid.json"), "-o", &path("out.svg")]), 1);
+    assert_eq!(code(&[&path("valid.json"), "--theme", &path("invalid.json"), "-o", &path("out.svg")]), 1);
+    assert_eq!(code(&["validate", "--strict", &path("valid.json")]), 2);
+    assert_eq!(code(&[&path("valid.json"), "-f", "gif"]), 2);
+    assert_eq!(code(&[&path("missing.json"), "-o", &path("out.svg")]), 3);
+    assert_eq!(code(&[&path("valid.json"), "-o", &path("missing/out.svg")]), 3);
+    assert_eq!(code(&["--version"]), 0);
+    fs::remove_dir_all(&dir).unwrap();
+}
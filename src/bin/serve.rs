@@ -0,0 +1,554 @@
+/*Copyright (c) 2022 Diego da Silva Lima. All rights reserved.
+
+This work is licensed under the terms of the MIT license.  
+For a copy, see <https://opensource.org/licenses/MIT>.*/
+
+/*! A small HTTP/1.1 server, which renders definitions posted to /render and reports its
+status at /health. Each connection serves a single request. !*/
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+use papyri::render::Panel;
+use crate::CliError;
+
+// Limit for the request line and headers.
+const MAX_HEADER : usize = 16 * 1024;
+
+// Unread request data discarded before closing connections.
+const DRAIN_LIMIT : u64 = 1024 * 1024;
+
+// Time clients have to send their whole request.
+const READ_TIMEOUT : Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone)]
+struct Config {
+    host : String,
+    port : u16,
+    max_body : usize,
+    timeout : Duration,
+    max_concurrent : usize,
+    max_connections : usize,
+    max_pixels : u64
+}
+
+impl Default for Config {
+
+    fn default() -> Self {
+        Self {
+            host : String::from("127.0.0.1"),
+            port : 8080,
+            max_body : 10 * 1024 * 1024,
+            timeout : Duration::from_secs(30),
+            max_concurrent : thread::available_parallelism().map(|n| n.get() ).unwrap_or(1),
+            max_connections : 256,
+            max_pixels : 4096 * 4096
+        }
+    }
+
+}
+
+struct Response {
+    status : u16,
+    content_type : &'static str,
+    headers : Vec<(&'static str, String)>,
+    body : Vec<u8>
+}
+
+impl Response {
+
+    fn new(status : u16, content_type : &'static str, body : Vec<u8>) -> Self {
+        Self { status, content_type, headers : Vec::new(), body }
+    }
+
+    fn error(status : u16, msg : impl ToString) -> Self {
+        Self::new(status, "text/plain; charset=utf-8", format!("{}\n", msg.to_string()).into_bytes())
+    }
+
+    fn header(mut self, name : &'static str, value : impl ToString) -> Self {
+        self.headers.push((name, value.to_string()));
+        self
+    }
+
+    fn write_to(&self, stream : &mut impl Write) -> io::Result<()> {
+        let mut head = format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+            self.status,
+            reason(self.status),
+            self.content_type,
+            self.body.len()
+        );
+        for (name, value) in &self.headers {
+            head += &format!("{}: {}\r\n", name, value);
+        }
+        head += "\r\n";
+        stream.write_all(head.as_bytes())?;
+        stream.write_all(&self.body)?;
+        stream.flush()
+    }
+
+}
+
+fn reason(status : u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        406 => "Not Acceptable",
+        408 => "Request Timeout",
+        411 => "Length Required",
+        413 => "Payload Too Large",
+        422 => "Unprocessable Entity",
+        431 => "Request Header Fields Too Large",
+        500 => "Internal Server Error",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        _ => "Unknown"
+    }
+}
+
+struct Request {
+    method : String,
+    path : String,
+    query : HashMap<String, String>,
+
+    // Header names are lower-cased.
+    headers : HashMap<String, String>,
+
+    body : Vec<u8>
+}
+
+// Reads from the connection until a deadline, so that clients sending their request slowly
+// cannot keep it open indefinitely.
+struct Deadline {
+    stream : TcpStream,
+    until : Instant
+}
+
+impl Read for Deadline {
+
+    fn read(&mut self, buf : &mut [u8]) -> io::Result<usize> {
+        let left = self.until.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "deadline expired"));
+        }
+        self.stream.set_read_timeout(Some(left))?;
+        self.stream.read(buf)
+    }
+
+}
+
+fn read_request(stream : &mut impl BufRead, config : &Config) -> Result<Request, Response> {
+    let io_err = |e : io::Error| match e.kind() {
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => Response::error(408, "Timed out reading the request"),
+        _ => Response::error(400, format!("Unable to read the request: {}", e))
+    };
+
+    let mut lines = Vec::new();
+    let mut size = 0;
+    loop {
+        let mut line = String::new();
+        let n = stream.by_ref().take((MAX_HEADER - size + 1) as u64).read_line(&mut line).map_err(io_err)?;
+        size += n;
+        if size > MAX_HEADER {
+            return Err(Response::error(431, "Request headers are too large"));
+        }
+        if n == 0 {
+            return Err(Response::error(400, "Incomplete request"));
+        }
+        let line = line.trim_end_matches(&['\r', '\n'][..]).to_string();
+        if line.is_empty() {
+            break;
+        }
+        lines.push(line);
+    }
+
+    let mut request_line = lines.first().map(|l| l.split(' ') ).ok_or_else(|| Response::error(400, "Missing request line") )?;
+    let (method, target) = match (request_line.next(), request_line.next(), request_line.next()) {
+        (Some(m), Some(t), Some(v)) if v.starts_with("HTTP/1.") => (m.to_string(), t.to_string()),
+        _ => return Err(Response::error(400, "Invalid request line"))
+    };
+    let mut headers = HashMap::new();
+    for line in &lines[1..] {
+        let (name, value) = line.split_once(':').ok_or_else(|| Response::error(400, "Invalid header") )?;
+        headers.insert(name.trim().to_lowercase(), value.trim().to_string());
+    }
+
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (path.to_string(), query),
+        None => (target.clone(), "")
+    };
+    let query = query.split('&')
+        .filter(|p| !p.is_empty() )
+        .map(|p| match p.split_once('=') {
+            Some((k, v)) => (percent_decode(k), percent_decode(v)),
+            None => (percent_decode(p), String::new())
+        })
+        .collect();
+
+    let mut body = Vec::new();
+    if method == "POST" {
+        if headers.contains_key("transfer-encoding") {
+            return Err(Response::error(411, "Chunked requests are not supported (inform Content-Length)"));
+        }
+        let len : usize = headers.get("content-length")
+            .ok_or_else(|| Response::error(411, "Missing Content-Length") )?
+            .parse()
+            .map_err(|_| Response::error(400, "Invalid Content-Length") )?;
+        if len > config.max_body {
+            return Err(Response::error(413, format!("Request body exceeds the limit of {} bytes", config.max_body)));
+        }
+        body.resize(len, 0);
+        stream.read_exact(&mut body).map_err(io_err)?;
+    }
+    Ok(Request { method, path, query, headers, body })
+}
+
+// Decodes a query string component, in which + stands for a space and %XX for a byte.
+// Invalid escapes are kept as they are.
+fn percent_decode(s : &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i+1..i+3)
+            .and_then(|h| std::str::from_utf8(h).ok() )
+            .and_then(|h| u8::from_str_radix(h, 16).ok() );
+        match (bytes[i], hex) {
+            (b'%', Some(b)) => {
+                out.push(b);
+                i += 3;
+                continue;
+            },
+            (b'+', _) => out.push(b' '),
+            (b, _) => out.push(b)
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+// The format query parameter (a format name or media type) takes precedence over the Accept
+// header. Without any of them, SVG is returned.
+fn negotiate(req : &Request) -> Result<(&'static str, &'static str), Response> {
+    let by_name = |name : &str| match name.trim_start_matches("image/").trim_start_matches("application/") {
+        "svg" | "svg+xml" => Some(("svg", "image/svg+xml")),
+        "png" => Some(("png", "image/png")),
+        "pdf" => Some(("pdf", "application/pdf")),
+        _ => None
+    };
+    if let Some(format) = req.query.get("format") {
+        return by_name(format).ok_or_else(|| Response::error(406, format!("Unsupported format: {} (expected svg, png or pdf)", format)) );
+    }
+    let accept = match req.headers.get("accept") {
+        Some(accept) => accept,
+        None => return Ok(("svg", "image/svg+xml"))
+    };
+    for media in accept.split(',') {
+        let media = media.split(';').next().unwrap_or("").trim();
+        let found = match media {
+            "image/svg+xml" => by_name("svg"),
+            "image/png" => by_name("png"),
+            "application/pdf" => by_name("pdf"),
+            "*/*" | "image/*" => by_name("svg"),
+            _ => None
+        };
+        if let Some(found) = found {
+            return Ok(found);
+        }
+    }
+    Err(Response::error(406, "None of the accepted media types is supported (image/svg+xml, image/png or application/pdf)"))
+}
+
+fn size_param(req : &Request, name : &str) -> Result<Option<u32>, Response> {
+    match req.query.get(name) {
+        Some(v) => match v.parse::<u32>() {
+            Ok(v) if v > 0 => Ok(Some(v)),
+            _ => Err(Response::error(400, format!("Invalid {}: {}", name, v)))
+        },
+        None => Ok(None)
+    }
+}
+
+// Decrements the number of active requests (or open connections) when the last clone is dropped.
+struct Slot(Arc<AtomicUsize>);
+
+impl Slot {
+
+    // Returns None when max requests are already active.
+    fn acquire(active : &Arc<AtomicUsize>, max : usize) -> Option<Arc<Slot>> {
+        if active.fetch_add(1, Ordering::SeqCst) >= max {
+            active.fetch_sub(1, Ordering::SeqCst);
+            None
+        } else {
+            Some(Arc::new(Slot(active.clone())))
+        }
+    }
+
+}
+
+impl Drop for Slot {
+
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+
+}
+
+fn render(req : Request, config : &Config, slot : Arc<Slot>) -> Response {
+    let (format, content_type) = match negotiate(&req) {
+        Ok(f) => f,
+        Err(resp) => return resp
+    };
+    let (width, height) = match (size_param(&req, "width"), size_param(&req, "height")) {
+        (Ok(w), Ok(h)) => (w, h),
+        (Err(resp), _) | (_, Err(resp)) => return resp
+    };
+    let json = match String::from_utf8(req.body) {
+        Ok(json) => json,
+        Err(_) => return Response::error(400, "Request body is not valid UTF-8")
+    };
+
+    // Rendering happens at a separate thread, so that the response can be sent when the timeout
+    // expires. Rendering cannot be interrupted, so the thread still runs to completion after a
+    // timeout, and keeps its slot (counting towards --max-concurrent) until it finishes.
+    let (tx, rx) = mpsc::channel();
+    let max_pixels = config.max_pixels;
+    thread::spawn(move || {
+        let res = Panel::new_from_json(&json)
+            .map_err(|e| Response::error(422, e) )
+            .and_then(|mut panel| {
+                if width.is_some() || height.is_some() {
+                    let (w, h) = panel.get_dimensions();
+                    panel = panel.dimensions(width.unwrap_or(w as u32), height.unwrap_or(h as u32));
+                }
+
+                // Checked after the overrides, since the definition might also set a large layout.
+                let (w, h) = panel.raster_dimensions();
+                if w as u64 * h as u64 > max_pixels {
+                    return Err(Response::error(413, format!("Panel of {}x{} pixels exceeds the limit of {} pixels", w, h, max_pixels)));
+                }
+                crate::render(&mut panel, format).map_err(|e| match e {
+                    CliError::Spec(msg) => Response::error(422, msg),
+                    CliError::Usage(msg) => Response::error(406, msg),
+                    e => Response::error(500, e.message())
+                })
+            });
+
+        // Released before answering, so that the slot is free once the response is sent.
+        drop(slot);
+        let _ = tx.send(res);
+    });
+    match rx.recv_timeout(config.timeout) {
+        Ok(Ok(out)) => Response::new(200, content_type, out),
+        Ok(Err(resp)) => resp,
+        Err(mpsc::RecvTimeoutError::Timeout) => Response::error(504, "Rendering timed out"),
+        Err(mpsc::RecvTimeoutError::Disconnected) => Response::error(500, "Rendering failed")
+    }
+}
+
+fn handle(stream : TcpStream, config : &Config, active : &Arc<AtomicUsize>) -> io::Result<()> {
+    let start = Instant::now();
+    let mut reader = BufReader::new(Deadline { stream : stream.try_clone()?, until : start + READ_TIMEOUT });
+    let (line, resp) = match read_request(&mut reader, config) {
+        Ok(req) => {
+            let line = format!("{} {}", req.method, req.path);
+            let resp = match (&req.method[..], &req.path[..]) {
+                ("GET", "/health") | ("HEAD", "/health") => {
+                    Response::new(200, "application/json", b"{\"status\":\"ok\"}\n".to_vec())
+                },
+
+                // Only requests already read take a slot, so that slow clients do not hold them.
+                ("POST", "/render") | ("POST", "/") => match Slot::acquire(active, config.max_concurrent) {
+                    Some(slot) => render(req, config, slot),
+                    None => Response::error(503, "Too many concurrent requests").header("Retry-After", 1)
+                },
+                (_, "/health") => Response::error(405, "Method not allowed").header("Allow", "GET, HEAD"),
+                (_, "/render") | (_, "/") => Response::error(405, "Method not allowed").header("Allow", "POST"),
+                _ => Response::error(404, "Not found")
+            };
+            (line, resp)
+        },
+        Err(resp) => (String::from("-"), resp)
+    };
+    eprintln!("papyri: {} {} ({} ms)", line, resp.status, start.elapsed().as_millis());
+    let mut stream = stream;
+    resp.write_to(&mut stream)?;
+
+    // Unread input (such as bodies of rejected requests) would make the connection be reset
+    // before the client reads the response.
+    stream.shutdown(Shutdown::Write)?;
+    reader.get_mut().until = Instant::now() + Duration::from_secs(1);
+    let _ = io::copy(&mut reader.take(DRAIN_LIMIT), &mut io::sink());
+    Ok(())
+}
+
+// Accepts connections until the listener fails.
+fn serve(listener : TcpListener, config : Arc<Config>) -> io::Result<()> {
+    let active = Arc::new(AtomicUsize::new(0));
+    let connections = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("papyri: Unable to accept connection: {}", e);
+                continue;
+            }
+        };
+
+        // Connections are answered by their own threads, limited so that idle clients cannot
+        // make the server spawn threads indefinitely.
+        let connection = match Slot::acquire(&connections, config.max_connections) {
+            Some(connection) => connection,
+            None => {
+                let mut stream = stream;
+                let _ = stream.set_write_timeout(Some(Duration::from_secs(1)));
+                let resp = Response::error(503, "Too many open connections").header("Retry-After", 1);
+                let _ = resp.write_to(&mut stream);
+                continue;
+            }
+        };
+        let config = config.clone();
+        let active = active.clone();
+        thread::spawn(move || {
+            let _connection = connection;
+            if let Err(e) = handle(stream, &config, &active) {
+                eprintln!("papyri: Unable to answer request: {}", e);
+            }
+        });
+    }
+    Ok(())
+}
+
+fn parse_config(args : Vec<String>) -> Result<Config, CliError> {
+    let mut config = Config::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let (name, inline) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name.to_string(), Some(value.to_string())),
+            _ => (arg.clone(), None)
+        };
+        let mut value = || crate::option_value(&name, inline.clone(), &mut args);
+        match &name[..] {
+            "--host" => config.host = value()?,
+            "-p" | "--port" => {
+                let v = value()?;
+                config.port = v.parse().map_err(|_| CliError::Usage(format!("Invalid port: {}", v)) )?;
+            },
+            "--max-body" => config.max_body = crate::parse_size("body size limit", &value()?)? as usize,
+            "--timeout" => config.timeout = Duration::from_secs(crate::parse_size("timeout", &value()?)? as u64),
+            "--max-concurrent" => config.max_concurrent = crate::parse_size("concurrency limit", &value()?)? as usize,
+            "--max-connections" => config.max_connections = crate::parse_size("connection limit", &value()?)? as usize,
+            "--max-pixels" => config.max_pixels = crate::parse_size("pixel limit", &value()?)? as u64,
+            other => return Err(CliError::Usage(format!("Unknown option: {}", other)))
+        }
+    }
+    Ok(config)
+}
+
+/// Listens for rendering requests until the process is interrupted.
+pub fn run_serve(args : Vec<String>) -> Result<(), CliError> {
+    let config = parse_config(args)?;
+    let listener = TcpListener::bind((&config.host[..], config.port))
+        .map_err(|e| CliError::Io(format!("Unable to listen at {}:{}: {}", config.host, config.port, e)) )?;
+    eprintln!("papyri: listening at http://{}", listener.local_addr().map(|a| a.to_string() ).unwrap_or_default());
+    serve(listener, Arc::new(config)).map_err(|e| CliError::Io(format!("{}", e)) )
+}
+
+#[test]
+fn serve_requests() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let config = Config { max_body : 4096, max_concurrent : 1, max_pixels : 1_000_000, ..Config::default() };
+    thread::spawn(move || serve(listener, Arc::new(config)) );
+
+    // A client that did not finish its request does not hold the only slot.
+    let mut stalled = TcpStream::connect(addr).unwrap();
+    stalled.write_all(b"POST /render HTTP/1.1\r\n").unwrap();
+
+    let request = |req : String| {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(req.as_bytes()).unwrap();
+        let mut resp = String::new();
+        stream.read_to_string(&mut resp).unwrap();
+        resp
+    };
+    assert!(request(String::from("GET /health HTTP/1.1\r\nHost: localhost\r\n\r\n")).starts_with("HTTP/1.1 200 OK"));
+    assert!(request(String::from("GET /nothing HTTP/1.1\r\n\r\n")).starts_with("HTTP/1.1 404"));
+
+    let spec = r#"{"kind":"line","map":{"x":[0,1,2],"y":[0,1,4]}}"#;
+    let post = |target : &str, headers : &str, body : &str| {
+        request(format!("POST {} HTTP/1.1\r\nContent-Length: {}\r\n{}\r\n{}", target, body.len(), headers, body))
+    };
+    let resp = post("/render", "Accept: image/svg+xml\r\n", spec);
+    assert!(resp.starts_with("HTTP/1.1 200 OK") && resp.contains("Content-Type: image/svg+xml") && resp.contains("<svg"));
+    assert!(post("/render?format=gif", "", spec).starts_with("HTTP/1.1 406"));
+    assert!(post("/render?format=image%2Fsvg%2Bxml", "", spec).starts_with("HTTP/1.1 200"));
+    assert!(post("/render?format=svg%2bxml&width=+800", "", spec).starts_with("HTTP/1.1 400"));
+    assert!(post("/render", "", "{\"plots\": 1}").starts_with("HTTP/1.1 422"));
+    assert!(post("/render", "", &" ".repeat(5000)).starts_with("HTTP/1.1 413"));
+    assert!(post("/render?width=800&height=600", "", spec).starts_with("HTTP/1.1 200"));
+    assert!(post("/render?width=2000&height=600", "", spec).starts_with("HTTP/1.1 413"));
+    assert!(post("/render?width=4294967295", "", spec).starts_with("HTTP/1.1 413"));
+    let large = r#"{
+        "plots":[{
+            "mappings":[{"kind":"line","map":{"x":[0,1],"y":[0,1]}}],
+            "x":{"label":"","from":0,"to":1,"adjust":"off"},
+            "y":{"label":"","from":0,"to":1,"adjust":"off"}
+        }],
+        "layout":{"width":4000,"height":4000,"hratio":0.5,"vratio":0.5,"split":"unique"}
+    }"#;
+    assert!(post("/render", "", large).starts_with("HTTP/1.1 413"));
+    drop(stalled);
+}
+
+#[test]
+fn limit_connections() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let config = Config { max_connections : 1, ..Config::default() };
+    thread::spawn(move || serve(listener, Arc::new(config)) );
+
+    // The idle connection holds the only connection slot, so the next one is refused.
+    let mut idle = TcpStream::connect(addr).unwrap();
+    idle.write_all(b"GET /health HTTP/1.1\r\n").unwrap();
+    let mut stream = TcpStream::connect(addr).unwrap();
+    let mut resp = String::new();
+    stream.read_to_string(&mut resp).unwrap();
+    assert!(resp.starts_with("HTTP/1.1 503") && resp.contains("Retry-After: 1"));
+
+    // Once it is answered, its slot is released (shortly after the response is written).
+    idle.write_all(b"\r\n").unwrap();
+    let mut resp = String::new();
+    idle.read_to_string(&mut resp).unwrap();
+    assert!(resp.starts_with("HTTP/1.1 200"));
+    let health = || {
+        let mut stream = TcpStream::connect(addr).ok()?;
+        stream.write_all(b"GET /health HTTP/1.1\r\n\r\n").ok()?;
+        let mut resp = String::new();
+        stream.read_to_string(&mut resp).ok()?;
+        Some(resp)
+    };
+    let answered = (0..100).any(|_| match health() {
+        Some(resp) if resp.starts_with("HTTP/1.1 200") => true,
+        _ => {
+            thread::sleep(Duration::from_millis(10));
+            false
+        }
+    });
+    assert!(answered);
+}
+
+#[test]
+fn decode_query() {
+    assert_eq!(percent_decode("svg%2Bxml"), "svg+xml");
+    assert_eq!(percent_decode("dark+theme%21"), "dark theme!");
+    assert_eq!(percent_decode("100%"), "100%");
+    assert_eq!(percent_decode("%zz%C3%A9"), "%zzé");
+}
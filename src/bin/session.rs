@@ -0,0 +1,241 @@
+/*Copyright (c) 2022 Diego da Silva Lima. All rights reserved.
+
+This work is licensed under the terms of the MIT license.  
+For a copy, see <https://opensource.org/licenses/MIT>.*/
+
+/*! Persistent process mode, in which papyri reads one JSON request per line from the
+standard input and writes one JSON response per line to the standard output. Panels
+are kept between requests, so that clients can send incremental updates instead of
+whole definitions. !*/
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::panic::{self, AssertUnwindSafe};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use papyri::render::Panel;
+use crate::CliError;
+
+#[derive(Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase", deny_unknown_fields)]
+enum Request {
+
+    // Builds a panel from a definition, optionally naming it.
+    Create { #[serde(default)] id : Value, panel : Option<String>, spec : Value },
+
+    // Sets layout, design, scale or mapping properties (see render::Panel::update_from_json).
+    Update {
+        #[serde(default)] id : Value,
+        panel : String,
+        plot : Option<Value>,
+        mapping : Option<Value>,
+        layout : Option<Value>,
+        design : Option<Value>,
+        x : Option<Value>,
+        y : Option<Value>,
+        set : Option<Value>
+    },
+
+    // Replaces the data of a mapping.
+    Data {
+        #[serde(default)] id : Value,
+        panel : String,
+        plot : Option<usize>,
+        mapping : usize,
+        columns : Option<Vec<Vec<Option<f64>>>>,
+        text : Option<Vec<String>>
+    },
+
+    Render { #[serde(default)] id : Value, panel : String, format : Option<String>, output : Option<String> },
+
+    Close { #[serde(default)] id : Value, panel : String },
+
+    Quit { #[serde(default)] id : Value }
+}
+
+#[derive(Default)]
+struct Session {
+    panels : HashMap<String, Panel>,
+    next : usize
+}
+
+impl Session {
+
+    fn panel(&mut self, name : &str) -> Result<&mut Panel, String> {
+        self.panels.get_mut(name).ok_or_else(|| format!("Unknown panel: {}", name) )
+    }
+
+    // Answers a request, returning the response fields (other than id and ok).
+    fn handle(&mut self, req : Request) -> Result<Value, String> {
+        match req {
+            Request::Create { panel, spec, .. } => {
+                let created = Panel::new_from_json_value(spec)?;
+                let name = panel.unwrap_or_else(|| {
+                    self.next += 1;
+                    format!("panel{}", self.next)
+                });
+                self.panels.insert(name.clone(), created);
+                Ok(json!({ "panel" : name }))
+            },
+            Request::Update { panel, plot, mapping, layout, design, x, y, set, .. } => {
+                let fields = [("plot", plot), ("mapping", mapping), ("layout", layout), ("design", design), ("x", x), ("y", y), ("set", set)];
+                let update : serde_json::Map<String, Value> = fields.iter()
+                    .filter_map(|(name, v)| v.clone().map(|v| (name.to_string(), v) ) )
+                    .collect();
+                let n = self.panel(&panel)?.update_from_json(Value::Object(update))?;
+                Ok(json!({ "updated" : n }))
+            },
+            Request::Data { panel, plot, mapping, columns, text, .. } => {
+                let pn = self.panel(&panel)?;
+                let plot = plot.unwrap_or(0);
+                if let Some(cols) = columns {
+
+                    // Null entries are missing values, as in definitions.
+                    let cols : Vec<Vec<f64>> = cols.into_iter()
+                        .map(|c| c.into_iter().map(|v| v.unwrap_or(f64::NAN) ).collect() )
+                        .collect();
+                    pn.update_mapping(plot, &mapping.to_string(), &cols).map_err(|e| format!("{}", e) )?;
+                }
+                if let Some(text) = text {
+                    pn.update_mapping_text(plot, &mapping.to_string(), &text).map_err(|e| format!("{}", e) )?;
+                }
+                Ok(json!({ }))
+            },
+            Request::Render { panel, format, output, .. } => {
+                let pn = self.panel(&panel)?;
+                let format = match (&format, &output) {
+                    (Some(format), _) => format.clone(),
+                    (None, Some(output)) => crate::format_from_extension(output).map_err(CliError::message)?.to_string(),
+                    (None, None) => String::from("svg")
+                };
+                let out = crate::render(pn, &format).map_err(CliError::message)?;
+                match output {
+                    Some(path) => {
+                        std::fs::write(&path, &out).map_err(|e| format!("Unable to write {}: {}", path, e) )?;
+                        Ok(json!({ "format" : format, "output" : path }))
+                    },
+
+                    // Binary formats are base64-encoded.
+                    None => match String::from_utf8(out) {
+                        Ok(text) => Ok(json!({ "format" : format, "data" : text })),
+                        Err(e) => Ok(json!({ "format" : format, "encoding" : "base64", "data" : base64::encode(e.into_bytes()) }))
+                    }
+                }
+            },
+            Request::Close { panel, .. } => {
+                self.panels.remove(&panel).ok_or_else(|| format!("Unknown panel: {}", panel) )?;
+                Ok(json!({ }))
+            },
+            Request::Quit { .. } => Ok(json!({ }))
+        }
+    }
+
+}
+
+fn request_id(req : &Request) -> Value {
+    match req {
+        Request::Create { id, .. } | Request::Update { id, .. } | Request::Data { id, .. } |
+        Request::Render { id, .. } | Request::Close { id, .. } | Request::Quit { id } => id.clone()
+    }
+}
+
+// Answers a request line, returning the response and whether the session should end.
+fn respond(session : &mut Session, line : &str) -> (Value, bool) {
+    let (id, res, quit) = match serde_json::from_str::<Request>(line) {
+        Ok(req) => {
+            let id = request_id(&req);
+            let quit = matches!(req, Request::Quit { .. });
+
+            // A panic while handling a request should not end the session.
+            let res = panic::catch_unwind(AssertUnwindSafe(|| session.handle(req) ))
+                .unwrap_or_else(|_| Err(String::from("Internal error while handling the request")) );
+            (id, res, quit)
+        },
+        Err(e) => {
+
+            // Still echoes the id when the request is an object with an invalid operation.
+            let id = serde_json::from_str::<Value>(line).ok()
+                .and_then(|v| v.get("id").cloned() )
+                .unwrap_or(Value::Null);
+            (id, Err(format!("Invalid request: {}", e)), false)
+        }
+    };
+    let mut resp = match res {
+        Ok(Value::Object(fields)) => Value::Object(fields),
+        Ok(_) => json!({ }),
+        Err(e) => json!({ "error" : e })
+    };
+    resp["ok"] = Value::Bool(resp.get("error").is_none());
+    if !id.is_null() {
+        resp["id"] = id;
+    }
+    (resp, quit)
+}
+
+/// Answers requests until the standard input is closed or a quit request is received.
+pub fn run_session(args : Vec<String>) -> Result<(), CliError> {
+    if let Some(arg) = args.first() {
+        return Err(CliError::Usage(format!("Unexpected argument: {}", arg)));
+    }
+    let mut session = Session::default();
+    let stdin = io::stdin();
+    let mut stdout = io::stdout().lock();
+    for line in stdin.lock().lines() {
+        let line = line.map_err(|e| CliError::Io(format!("Unable to read standard input: {}", e)) )?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (resp, quit) = respond(&mut session, &line);
+        writeln!(stdout, "{}", resp)
+            .and_then(|_| stdout.flush() )
+            .map_err(|e| CliError::Io(format!("Unable to write to standard output: {}", e)) )?;
+        if quit {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn session_protocol() {
+    let mut session = Session::default();
+    let mut send = |line : &str| respond(&mut session, line);
+
+    let (resp, quit) = send(r#"{"op":"create","id":1,"spec":{"kind":"line","map":{"x":[0,1,2],"y":[0,1,4]}}}"#);
+    assert_eq!(resp, json!({ "ok" : true, "id" : 1, "panel" : "panel1" }));
+    assert!(!quit);
+    let (resp, _) = send(r#"{"op":"create","id":"b","panel":"named","spec":{"kind":"scatter","map":{"x":[0,1],"y":[0,1]}}}"#);
+    assert_eq!(resp, json!({ "ok" : true, "id" : "b", "panel" : "named" }));
+
+    let (resp, _) = send(r#"{"op":"update","id":2,"panel":"panel1","layout":{"width":320},"x":{"from":-1,"to":3},"mapping":0,"set":{"color":"red"}}"#);
+    assert_eq!(resp["ok"], json!(true));
+    assert_eq!(resp["id"], json!(2));
+    assert!(resp["updated"].as_u64().unwrap() >= 3);
+
+    let (resp, _) = send(r#"{"op":"data","panel":"panel1","mapping":0,"columns":[[0,1,2,3],[3,null,1,0]]}"#);
+    assert_eq!(resp, json!({ "ok" : true }));
+
+    let (resp, _) = send(r#"{"op":"render","id":3,"panel":"panel1","format":"svg"}"#);
+    assert_eq!(resp["format"], json!("svg"));
+    let svg = resp["data"].as_str().unwrap();
+    assert!(svg.contains("<svg") && svg.contains("width=\"320\""));
+
+    // Failed requests report their error and keep the session.
+    let (resp, quit) = send(r#"{"op":"render","id":4,"panel":"missing"}"#);
+    assert_eq!(resp, json!({ "ok" : false, "id" : 4, "error" : "Unknown panel: missing" }));
+    assert!(!quit);
+    let (resp, _) = send(r#"{"op":"resize","id":5,"panel":"panel1"}"#);
+    assert_eq!((&resp["ok"], &resp["id"]), (&json!(false), &json!(5)));
+    assert!(resp["error"].as_str().unwrap().starts_with("Invalid request"));
+    let (resp, _) = send("not json");
+    assert_eq!(resp["ok"], json!(false));
+    assert!(resp.get("id").is_none());
+
+    let (resp, _) = send(r#"{"op":"close","id":6,"panel":"named"}"#);
+    assert_eq!(resp, json!({ "ok" : true, "id" : 6 }));
+    let (resp, _) = send(r#"{"op":"close","panel":"named"}"#);
+    assert_eq!(resp["ok"], json!(false));
+    let (resp, quit) = send(r#"{"op":"quit","id":7}"#);
+    assert_eq!(resp, json!({ "ok" : true, "id" : 7 }));
+    assert!(quit);
+}
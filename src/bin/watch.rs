@@ -0,0 +1,120 @@
+/*Copyright (c) 2022 Diego da Silva Lima. All rights reserved.
+
+This work is licensed under the terms of the MIT license.  
+For a copy, see <https://opensource.org/licenses/MIT>.*/
+
+use std::fs;
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, SystemTime};
+use crate::{CliError, RenderArgs};
+
+// Interval between checks of the watched files.
+const POLL_INTERVAL : Duration = Duration::from_millis(250);
+
+// Files should stay unmodified for this long before being rendered, since editors
+// might save them in several writes.
+const DEBOUNCE : Duration = Duration::from_millis(150);
+
+// Modification time and size of each watched file (None if it could not be read).
+type Stamp = Vec<Option<(SystemTime, u64)>>;
+
+fn stamp(paths : &[PathBuf]) -> Stamp {
+    paths.iter()
+        .map(|p| fs::metadata(p).ok().and_then(|m| Some((m.modified().ok()?, m.len())) ) )
+        .collect()
+}
+
+// Waits until the watched files stop changing, given by poll (called after each debounce
+// interval) returning the same stamp twice in a row.
+fn settle(mut poll : impl FnMut() -> Stamp, mut last : Stamp) -> Stamp {
+    loop {
+        let curr = poll();
+        if curr == last {
+            return curr;
+        }
+        last = curr;
+    }
+}
+
+// Renders the definition once, reporting (but not propagating) any errors.
+fn update(input : &str, args : &RenderArgs, format : &str, clear : bool) {
+    let json = match fs::read_to_string(input) {
+        Ok(json) => json,
+        Err(e) => {
+            eprintln!("papyri: Unable to read {}: {}", input, e);
+            return;
+        }
+    };
+    let errs = papyri::validate::validate_json(&json);
+    for e in errs.iter() {
+        eprintln!("{}:{}", input, e);
+    }
+    if errs.iter().any(|e| e.is_error() ) {
+        return;
+    }
+    let res = crate::load_panel(&json, args)
+        .and_then(|mut panel| crate::render(&mut panel, format) )
+        .and_then(|out| {
+            if clear {
+                print!("\x1b[2J\x1b[H");
+            }
+            crate::write_output(args.output.as_deref(), &out)
+        });
+    match (res, &args.output) {
+        (Ok(_), Some(output)) => eprintln!("papyri: rendered {} to {}", input, output),
+        (Ok(_), None) => { },
+        (Err(e), _) => eprintln!("papyri: {}", e)
+    }
+}
+
+/// Renders the input whenever it (or the theme definition) changes, until the process is
+/// interrupted. The files are polled, so that no platform-specific notification API is required.
+pub fn run_watch(args : RenderArgs) -> Result<(), CliError> {
+    let input = match args.input.as_deref() {
+        None | Some("-") => return Err(CliError::Usage(String::from("watch requires an input file"))),
+        Some(input) => input.to_string()
+    };
+    let format = crate::output_format(&args)?;
+    let mut paths = vec![PathBuf::from(&input)];
+    if let Some(theme) = args.theme.as_deref().filter(|t| *t != "light" && *t != "dark" ) {
+        paths.push(PathBuf::from(theme));
+    }
+    let clear = args.output.is_none() && std::io::stdout().is_terminal();
+    let mut last : Option<Stamp> = None;
+    loop {
+        let curr = stamp(&paths);
+        if last.as_ref() != Some(&curr) {
+            let curr = settle(|| {
+                thread::sleep(DEBOUNCE);
+                stamp(&paths)
+            }, curr);
+            update(&input, &args, &format, clear);
+            last = Some(curr);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+#[test]
+fn debounce_writes() {
+    let dir = std::env::temp_dir().join(format!("papyri-watch-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let paths = vec![dir.join("plot.json"), dir.join("missing.json")];
+    fs::write(&paths[0], "x").unwrap();
+    let first = stamp(&paths);
+    assert!(first[0].map(|s| s.1 ) == Some(1) && first[1].is_none());
+    fs::remove_dir_all(&dir).unwrap();
+
+    // Unchanged files settle at the first poll.
+    let mut polls = 0;
+    assert_eq!(settle(|| { polls += 1; first.clone() }, first.clone()), first);
+    assert_eq!(polls, 1);
+
+    // A save split into several writes is only reported once two polls agree.
+    let size = |n : u64| vec![first[0].map(|(t, _)| (t, n) ), None];
+    let mut writes = vec![size(2), size(3), size(3), size(4)].into_iter();
+    assert_eq!(settle(|| writes.next().unwrap(), first.clone()), size(3));
+    assert_eq!(writes.next(), Some(size(4)));
+}
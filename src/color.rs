@@ -0,0 +1,294 @@
+/*Copyright (c) 2022 Diego da Silva Lima. All rights reserved.
+
+This work is licensed under the terms of the MIT license.  
+For a copy, see <https://opensource.org/licenses/MIT>.*/
+
+use std::str::FromStr;
+use std::fmt;
+
+/// A color with red, green, blue and alpha channels in the interval [0, 1]. Colors are
+/// parsed from CSS-style strings: hexadecimal (#rgb, #rgba, #rrggbb and #rrggbbaa),
+/// functional (rgb(), rgba(), hsl() and hsla()) and named colors (e.g. "steelblue"
+/// or "transparent"). Colors are written back as #rrggbb, or #rrggbbaa when
+/// they are not opaque.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Color {
+    red : f32,
+    green : f32,
+    blue : f32,
+    alpha : f32
+}
+
+impl Default for Color {
+
+    fn default() -> Self {
+        Self::BLACK
+    }
+
+}
+
+impl Color {
+
+    pub const BLACK : Color = Color { red : 0.0, green : 0.0, blue : 0.0, alpha : 1.0 };
+
+    pub const WHITE : Color = Color { red : 1.0, green : 1.0, blue : 1.0, alpha : 1.0 };
+
+    pub const TRANSPARENT : Color = Color { red : 0.0, green : 0.0, blue : 0.0, alpha : 0.0 };
+
+    /// Builds a color from channels in the interval [0, 1]. Values outside it are clamped.
+    pub fn new(red : f32, green : f32, blue : f32, alpha : f32) -> Self {
+        let clamp = |c : f32| if c.is_nan() { 0.0 } else { c.clamp(0.0, 1.0) };
+        Self { red : clamp(red), green : clamp(green), blue : clamp(blue), alpha : clamp(alpha) }
+    }
+
+    pub fn from_rgb8(red : u8, green : u8, blue : u8) -> Self {
+        Self::from_rgba8(red, green, blue, 255)
+    }
+
+    pub fn from_rgba8(red : u8, green : u8, blue : u8, alpha : u8) -> Self {
+        let norm = |c : u8| c as f32 / 255.0;
+        Self::new(norm(red), norm(green), norm(blue), norm(alpha))
+    }
+
+    pub fn red(&self) -> f32 {
+        self.red
+    }
+
+    pub fn green(&self) -> f32 {
+        self.green
+    }
+
+    pub fn blue(&self) -> f32 {
+        self.blue
+    }
+
+    pub fn alpha(&self) -> f32 {
+        self.alpha
+    }
+
+    pub fn with_alpha(mut self, alpha : f32) -> Self {
+        self.alpha = alpha.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Returns the channels as bytes, in the order red, green, blue, alpha.
+    pub fn to_rgba8(&self) -> [u8; 4] {
+        let byte = |c : f32| (c * 255.0).round() as u8;
+        [byte(self.red), byte(self.green), byte(self.blue), byte(self.alpha)]
+    }
+
+}
+
+impl fmt::Display for Color {
+
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        let [r, g, b, a] = self.to_rgba8();
+        if a == 255 {
+            write!(f, "#{:02x}{:02x}{:02x}", r, g, b)
+        } else {
+            write!(f, "#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
+        }
+    }
+
+}
+
+impl FromStr for Color {
+
+    type Err = ();
+
+    fn from_str(s : &str) -> Result<Self, ()> {
+        let s = s.trim().to_lowercase();
+        if let Some(hex) = s.strip_prefix('#') {
+            parse_hex(hex)
+        } else if let Some((name, args)) = s.strip_suffix(')').and_then(|s| s.split_once('(') ) {
+            parse_function(name.trim(), args)
+        } else {
+            named_color(&s).ok_or(())
+        }
+    }
+
+}
+
+fn parse_hex(hex : &str) -> Result<Color, ()> {
+    if !hex.chars().all(|c| c.is_ascii_hexdigit() ) {
+        return Err(());
+    }
+
+    // Short forms have a single digit per channel, which is repeated.
+    let digits : Vec<u8> = match hex.len() {
+        3 | 4 => hex.chars()
+            .map(|c| u8::from_str_radix(&format!("{}{}", c, c), 16).unwrap() )
+            .collect(),
+        6 | 8 => (0..hex.len()).step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i+2], 16).unwrap() )
+            .collect(),
+        _ => return Err(())
+    };
+    let alpha = digits.get(3).copied().unwrap_or(255);
+    Ok(Color::from_rgba8(digits[0], digits[1], digits[2], alpha))
+}
+
+// Arguments might be separated by commas (legacy syntax) or by whitespace, with alpha
+// after a slash (modern syntax), as in rgb(255, 0, 0) or rgb(255 0 0 / 50%).
+fn parse_function(name : &str, args : &str) -> Result<Color, ()> {
+    let args : Vec<&str> = args.split(|c : char| c == ',' || c == '/' || c.is_whitespace() )
+        .filter(|a| !a.is_empty() )
+        .collect();
+    let alpha = match args.len() {
+        3 => 1.0,
+        4 => parse_alpha(args[3])?,
+        _ => return Err(())
+    };
+    match name {
+        "rgb" | "rgba" => {
+            Ok(Color::new(parse_channel(args[0])?, parse_channel(args[1])?, parse_channel(args[2])?, alpha))
+        },
+        "hsl" | "hsla" => {
+            let hue = args[0].strip_suffix("deg").unwrap_or(args[0]).parse::<f32>().map_err(|_| ())?;
+            let sat = parse_percentage(args[1])?;
+            let light = parse_percentage(args[2])?;
+            let (r, g, b) = hsl_to_rgb(hue, sat, light);
+            Ok(Color::new(r, g, b, alpha))
+        },
+        _ => Err(())
+    }
+}
+
+// A color channel is either a number in [0, 255] or a percentage.
+fn parse_channel(s : &str) -> Result<f32, ()> {
+    if s.ends_with('%') {
+        parse_percentage(s)
+    } else {
+        s.parse::<f32>().map(|v| v / 255.0 ).map_err(|_| ())
+    }
+}
+
+// Alpha is either a number in [0, 1] or a percentage.
+fn parse_alpha(s : &str) -> Result<f32, ()> {
+    if s.ends_with('%') {
+        parse_percentage(s)
+    } else {
+        s.parse::<f32>().map_err(|_| ())
+    }
+}
+
+fn parse_percentage(s : &str) -> Result<f32, ()> {
+    let v = s.strip_suffix('%').ok_or(())?.parse::<f32>().map_err(|_| ())?;
+    Ok(v / 100.0)
+}
+
+fn hsl_to_rgb(hue : f32, sat : f32, light : f32) -> (f32, f32, f32) {
+    let hue = hue.rem_euclid(360.0) / 360.0;
+    let (sat, light) = (sat.clamp(0.0, 1.0), light.clamp(0.0, 1.0));
+    let q = if light < 0.5 { light * (1.0 + sat) } else { light + sat - light * sat };
+    let p = 2.0 * light - q;
+    let channel = |t : f32| {
+        let t = t.rem_euclid(1.0);
+        if t < 1.0 / 6.0 {
+            p + (q - p) * 6.0 * t
+        } else if t < 0.5 {
+            q
+        } else if t < 2.0 / 3.0 {
+            p + (q - p) * (2.0 / 3.0 - t) * 6.0
+        } else {
+            p
+        }
+    };
+    (channel(hue + 1.0 / 3.0), channel(hue), channel(hue - 1.0 / 3.0))
+}
+
+fn named_color(name : &str) -> Option<Color> {
+    if name == "transparent" {
+        return Some(Color::TRANSPARENT);
+    }
+    let pos = NAMED_COLORS.binary_search_by(|(n, _)| n.cmp(&name) ).ok()?;
+    let rgb = NAMED_COLORS[pos].1;
+    Some(Color::from_rgb8((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8))
+}
+
+// CSS named colors, sorted by name.
+const NAMED_COLORS : [(&str, u32); 148] = [
+    ("aliceblue", 0xf0f8ff), ("antiquewhite", 0xfaebd7), ("aqua", 0x00ffff),
+    ("aquamarine", 0x7fffd4), ("azure", 0xf0ffff), ("beige", 0xf5f5dc),
+    ("bisque", 0xffe4c4), ("black", 0x000000), ("blanchedalmond", 0xffebcd),
+    ("blue", 0x0000ff), ("blueviolet", 0x8a2be2), ("brown", 0xa52a2a),
+    ("burlywood", 0xdeb887), ("cadetblue", 0x5f9ea0), ("chartreuse", 0x7fff00),
+    ("chocolate", 0xd2691e), ("coral", 0xff7f50), ("cornflowerblue", 0x6495ed),
+    ("cornsilk", 0xfff8dc), ("crimson", 0xdc143c), ("cyan", 0x00ffff),
+    ("darkblue", 0x00008b), ("darkcyan", 0x008b8b), ("darkgoldenrod", 0xb8860b),
+    ("darkgray", 0xa9a9a9), ("darkgreen", 0x006400), ("darkgrey", 0xa9a9a9),
+    ("darkkhaki", 0xbdb76b), ("darkmagenta", 0x8b008b), ("darkolivegreen", 0x556b2f),
+    ("darkorange", 0xff8c00), ("darkorchid", 0x9932cc), ("darkred", 0x8b0000),
+    ("darksalmon", 0xe9967a), ("darkseagreen", 0x8fbc8f), ("darkslateblue", 0x483d8b),
+    ("darkslategray", 0x2f4f4f), ("darkslategrey", 0x2f4f4f), ("darkturquoise", 0x00ced1),
+    ("darkviolet", 0x9400d3), ("deeppink", 0xff1493), ("deepskyblue", 0x00bfff),
+    ("dimgray", 0x696969), ("dimgrey", 0x696969), ("dodgerblue", 0x1e90ff),
+    ("firebrick", 0xb22222), ("floralwhite", 0xfffaf0), ("forestgreen", 0x228b22),
+    ("fuchsia", 0xff00ff), ("gainsboro", 0xdcdcdc), ("ghostwhite", 0xf8f8ff),
+    ("gold", 0xffd700), ("goldenrod", 0xdaa520), ("gray", 0x808080),
+    ("green", 0x008000), ("greenyellow", 0xadff2f), ("grey", 0x808080),
+    ("honeydew", 0xf0fff0), ("hotpink", 0xff69b4), ("indianred", 0xcd5c5c),
+    ("indigo", 0x4b0082), ("ivory", 0xfffff0), ("khaki", 0xf0e68c),
+    ("lavender", 0xe6e6fa), ("lavenderblush", 0xfff0f5), ("lawngreen", 0x7cfc00),
+    ("lemonchiffon", 0xfffacd), ("lightblue", 0xadd8e6), ("lightcoral", 0xf08080),
+    ("lightcyan", 0xe0ffff), ("lightgoldenrodyellow", 0xfafad2), ("lightgray", 0xd3d3d3),
+    ("lightgreen", 0x90ee90), ("lightgrey", 0xd3d3d3), ("lightpink", 0xffb6c1),
+    ("lightsalmon", 0xffa07a), ("lightseagreen", 0x20b2aa), ("lightskyblue", 0x87cefa),
+    ("lightslategray", 0x778899), ("lightslategrey", 0x778899), ("lightsteelblue", 0xb0c4de),
+    ("lightyellow", 0xffffe0), ("lime", 0x00ff00), ("limegreen", 0x32cd32),
+    ("linen", 0xfaf0e6), ("magenta", 0xff00ff), ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66cdaa), ("mediumblue", 0x0000cd), ("mediumorchid", 0xba55d3),
+    ("mediumpurple", 0x9370db), ("mediumseagreen", 0x3cb371), ("mediumslateblue", 0x7b68ee),
+    ("mediumspringgreen", 0x00fa9a), ("mediumturquoise", 0x48d1cc), ("mediumvioletred", 0xc71585),
+    ("midnightblue", 0x191970), ("mintcream", 0xf5fffa), ("mistyrose", 0xffe4e1),
+    ("moccasin", 0xffe4b5), ("navajowhite", 0xffdead), ("navy", 0x000080),
+    ("oldlace", 0xfdf5e6), ("olive", 0x808000), ("olivedrab", 0x6b8e23),
+    ("orange", 0xffa500), ("orangered", 0xff4500), ("orchid", 0xda70d6),
+    ("palegoldenrod", 0xeee8aa), ("palegreen", 0x98fb98), ("paleturquoise", 0xafeeee),
+    ("palevioletred", 0xdb7093), ("papayawhip", 0xffefd5), ("peachpuff", 0xffdab9),
+    ("peru", 0xcd853f), ("pink", 0xffc0cb), ("plum", 0xdda0dd),
+    ("powderblue", 0xb0e0e6), ("purple", 0x800080), ("rebeccapurple", 0x663399),
+    ("red", 0xff0000), ("rosybrown", 0xbc8f8f), ("royalblue", 0x4169e1),
+    ("saddlebrown", 0x8b4513), ("salmon", 0xfa8072), ("sandybrown", 0xf4a460),
+    ("seagreen", 0x2e8b57), ("seashell", 0xfff5ee), ("sienna", 0xa0522d),
+    ("silver", 0xc0c0c0), ("skyblue", 0x87ceeb), ("slateblue", 0x6a5acd),
+    ("slategray", 0x708090), ("slategrey", 0x708090), ("snow", 0xfffafa),
+    ("springgreen", 0x00ff7f), ("steelblue", 0x4682b4), ("tan", 0xd2b48c),
+    ("teal", 0x008080), ("thistle", 0xd8bfd8), ("tomato", 0xff6347),
+    ("turquoise", 0x40e0d0), ("violet", 0xee82ee), ("wheat", 0xf5deb3),
+    ("white", 0xffffff), ("whitesmoke", 0xf5f5f5), ("yellow", 0xffff00),
+    ("yellowgreen", 0x9acd32)
+];
+
+// Conversions from and to the GDK color type, for embedding plots in GTK applications.
+#[cfg(feature="gtk")]
+impl From<gdk4::RGBA> for Color {
+
+    fn from(rgba : gdk4::RGBA) -> Self {
+        Color::new(rgba.red(), rgba.green(), rgba.blue(), rgba.alpha())
+    }
+
+}
+
+#[cfg(feature="gtk")]
+impl From<Color> for gdk4::RGBA {
+
+    fn from(color : Color) -> Self {
+        gdk4::RGBA::new(color.red, color.green, color.blue, color.alpha)
+    }
+
+}
+
+#[test]
+fn parse_css_colors() {
+    for s in ["#f00", "#ff0000", "#FF0000FF", "red", " Red ", "rgb(255, 0, 0)", "rgb(100% 0% 0%)", "hsl(0, 100%, 50%)", "hsla(360deg 100% 50% / 1)"] {
+        assert_eq!(s.parse::<Color>().map(|c| c.to_rgba8() ), Ok([255, 0, 0, 255]), "{}", s);
+    }
+    assert_eq!("rgba(255, 0, 0, 0.5)".parse::<Color>().unwrap().to_string(), "#ff000080");
+    assert_eq!("#0000".parse::<Color>(), Ok(Color::TRANSPARENT));
+    assert_eq!("steelblue".parse::<Color>().unwrap().to_string(), "#4682b4");
+    for s in ["", "#ff000", "#gg0000", "rgb(1, 2)", "cmyk(0, 0, 0, 0)", "notacolor"] {
+        assert!(s.parse::<Color>().is_err(), "{}", s);
+    }
+    assert!(NAMED_COLORS.windows(2).all(|w| w[0].0 < w[1].0 ));
+}
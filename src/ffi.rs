@@ -0,0 +1,370 @@
+/*Copyright (c) 2022 Diego da Silva Lima. All rights reserved.
+
+This work is licensed under the terms of the MIT license.  
+For a copy, see <https://opensource.org/licenses/MIT>.*/
+
+/*! C interface. Panels are created from JSON definitions and manipulated through opaque
+PapyriPanel handles, which must be released by papyri_panel_free. Functions return PAPYRI_OK (0)
+or one of the error codes below, and the message of the last error of each handle is given
+by papyri_panel_last_error.
+
+Functions that write to a caller-allocated buffer take its capacity at *out_len. When out is
+NULL, or the capacity is insufficient, nothing is written and *out_len receives the required
+length (the return value is PAPYRI_OK and PAPYRI_INSUFFICIENT_LEN, respectively). Otherwise,
+*out_len receives the number of bytes written. The last render of each handle is cached, so
+querying the length and then rendering only draws the panel once. The header at include/papyri.h
+is generated from this module with PAPYRI_HEADER_DEST=include/papyri.h cargo build --features header.
+
+All functions are unsafe: pointers must either be NULL or valid for the given lengths, and handles
+must come from papyri_panel_new and not be used after papyri_panel_free. Handles are not synchronized,
+and should not be shared across threads without a lock. !*/
+
+#![allow(clippy::missing_safety_doc)]
+
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use crate::render::Panel;
+
+pub const PAPYRI_OK : i64 = 0;
+
+pub const PAPYRI_INVALID_UTF8 : i64 = 1;
+
+pub const PAPYRI_INSUFFICIENT_LEN : i64 = 2;
+
+pub const PAPYRI_INVALID_DEFINITION : i64 = 3;
+
+/// A required pointer argument was NULL.
+pub const PAPYRI_NULL_POINTER : i64 = 4;
+
+/// An argument (such as a plot or mapping position) is out of range, or the data is invalid.
+pub const PAPYRI_INVALID_ARGUMENT : i64 = 5;
+
+/// The panel could not be rendered, or the format is not supported by this build.
+pub const PAPYRI_RENDER_ERROR : i64 = 6;
+
+/// The library panicked. The handle should be released.
+pub const PAPYRI_INTERNAL_ERROR : i64 = 7;
+
+pub const PAPYRI_FORMAT_SVG : i32 = 0;
+
+/// Requires the library to be built with the cairo-rs feature.
+pub const PAPYRI_FORMAT_PNG : i32 = 1;
+
+/// Requires the library to be built with the cairo-rs feature.
+pub const PAPYRI_FORMAT_PDF : i32 = 2;
+
+/// Opaque handle to a panel.
+pub struct PapyriPanel {
+
+    // None if the panel could not be created from its definition.
+    panel : Option<Panel>,
+
+    last_error : Option<CString>,
+
+    // Output of the last render, and its format.
+    rendered : Option<(i32, Vec<u8>)>
+
+}
+
+impl PapyriPanel {
+
+    fn fail(&mut self, code : i64, msg : impl ToString) -> i64 {
+        let msg = msg.to_string().replace('\0', " ");
+        self.last_error = CString::new(msg).ok();
+        code
+    }
+
+    // Runs an operation over the panel, recording its error (if any) and invalidating the cached render.
+    fn with_panel(&mut self, f : impl FnOnce(&mut Panel) -> Result<(), (i64, String)>) -> i64 {
+        self.rendered = None;
+        let res = match self.panel.as_mut() {
+            Some(panel) => panic::catch_unwind(AssertUnwindSafe(|| f(panel) ))
+                .unwrap_or_else(|_| Err((PAPYRI_INTERNAL_ERROR, String::from("Internal error"))) ),
+            None => Err((PAPYRI_INVALID_DEFINITION, String::from("Panel was not created from a valid definition")))
+        };
+        match res {
+            Ok(_) => {
+                self.last_error = None;
+                PAPYRI_OK
+            },
+            Err((code, msg)) => self.fail(code, msg)
+        }
+    }
+
+}
+
+fn message(code : i64) -> &'static str {
+    match code {
+        PAPYRI_OK => "Success",
+        PAPYRI_INVALID_UTF8 => "Invalid UTF-8",
+        PAPYRI_INSUFFICIENT_LEN => "Insufficient length",
+        PAPYRI_INVALID_DEFINITION => "Invalid definition",
+        PAPYRI_NULL_POINTER => "Null pointer",
+        PAPYRI_INVALID_ARGUMENT => "Invalid argument",
+        PAPYRI_RENDER_ERROR => "Unable to render",
+        PAPYRI_INTERNAL_ERROR => "Internal error",
+        _ => "Unknown error"
+    }
+}
+
+// Copies data to a caller-allocated buffer, following the size-query convention.
+unsafe fn write_out(data : &[u8], out : *mut u8, out_len : *mut usize) -> i64 {
+    if out_len.is_null() {
+        return PAPYRI_NULL_POINTER;
+    }
+    let capacity = *out_len;
+    *out_len = data.len();
+    if out.is_null() {
+        PAPYRI_OK
+    } else if capacity < data.len() {
+        PAPYRI_INSUFFICIENT_LEN
+    } else {
+        std::ptr::copy_nonoverlapping(data.as_ptr(), out, data.len());
+        PAPYRI_OK
+    }
+}
+
+unsafe fn read_str<'a>(data : *const u8, len : usize) -> Result<&'a str, i64> {
+    if data.is_null() {
+        return Err(PAPYRI_NULL_POINTER);
+    }
+    std::str::from_utf8(std::slice::from_raw_parts(data, len)).map_err(|_| PAPYRI_INVALID_UTF8 )
+}
+
+/// Writes the generic message for an error code (without a trailing NUL) to out.
+#[no_mangle]
+pub unsafe extern "C" fn papyri_error(code : i64, out : *mut u8, out_len : *mut usize) -> i64 {
+    write_out(message(code).as_bytes(), out, out_len)
+}
+
+/// Renders a JSON definition (not NUL-terminated) to SVG at once, without keeping a handle.
+#[no_mangle]
+pub unsafe extern "C" fn papyri_svg(model : *const u8, model_len : usize, out : *mut u8, out_len : *mut usize) -> i64 {
+    let model = match read_str(model, model_len) {
+        Ok(model) => model,
+        Err(code) => return code
+    };
+    let svg = panic::catch_unwind(|| {
+        Panel::new_from_json(model).ok().and_then(|mut pl| pl.svg().ok() )
+    });
+    match svg {
+        Ok(Some(svg)) => write_out(svg.as_bytes(), out, out_len),
+        Ok(None) => PAPYRI_INVALID_DEFINITION,
+        Err(_) => PAPYRI_INTERNAL_ERROR
+    }
+}
+
+/// Creates a panel from a JSON definition of a panel, plot or mapping (json_len bytes,
+/// not NUL-terminated). Returns NULL only if json is NULL. If the definition is invalid,
+/// the returned handle has its error set, and all operations over it fail with
+/// PAPYRI_INVALID_DEFINITION.
+#[no_mangle]
+pub unsafe extern "C" fn papyri_panel_new(json : *const u8, json_len : usize) -> *mut PapyriPanel {
+    if json.is_null() {
+        return std::ptr::null_mut();
+    }
+    let mut handle = PapyriPanel { panel : None, last_error : None, rendered : None };
+    match read_str(json, json_len) {
+        Ok(json) => match panic::catch_unwind(|| Panel::new_from_json(json) ) {
+            Ok(Ok(panel)) => handle.panel = Some(panel),
+            Ok(Err(e)) => { handle.fail(PAPYRI_INVALID_DEFINITION, e); },
+            Err(_) => { handle.fail(PAPYRI_INTERNAL_ERROR, "Internal error"); }
+        },
+        Err(code) => { handle.fail(code, message(code)); }
+    }
+    Box::into_raw(Box::new(handle))
+}
+
+/// Releases a panel. Passing NULL has no effect.
+#[no_mangle]
+pub unsafe extern "C" fn papyri_panel_free(panel : *mut PapyriPanel) {
+    if !panel.is_null() {
+        drop(Box::from_raw(panel));
+    }
+}
+
+/// Returns the message of the last failed operation over the panel (NUL-terminated, valid
+/// until the next call using the handle), or NULL if the last operation succeeded.
+#[no_mangle]
+pub unsafe extern "C" fn papyri_panel_last_error(panel : *const PapyriPanel) -> *const c_char {
+    match panel.as_ref().and_then(|p| p.last_error.as_ref() ) {
+        Some(msg) => msg.as_ptr(),
+        None => std::ptr::null()
+    }
+}
+
+/// Returns 1 if the panel was created from a valid definition, and 0 otherwise.
+#[no_mangle]
+pub unsafe extern "C" fn papyri_panel_is_valid(panel : *const PapyriPanel) -> i32 {
+    panel.as_ref().map(|p| p.panel.is_some() as i32 ).unwrap_or(0)
+}
+
+/// Replaces the data of a mapping. columns points to n_columns arrays of n_rows values each
+/// (NaN for missing values). Lines, scatters and texts take x and y columns, bars and wedges a
+/// single column, and areas and intervals three columns.
+#[no_mangle]
+pub unsafe extern "C" fn papyri_panel_update_mapping(
+    panel : *mut PapyriPanel,
+    plot : usize,
+    mapping : usize,
+    columns : *const *const f64,
+    n_columns : usize,
+    n_rows : usize
+) -> i64 {
+    let panel = match panel.as_mut() {
+        Some(panel) => panel,
+        None => return PAPYRI_NULL_POINTER
+    };
+    if columns.is_null() && n_columns > 0 {
+        return panel.fail(PAPYRI_NULL_POINTER, "Null columns");
+    }
+    let mut data = Vec::with_capacity(n_columns);
+    for i in 0..n_columns {
+        let col = *columns.add(i);
+        if col.is_null() && n_rows > 0 {
+            return panel.fail(PAPYRI_NULL_POINTER, format!("Null column {}", i));
+        }
+        data.push(if n_rows == 0 { &[][..] } else { std::slice::from_raw_parts(col, n_rows) });
+    }
+    panel.with_panel(|p| {
+        p.update_mapping(plot, &mapping.to_string(), &data).map_err(|e| (PAPYRI_INVALID_ARGUMENT, format!("{}", e)) )
+    })
+}
+
+/// Replaces the labels of a text mapping with n NUL-terminated UTF-8 strings.
+#[no_mangle]
+pub unsafe extern "C" fn papyri_panel_update_text(
+    panel : *mut PapyriPanel,
+    plot : usize,
+    mapping : usize,
+    text : *const *const c_char,
+    n : usize
+) -> i64 {
+    let panel = match panel.as_mut() {
+        Some(panel) => panel,
+        None => return PAPYRI_NULL_POINTER
+    };
+    if text.is_null() && n > 0 {
+        return panel.fail(PAPYRI_NULL_POINTER, "Null text");
+    }
+    let mut labels = Vec::with_capacity(n);
+    for i in 0..n {
+        let s = *text.add(i);
+        if s.is_null() {
+            return panel.fail(PAPYRI_NULL_POINTER, format!("Null label {}", i));
+        }
+        match CStr::from_ptr(s).to_str() {
+            Ok(s) => labels.push(s.to_string()),
+            Err(_) => return panel.fail(PAPYRI_INVALID_UTF8, format!("Invalid UTF-8 at label {}", i))
+        }
+    }
+    panel.with_panel(|p| {
+        p.update_mapping_text(plot, &mapping.to_string(), &labels).map_err(|e| (PAPYRI_INVALID_ARGUMENT, format!("{}", e)) )
+    })
+}
+
+/// Sets layout, design, scale or mapping properties from a JSON object (json_len bytes), such as
+/// {"layout": {"width": 800}, "plot": 0, "x": {"label": "Time"}, "mapping": 0, "set": {"color": "red"}}.
+/// No property is set if any of them is invalid.
+#[no_mangle]
+pub unsafe extern "C" fn papyri_panel_set(panel : *mut PapyriPanel, json : *const u8, json_len : usize) -> i64 {
+    let panel = match panel.as_mut() {
+        Some(panel) => panel,
+        None => return PAPYRI_NULL_POINTER
+    };
+    let json = match read_str(json, json_len) {
+        Ok(json) => json,
+        Err(code) => return panel.fail(code, message(code))
+    };
+    let update : serde_json::Value = match serde_json::from_str(json) {
+        Ok(v) => v,
+        Err(e) => return panel.fail(PAPYRI_INVALID_ARGUMENT, e)
+    };
+    panel.with_panel(|p| p.update_from_json(update).map(|_| () ).map_err(|e| (PAPYRI_INVALID_ARGUMENT, e) ) )
+}
+
+/// Renders the panel to one of the PAPYRI_FORMAT_* formats, following the size-query convention.
+#[no_mangle]
+pub unsafe extern "C" fn papyri_panel_render(panel : *mut PapyriPanel, format : i32, out : *mut u8, out_len : *mut usize) -> i64 {
+    let panel = match panel.as_mut() {
+        Some(panel) => panel,
+        None => return PAPYRI_NULL_POINTER
+    };
+    if !matches!(&panel.rendered, Some((f, _)) if *f == format) {
+        let mut rendered = Vec::new();
+        let code = panel.with_panel(|p| {
+            let res = match format {
+                PAPYRI_FORMAT_SVG => p.svg().map(|svg| svg.into_bytes() ),
+
+                #[cfg(feature="cairo-rs")]
+                PAPYRI_FORMAT_PNG => p.png(),
+
+                #[cfg(feature="cairo-rs")]
+                PAPYRI_FORMAT_PDF => p.pdf(),
+
+                #[cfg(not(feature="cairo-rs"))]
+                PAPYRI_FORMAT_PNG | PAPYRI_FORMAT_PDF => {
+                    return Err((PAPYRI_RENDER_ERROR, String::from("PNG and PDF require papyri to be built with cairo")));
+                },
+
+                other => return Err((PAPYRI_INVALID_ARGUMENT, format!("Invalid format: {}", other)))
+            };
+            rendered = res.map_err(|e| (PAPYRI_RENDER_ERROR, format!("{}", e)) )?;
+            Ok(())
+        });
+        if code != PAPYRI_OK {
+            return code;
+        }
+        panel.rendered = Some((format, rendered));
+    }
+    let data = panel.rendered.as_ref().map(|(_, data)| &data[..] ).unwrap_or(&[]);
+    match write_out(data, out, out_len) {
+        PAPYRI_INSUFFICIENT_LEN => panel.fail(PAPYRI_INSUFFICIENT_LEN, format!("Output requires {} bytes", data.len())),
+        code => code
+    }
+}
+
+#[test]
+fn panel_handles() {
+    unsafe {
+        let json = r#"{"kind":"line","map":{"x":[0,1,2],"y":[0,1,4]}}"#;
+        let panel = papyri_panel_new(json.as_ptr(), json.len());
+        assert_eq!(papyri_panel_is_valid(panel), 1);
+
+        // Size query, insufficient capacity and full render.
+        let mut len = 0;
+        assert_eq!(papyri_panel_render(panel, PAPYRI_FORMAT_SVG, std::ptr::null_mut(), &mut len), PAPYRI_OK);
+        let mut buf = vec![0u8; len];
+        let mut short = len - 1;
+        assert_eq!(papyri_panel_render(panel, PAPYRI_FORMAT_SVG, buf.as_mut_ptr(), &mut short), PAPYRI_INSUFFICIENT_LEN);
+        assert_eq!(papyri_panel_render(panel, PAPYRI_FORMAT_SVG, buf.as_mut_ptr(), &mut len), PAPYRI_OK);
+        assert!(String::from_utf8(buf).unwrap().contains("<svg"));
+
+        #[cfg(feature="cairo-rs")]
+        {
+            let mut len = 0;
+            assert_eq!(papyri_panel_render(panel, PAPYRI_FORMAT_PNG, std::ptr::null_mut(), &mut len), PAPYRI_OK);
+            assert!(len > 0);
+        }
+
+        let (x, y) = ([0.0, 1.0], [1.0, 0.0]);
+        let cols = [x.as_ptr(), y.as_ptr()];
+        assert_eq!(papyri_panel_update_mapping(panel, 0, 0, cols.as_ptr(), 2, 2), PAPYRI_OK);
+        assert!(papyri_panel_last_error(panel).is_null());
+        assert_eq!(papyri_panel_update_mapping(panel, 0, 0, cols.as_ptr(), 1, 2), PAPYRI_INVALID_ARGUMENT);
+        assert!(!papyri_panel_last_error(panel).is_null());
+
+        let set = r#"{"mapping": 0, "set": {"radius": 2}}"#;
+        assert_eq!(papyri_panel_set(panel, set.as_ptr(), set.len()), PAPYRI_INVALID_ARGUMENT);
+        let msg = CStr::from_ptr(papyri_panel_last_error(panel)).to_str().unwrap();
+        assert!(msg.contains("radius"));
+        papyri_panel_free(panel);
+
+        let invalid = r#"{"plots": 1}"#;
+        let panel = papyri_panel_new(invalid.as_ptr(), invalid.len());
+        assert_eq!(papyri_panel_is_valid(panel), 0);
+        assert!(!papyri_panel_last_error(panel).is_null());
+        papyri_panel_free(panel);
+    }
+}
@@ -3,10 +3,9 @@
 This work is licensed under the terms of the MIT license.  
 For a copy, see <https://opensource.org/licenses/MIT>.*/
 
-use serde::{Serialize, Deserialize};
+use serde::{Serialize, Deserialize, Deserializer};
 use std::default::Default;
 use std::fmt;
-use std::ops::Range;
 use std::str::FromStr;
 use std::cmp::{PartialEq, Eq};
 use std::error::Error;
@@ -90,6 +89,18 @@ impl FromStr for Adjustment {
 
 }
 
+impl Adjustment {
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Tight => "tight",
+            Self::Round => "round",
+            Self::Off => "off"
+        }
+    }
+
+}
+
 impl Default for Adjustment {
 
     fn default() -> Self {
@@ -98,6 +109,43 @@ impl Default for Adjustment {
 
 }
 
+/// Coordinate system of a plot. At polar coordinates, the horizontal (x) scale
+/// is mapped to the angle, which increases clockwise from the top of the plot,
+/// and the vertical (y) scale is mapped to the distance from the plot center.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum Coordinates {
+    #[default]
+    Cartesian,
+
+    Polar
+}
+
+impl FromStr for Coordinates {
+
+    type Err = ();
+
+    fn from_str(s : &str) -> Result<Self, ()> {
+        match s {
+            "cartesian" => Ok(Self::Cartesian),
+            "polar" => Ok(Self::Polar),
+            _ => Err(())
+        }
+    }
+
+}
+
+impl Coordinates {
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Cartesian => "cartesian",
+            Self::Polar => "polar"
+        }
+    }
+
+}
+
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MappingType {
     Line,
@@ -106,7 +154,8 @@ pub enum MappingType {
     Area,
     Surface,
     Text,
-    Interval
+    Interval,
+    Wedge
 }
 
 impl MappingType {
@@ -120,6 +169,7 @@ impl MappingType {
             "surface" => Some(MappingType::Surface),
             "text" => Some(MappingType::Text),
             "interval" => Some(MappingType::Interval),
+            "wedge" => Some(MappingType::Wedge),
             _ => None
         }
     }
@@ -186,11 +236,24 @@ carry a layout and design, they refer to the design and layout of the implicit
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Design {
+
+    // Background of the data area of each plot.
     pub bgcolor : String,
     pub fgcolor : String,
     pub width : i32,
     pub font : String,
     // pub fontcolor : String
+
+    // Background of the whole panel (defaults to bgcolor).
+    pub panel_bgcolor : Option<String>,
+
+    // Background of the full area of each plot, including its margins (defaults
+    // to no fill, so the panel background is visible).
+    pub plot_bgcolor : Option<String>,
+
+    // If true, no backgrounds are filled (including the data area), and
+    // only the plot elements are drawn.
+    pub transparent : Option<bool>
 }
 
 #[derive(Debug, Default)]
@@ -227,6 +290,21 @@ impl DesignBuilder {
         self
     }
 
+    pub fn panel_bgcolor(mut self, s : &str) -> Self {
+        self.0.panel_bgcolor = Some(s.to_string());
+        self
+    }
+
+    pub fn plot_bgcolor(mut self, s : &str) -> Self {
+        self.0.plot_bgcolor = Some(s.to_string());
+        self
+    }
+
+    pub fn transparent(mut self, transparent : bool) -> Self {
+        self.0.transparent = Some(transparent);
+        self
+    }
+
 }
 
 /// Represents a design definition error propagated to the user.
@@ -240,7 +318,13 @@ pub enum DesignError {
     InvalidGridColor,
 
     #[error("Invalid background color")]
-    InvalidBackgroundColor
+    InvalidBackgroundColor,
+
+    #[error("Invalid panel background color")]
+    InvalidPanelBackgroundColor,
+
+    #[error("Invalid plot background color")]
+    InvalidPlotBackgroundColor
 }
 
 impl Design {
@@ -262,6 +346,16 @@ impl Design {
         if !crate::model::validate_color(&self.bgcolor) {
             Err(DesignError::InvalidBackgroundColor)?;
         }
+        if let Some(color) = &self.panel_bgcolor {
+            if !crate::model::validate_color(color) {
+                Err(DesignError::InvalidPanelBackgroundColor)?;
+            }
+        }
+        if let Some(color) = &self.plot_bgcolor {
+            if !crate::model::validate_color(color) {
+                Err(DesignError::InvalidPlotBackgroundColor)?;
+            }
+        }
         Ok(())
     }
 
@@ -281,7 +375,10 @@ impl Default for Design {
             bgcolor : String::from("#ffffff"),
             fgcolor : String::from("#d3d7cf"),
             width : 1,
-            font : String::from("Monospace Regular 22")
+            font : String::from("Monospace Regular 22"),
+            panel_bgcolor : None,
+            plot_bgcolor : None,
+            transparent : None
         }
     }
 }
@@ -289,22 +386,91 @@ impl Default for Design {
 // #[derive(Clone, Debug, Serialize, Deserialize)]
 // pub struct Layout { ratio : f64, stacked : bool }
 
+/// Unit of the layout width and height. Pixels are the default; the remaining units
+/// are physical, in which case the drawing is done in points (so font sizes and
+/// line widths are also informed in points), and vector exports carry the informed size.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum Unit {
+    #[default]
+    Px,
+
+    Mm,
+
+    In,
+
+    Pt
+}
+
+impl FromStr for Unit {
+
+    type Err = ();
+
+    fn from_str(s : &str) -> Result<Self, ()> {
+        match s {
+            "px" => Ok(Self::Px),
+            "mm" => Ok(Self::Mm),
+            "in" => Ok(Self::In),
+            "pt" => Ok(Self::Pt),
+            _ => Err(())
+        }
+    }
+
+}
+
+impl Unit {
+
+    /// Number of points (1/72 inch) in one unit. Pixels are taken at the
+    /// CSS reference resolution of 96 pixels per inch.
+    pub fn points(&self) -> f64 {
+        match self {
+            Self::Px => 0.75,
+            Self::Mm => 72.0 / 25.4,
+            Self::In => 72.0,
+            Self::Pt => 1.0
+        }
+    }
+
+    pub fn is_physical(&self) -> bool {
+        *self != Self::Px
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Px => "px",
+            Self::Mm => "mm",
+            Self::In => "in",
+            Self::Pt => "pt"
+        }
+    }
+
+}
+
+/// Default resolution, in dots per inch. At this resolution, a layout with pixel
+/// units is rasterized at exactly the informed width and height.
+pub const DEFAULT_DPI : f64 = 96.0;
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Layout {
-    pub width : i32,
-    pub height : i32,
+    pub width : f64,
+    pub height : f64,
     pub hratio : f64,
     pub vratio : f64,
-    pub split : Option<String>
+    pub split : Option<String>,
+
+    // Unit of width and height. Must be px|mm|in|pt (defaults to px).
+    pub unit : Option<String>,
+
+    // Resolution used when rasterizing (PNG export), in dots per inch (defaults to 96).
+    pub dpi : Option<f64>
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum LayoutError {
 
-    #[error("'width' should be strictly positive")]
+    #[error("'width' should be finite and strictly positive")]
     Width,
     
-    #[error("'height' should be strictly positive")]
+    #[error("'height' should be finite and strictly positive")]
     Height,
     
     #[error("'hratio' should be in the interval 0.0 - 1.0")]
@@ -314,7 +480,13 @@ pub enum LayoutError {
     VRatio,
     
     #[error("Invalid value for 'split'. Expected one of 'unique', 'horizontal', 'vertical', 'threetop', 'threebottom', 'threeleft', 'threeright', 'four'")]
-    Split
+    Split,
+
+    #[error("Invalid value for 'unit'. Expected one of 'px', 'mm', 'in', 'pt'")]
+    Unit,
+
+    #[error("'dpi' should be finite and strictly positive")]
+    Dpi
     
 }
 
@@ -340,21 +512,43 @@ impl Layout {
     }
     
     pub fn validate(&self) -> Result<(), LayoutError> {
-        if self.width < 0 {
+        if !(self.width.is_finite() && self.width > 0.0) {
             Err(LayoutError::Width)
-        } else if self.height < 0 {
+        } else if !(self.height.is_finite() && self.height > 0.0) {
             Err(LayoutError::Height)
-        } else if self.hratio < 0.0 || self.hratio > 1.0 {
+        } else if !(0.0..=1.0).contains(&self.hratio) {
             Err(LayoutError::HRatio)
-        } else if self.vratio < 0.0 || self.vratio > 1.0 {
+        } else if !(0.0..=1.0).contains(&self.vratio) {
             Err(LayoutError::VRatio)
         } else if self.split.as_ref().map(|s| !(VALID_SPLITS.iter().any(|r| &s[..] == &r[..] )) ).unwrap_or(false) {
             Err(LayoutError::Split)
+        } else if self.unit.as_ref().map(|u| Unit::from_str(u).is_err() ).unwrap_or(false) {
+            Err(LayoutError::Unit)
+        } else if self.dpi.map(|d| !(d.is_finite() && d > 0.0) ).unwrap_or(false) {
+            Err(LayoutError::Dpi)
         } else {
             Ok(())
         }
     }
 
+    /// Returns the layout unit (assumed valid; pixels when absent or invalid).
+    pub fn unit(&self) -> Unit {
+        self.unit.as_ref().and_then(|u| Unit::from_str(u).ok() ).unwrap_or_default()
+    }
+
+    /// Returns the drawing size and the rasterization scale factor. The drawing size is
+    /// in pixels for pixel layouts, and in points for layouts with physical units. The
+    /// scale factor maps the drawing size to the number of pixels at the informed dpi.
+    pub fn drawing_size(&self) -> ((f64, f64), f64) {
+        let unit = self.unit();
+        let dpi = self.dpi.unwrap_or(DEFAULT_DPI);
+        if unit.is_physical() {
+            ((self.width * unit.points(), self.height * unit.points()), dpi / 72.0)
+        } else {
+            ((self.width, self.height), dpi / DEFAULT_DPI)
+        }
+    }
+
 }
 
 pub struct LayoutBuilder(Layout);
@@ -365,13 +559,25 @@ impl LayoutBuilder {
         self.0
     }
 
-    pub fn width(mut self, width : i32) -> Self {
-        self.0.width = width;
+    pub fn width(mut self, width : impl Into<f64>) -> Self {
+        self.0.width = width.into();
+        self
+    }
+
+    pub fn height(mut self, height : impl Into<f64>) -> Self {
+        self.0.height = height.into();
+        self
+    }
+
+    /// Unit of width and height (px, mm, in or pt).
+    pub fn unit(mut self, unit : &str) -> Self {
+        self.0.unit = Some(unit.to_string());
         self
     }
 
-    pub fn height(mut self, height : i32) -> Self {
-        self.0.height = height;
+    /// Resolution for raster exports, in dots per inch.
+    pub fn dpi(mut self, dpi : f64) -> Self {
+        self.0.dpi = Some(dpi);
         self
     }
 
@@ -394,11 +600,13 @@ impl LayoutBuilder {
 impl Default for Layout {
     fn default() -> Self {
         Self {
-            width : 800,
-            height : 600,
+            width : 800.0,
+            height : 600.0,
             hratio : 0.5,
             vratio : 0.5,
-            split : None
+            split : None,
+            unit : None,
+            dpi : None
         }
     }
 }
@@ -438,12 +646,13 @@ pub struct Scale {
 impl Scale {
 
     pub fn new_adjusted(vs : &[f64]) -> Result<Self, ScaleError> {
-        let mut scale = crate::model::Scale::default();
-        scale.from = *vs.iter().min_by(|a, b| a.total_cmp(&b) )
+
+        // Missing values do not contribute to the adjustment.
+        let from = *vs.iter().filter(|v| !v.is_nan() ).min_by(|a, b| a.total_cmp(b) )
             .ok_or(ScaleError::InvalidAdjustment)?;
-        scale.to = *vs.iter().max_by(|a, b| a.total_cmp(&b) )
+        let to = *vs.iter().filter(|v| !v.is_nan() ).max_by(|a, b| a.total_cmp(b) )
             .ok_or(ScaleError::InvalidAdjustment)?;
-        Ok(scale)
+        Ok(crate::model::Scale { from, to, ..Default::default() })
     }
 
     pub fn validate(&self) -> Result<(), ScaleError> {
@@ -565,47 +774,42 @@ impl ScaleBuilder {
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Map {
 
-    /* TODO apply custom parsing here. It is very common to
-    get floating point values that are f64::NAN, which will
-    be converted to Json's null. Inform the user when there
-    are null values, instead of a generic error message. */
+    /* Numeric columns accept null entries, which are represented as f64::NAN
+    and are treated as missing values by the mappings. Since serde_json writes
+    f64::NAN as null, maps with missing values survive a serialization round-trip. */
 
-    //#[serde(deserialize_with = "deser_num_data")]
+    #[serde(default, deserialize_with = "deser_num_data")]
     pub x : Option<Vec<f64>>,
 
-    //#[serde(deserialize_with = "deser_num_data")]
+    #[serde(default, deserialize_with = "deser_num_data")]
     pub y : Option<Vec<f64>>,
 
     //surface and area-specific
-    //#[serde(deserialize_with = "deser_num_data")]
+    #[serde(default, deserialize_with = "deser_num_data")]
     pub z : Option<Vec<f64>>,
 
     // Text-specific
-    //#[serde(deserialize_with = "deser_text_data")]
+    #[serde(default, deserialize_with = "deser_text_data")]
     pub text : Option<Vec<String>>
 }
 
-/*// This avoids conversion from NULL or null to serde_json::Value::Nil,
-// (nulls can be represented as such on the textual data).
-fn deser_text_data<'de, D>(deserializer: D) -> Result<String, D::Error>
+// Null labels are represented as empty labels.
+fn deser_text_data<'de, D>(deserializer: D) -> Result<Option<Vec<String>>, D::Error>
 where
     D: Deserializer<'de>,
 {
-    let s: &str = Deserialize::deserialize(deserializer)?;
-    Ok(s.to_string())
+    let data : Option<Vec<Option<String>>> = Deserialize::deserialize(deserializer)?;
+    Ok(data.map(|d| d.into_iter().map(|t| t.unwrap_or_default() ).collect() ))
 }
 
-fn deser_num_data<'de, D>(deserializer: D) -> Result<f64, D::Error>
+// Null values are represented as f64::NAN.
+fn deser_num_data<'de, D>(deserializer: D) -> Result<Option<Vec<f64>>, D::Error>
 where
     D: Deserializer<'de>,
 {
-    let s: &str = Deserialize::deserialize(deserializer)?;
-    if let Ok(i) = s.parse::<i64>() {
-        Ok(i as f64)
-    } else {
-        s.parse::<f64>().map_err(D::Error::custom)
-    }
-}*/
+    let data : Option<Vec<Option<f64>>> = Deserialize::deserialize(deserializer)?;
+    Ok(data.map(|d| d.into_iter().map(|v| v.unwrap_or(f64::NAN) ).collect() ))
+}
 
 impl Map {
 
@@ -639,6 +843,13 @@ impl Map {
             ..Default::default()
         }
     }
+
+    pub fn empty_for_wedge() -> Self {
+        Self {
+            x : Some(Vec::new()),
+            ..Default::default()
+        }
+    }
     
     pub fn empty_for_interval() -> Self {
         Self {
@@ -694,7 +905,18 @@ impl Map {
         s += ")";
         s
     }
-    
+
+    /// Returns the name and number of missing (NaN) values of each numeric
+    /// column that has at least one missing value.
+    pub fn missing_values(&self) -> Vec<(&'static str, usize)> {
+        [("x", &self.x), ("y", &self.y), ("z", &self.z)].iter()
+            .filter_map(|(name, col)| {
+                let n = col.as_ref()?.iter().filter(|v| v.is_nan() ).count();
+                if n > 0 { Some((*name, n)) } else { None }
+            })
+            .collect()
+    }
+
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -702,7 +924,8 @@ pub struct Line {
     pub map : Map,
     pub width : f64,
     pub spacing : f64,
-    pub color : String
+    pub color : String,
+    pub closed : bool
 }
 
 pub struct LineBuilder(Line);
@@ -732,6 +955,12 @@ impl LineBuilder {
         self.0.color = color.to_string();
         self
     }
+
+    /// Connects the last point back to the first (useful for radar charts).
+    pub fn closed(mut self, closed : bool) -> Self {
+        self.0.closed = closed;
+        self
+    }
 }
 
 impl Line {
@@ -753,7 +982,8 @@ impl Default for Line {
             map : Map::empty_for_line(),
             width : 1.0,
             spacing : 1.0,
-            color : String::from("#000000")
+            color : String::from("#000000"),
+            closed : false
         }
     }
 
@@ -762,8 +992,8 @@ impl Default for Line {
 impl From<Line> for Mapping {
 
     fn from(line : Line) -> Self {
-        let Line { map, width, spacing, color, .. } = line;
-        Mapping { kind : String::from("line"), map : map, width : Some(width), spacing : Some(spacing), color : Some(color), ..Default::default() }
+        let Line { map, width, spacing, color, closed } = line;
+        Mapping { kind : String::from("line"), map, width : Some(width), spacing : Some(spacing), color : Some(color), closed : Some(closed), ..Default::default() }
     }
 
 }
@@ -1094,6 +1324,92 @@ impl From<Bar> for Mapping {
 
 }
 
+/// Represents consecutive circular sectors, such as the slices of pie and donut
+/// charts. The only map required is the size of each wedge: wedges are placed
+/// one after the other along the angle (x) scale, starting at zero, and span the
+/// radius (y) scale from the origin to its end. The fill color is interpolated
+/// from color (first wedge) to color_final (last wedge).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Wedge {
+    pub map : Map,
+    pub color : String,
+    pub color_final : String,
+    pub origin : f64
+}
+
+pub struct WedgeBuilder(Wedge);
+
+impl WedgeBuilder {
+
+    pub fn build(self) -> Wedge {
+        self.0
+    }
+
+    pub fn map(mut self, x : Vec<f64>) -> Self {
+        self.0.map = Map { x : Some(x), y : None, z : None, text : None };
+        self
+    }
+
+    pub fn color(mut self, color : &str) -> Self {
+        self.0.color = color.to_string();
+        self
+    }
+
+    pub fn color_final(mut self, color : &str) -> Self {
+        self.0.color_final = color.to_string();
+        self
+    }
+
+    /// Inner radius, at the radius (y) scale. Values greater than the scale start
+    /// produce donut charts.
+    pub fn origin(mut self, origin : f64) -> Self {
+        self.0.origin = origin;
+        self
+    }
+
+}
+
+impl Wedge {
+
+    pub fn new() -> Wedge {
+        Wedge::default()
+    }
+
+    pub fn builder() -> WedgeBuilder {
+        WedgeBuilder(Self::default())
+    }
+
+}
+
+impl Default for Wedge {
+
+    fn default() -> Wedge {
+        Wedge {
+            map : Map::empty_for_wedge(),
+            color : String::from("#000000"),
+            color_final : String::from("#000000"),
+            origin : 0.0
+        }
+    }
+
+}
+
+impl From<Wedge> for Mapping {
+
+    fn from(wedge : Wedge) -> Self {
+        let Wedge { map, color, color_final, origin } = wedge;
+        Mapping {
+            kind : String::from("wedge"),
+            map,
+            color : Some(color),
+            color_final : Some(color_final),
+            origin : Some(origin),
+            ..Default::default()
+        }
+    }
+
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Surface {
     pub map : Map,
@@ -1196,7 +1512,8 @@ pub enum Property {
     Origin,
     ColorFinal,
     ZStart,
-    ZEnd
+    ZEnd,
+    Closed
 }
 
 impl Property {
@@ -1209,8 +1526,11 @@ impl Property {
             Property::Font => *m == MappingType::Text,
             Property::Radius => *m == MappingType::Scatter,
             Property::Limits => *m == MappingType::Interval,
-            Property::Center | Property::Origin => *m == MappingType::Bar,
-            Property::ZStart | Property::ZEnd | Property::ColorFinal => *m == MappingType::Surface
+            Property::Center => *m == MappingType::Bar,
+            Property::Origin => *m == MappingType::Bar || *m == MappingType::Wedge,
+            Property::ColorFinal => *m == MappingType::Surface || *m == MappingType::Wedge,
+            Property::ZStart | Property::ZEnd => *m == MappingType::Surface,
+            Property::Closed => *m == MappingType::Line
         }
     }
     
@@ -1234,6 +1554,7 @@ impl Property {
             Self::ZStart => format!("zstart"),
             Self::ZEnd => format!("zend"),
             Self::ColorFinal => format!("colorfinal"),
+            Self::Closed => String::from("closed"),
         }
     }
     
@@ -1242,7 +1563,7 @@ impl Property {
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Mapping {
 
-    // Must be line|scatter|area|bar|text|interval
+    // Must be line|scatter|area|bar|text|interval|wedge
     pub kind : String,
 
     pub map : Map,
@@ -1266,32 +1587,23 @@ pub struct Mapping {
     // Interval-specific
     pub limits : Option<f64>,
    
-    // Bar-specific
+    // Bar-specific (origin is shared by bar and wedge)
     pub center : Option<bool>,
     pub origin : Option<f64>,
 
-    // Surface-specific
+    // Surface-specific (color_final is shared by surface and wedge)
     pub color_final : Option<String>,
     pub z_start : Option<f64>,
-    pub z_end : Option<f64>
+    pub z_end : Option<f64>,
 
-}
+    // Line-specific
+    pub closed : Option<bool>
 
-fn hex_byte_at(full : &str, pos : Range<usize>) -> bool {
-    if let Some(sub) = full.get(pos) {
-        u8::from_str_radix(sub, 16).is_ok()
-    } else {
-        false
-    }
 }
 
+// Colors are validated with the same CSS-style parser used for rendering.
 pub(crate) fn validate_color(s : &str) -> bool {
-    let has_rgb = s.starts_with("#") && hex_byte_at(s, 1..3) && hex_byte_at(s, 3..5) && hex_byte_at(s, 5..7);
-    match s.len() {
-        7 => has_rgb,
-        9 => has_rgb && hex_byte_at(s, 7..9),
-        _ => false
-    }
+    s.parse::<crate::color::Color>().is_ok()
 }
 
 impl Mapping {
@@ -1346,6 +1658,10 @@ impl Mapping {
             props.push(Property::ZEnd);
         }
 
+        if self.closed.is_some() {
+            props.push(Property::Closed);
+        }
+
         props
     }
     
@@ -1382,6 +1698,12 @@ impl Mapping {
             MappingType::Scatter => {
                 self.radius.is_some()
             },
+            MappingType::Line => {
+                self.closed.is_some()
+            },
+            MappingType::Wedge => {
+                self.origin.is_some() || self.color_final.is_some()
+            },
             _ => false
         }
     }
@@ -1506,6 +1828,22 @@ impl Mapping {
                     }
                 }
             },
+            MappingType::Wedge => {
+                let empty = Map::empty_for_wedge();
+                if !self.map.like(&empty) {
+                    return Err(MappingError::DataMapping { expected : empty.description(), informed : self.map.description() });
+                }
+                if let Some(color) = &self.color_final {
+                    if !validate_color(&color[..]) {
+                        Err(MappingError::InvalidColor)?;
+                    }
+                }
+                for pr in self.properties() {
+                    if pr.absent(&MappingType::Wedge) {
+                        return Err(MappingError::InvalidProperty(pr.name()));
+                    }
+                }
+            },
         }
         Ok(())
     }
@@ -1519,7 +1857,7 @@ pub enum MappingError {
     #[error("Missing first mapping data column (x)")]
     MissingColumn,
     
-    #[error("Invalid mapping kind: {0} (expected line, scatter, interval, area, label or bar)")]
+    #[error("Invalid mapping kind: {0} (expected line, scatter, interval, area, label, bar or wedge)")]
     InvalidKind(String),
 
     #[error("Data length mismatch (expected {expected}, but informed {informed} for {column})")]
@@ -1531,10 +1869,261 @@ pub enum MappingError {
     #[error("Invalid mapping property: {0}")]
     InvalidProperty(String),
 
+    #[error("Invalid RGB/RGBA color")]
+    InvalidColor,
+
+    #[error("Found {count} missing value(s) at column {column}")]
+    MissingValues { column : &'static str, count : usize }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnnotationType {
+    HLine,
+    VLine,
+    HSpan,
+    VSpan,
+    Rect,
+    Arrow
+}
+
+impl FromStr for AnnotationType {
+
+    type Err = ();
+
+    fn from_str(s : &str) -> Result<Self, ()> {
+        match s {
+            "hline" => Ok(Self::HLine),
+            "vline" => Ok(Self::VLine),
+            "hspan" => Ok(Self::HSpan),
+            "vspan" => Ok(Self::VSpan),
+            "rect" => Ok(Self::Rect),
+            "arrow" => Ok(Self::Arrow),
+            _ => Err(())
+        }
+    }
+
+}
+
+impl AnnotationType {
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AnnotationType::HLine => "hline",
+            AnnotationType::VLine => "vline",
+            AnnotationType::HSpan => "hspan",
+            AnnotationType::VSpan => "vspan",
+            AnnotationType::Rect => "rect",
+            AnnotationType::Arrow => "arrow"
+        }
+    }
+
+}
+
+/// Represents an annotation definition error propagated to the user.
+#[derive(Debug, thiserror::Error)]
+pub enum AnnotationError {
+
+    #[error("Invalid annotation kind: {0} (expected hline, vline, hspan, vspan, rect or arrow)")]
+    InvalidKind(String),
+
+    #[error("Missing coordinate '{coord}' for {kind} annotation")]
+    MissingCoordinate { kind : &'static str, coord : &'static str },
+
+    #[error("Invalid annotation property: {0}")]
+    InvalidProperty(String),
+
     #[error("Invalid RGB/RGBA color")]
     InvalidColor
 }
 
+/// Annotations are non-data elements drawn on top of the mappings: reference
+/// lines at fixed data values (hline, vline), shaded regions spanning the whole
+/// data area along one dimension (hspan, vspan), rectangles and arrows pointing at
+/// a data point. All coordinates are at data scale. Annotations do not take part
+/// in scale adjustment unless adjust is set to true.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Annotation {
+
+    // Must be hline|vline|hspan|vspan|rect|arrow
+    pub kind : String,
+
+    // Position of vline/hline, start of vspan/hspan/rect and arrow tip.
+    pub x : Option<f64>,
+    pub y : Option<f64>,
+
+    // End of vspan/hspan/rect and arrow tail (where its text is placed).
+    pub x_end : Option<f64>,
+    pub y_end : Option<f64>,
+
+    pub text : Option<String>,
+
+    pub color : Option<String>,
+
+    // Line thickness and dash spacing (lines, rect and arrow)
+    pub width : Option<f64>,
+    pub spacing : Option<f64>,
+
+    pub font : Option<String>,
+
+    pub adjust : Option<bool>
+
+}
+
+pub struct AnnotationBuilder(Annotation);
+
+impl AnnotationBuilder {
+
+    pub fn build(self) -> Annotation {
+        self.0
+    }
+
+    pub fn text(mut self, text : &str) -> Self {
+        self.0.text = Some(text.to_string());
+        self
+    }
+
+    pub fn color(mut self, color : &str) -> Self {
+        self.0.color = Some(color.to_string());
+        self
+    }
+
+    pub fn width(mut self, width : f64) -> Self {
+        self.0.width = Some(width);
+        self
+    }
+
+    pub fn spacing(mut self, spacing : f64) -> Self {
+        self.0.spacing = Some(spacing);
+        self
+    }
+
+    pub fn font(mut self, font : &str) -> Self {
+        self.0.font = Some(font.to_string());
+        self
+    }
+
+    /// Whether the annotation extension should be considered when adjusting the plot scales.
+    pub fn adjust(mut self, adjust : bool) -> Self {
+        self.0.adjust = Some(adjust);
+        self
+    }
+
+}
+
+impl Annotation {
+
+    fn builder_for(kind : AnnotationType) -> AnnotationBuilder {
+        AnnotationBuilder(Annotation { kind : kind.name().to_string(), ..Default::default() })
+    }
+
+    /// Horizontal reference line at the given y value.
+    pub fn hline(y : f64) -> AnnotationBuilder {
+        let mut b = Self::builder_for(AnnotationType::HLine);
+        b.0.y = Some(y);
+        b
+    }
+
+    /// Vertical reference line at the given x value.
+    pub fn vline(x : f64) -> AnnotationBuilder {
+        let mut b = Self::builder_for(AnnotationType::VLine);
+        b.0.x = Some(x);
+        b
+    }
+
+    /// Shaded region between two y values, covering the full horizontal extension.
+    pub fn hspan(from : f64, to : f64) -> AnnotationBuilder {
+        let mut b = Self::builder_for(AnnotationType::HSpan);
+        b.0.y = Some(from);
+        b.0.y_end = Some(to);
+        b
+    }
+
+    /// Shaded region between two x values, covering the full vertical extension.
+    pub fn vspan(from : f64, to : f64) -> AnnotationBuilder {
+        let mut b = Self::builder_for(AnnotationType::VSpan);
+        b.0.x = Some(from);
+        b.0.x_end = Some(to);
+        b
+    }
+
+    pub fn rect(bottom_left : (f64, f64), top_right : (f64, f64)) -> AnnotationBuilder {
+        let mut b = Self::builder_for(AnnotationType::Rect);
+        b.0.x = Some(bottom_left.0);
+        b.0.y = Some(bottom_left.1);
+        b.0.x_end = Some(top_right.0);
+        b.0.y_end = Some(top_right.1);
+        b
+    }
+
+    /// Arrow pointing at the data point tip, starting from tail, where the text is drawn.
+    pub fn arrow(tip : (f64, f64), tail : (f64, f64), text : &str) -> AnnotationBuilder {
+        let mut b = Self::builder_for(AnnotationType::Arrow);
+        b.0.x = Some(tip.0);
+        b.0.y = Some(tip.1);
+        b.0.x_end = Some(tail.0);
+        b.0.y_end = Some(tail.1);
+        b.0.text = Some(text.to_string());
+        b
+    }
+
+    pub fn validate(&self) -> Result<(), AnnotationError> {
+        let ty = AnnotationType::from_str(&self.kind)
+            .map_err(|_| AnnotationError::InvalidKind(self.kind.to_string()) )?;
+        let required : &[(&'static str, bool)] = match ty {
+            AnnotationType::HLine => &[("y", self.y.is_some())],
+            AnnotationType::VLine => &[("x", self.x.is_some())],
+            AnnotationType::HSpan => &[("y", self.y.is_some()), ("y_end", self.y_end.is_some())],
+            AnnotationType::VSpan => &[("x", self.x.is_some()), ("x_end", self.x_end.is_some())],
+            AnnotationType::Rect | AnnotationType::Arrow => &[
+                ("x", self.x.is_some()),
+                ("y", self.y.is_some()),
+                ("x_end", self.x_end.is_some()),
+                ("y_end", self.y_end.is_some())
+            ]
+        };
+        for (coord, present) in required {
+            if !present {
+                return Err(AnnotationError::MissingCoordinate { kind : ty.name(), coord });
+            }
+        }
+        match ty {
+            AnnotationType::HLine if self.x.is_some() || self.x_end.is_some() || self.y_end.is_some() => {
+                return Err(AnnotationError::InvalidProperty(String::from("x")));
+            },
+            AnnotationType::VLine if self.y.is_some() || self.x_end.is_some() || self.y_end.is_some() => {
+                return Err(AnnotationError::InvalidProperty(String::from("y")));
+            },
+            AnnotationType::HSpan if self.x.is_some() || self.x_end.is_some() => {
+                return Err(AnnotationError::InvalidProperty(String::from("x")));
+            },
+            AnnotationType::VSpan if self.y.is_some() || self.y_end.is_some() => {
+                return Err(AnnotationError::InvalidProperty(String::from("y")));
+            },
+            _ => { }
+        }
+        if let Some(color) = &self.color {
+            if !validate_color(&color[..]) {
+                return Err(AnnotationError::InvalidColor);
+            }
+        }
+        if let Some(w) = self.width {
+            if w < 0.0 {
+                return Err(AnnotationError::InvalidProperty(String::from("width")));
+            }
+        }
+        Ok(())
+    }
+
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PlotError {
+
+    #[error("Invalid coordinate system: {0} (expected cartesian or polar)")]
+    InvalidCoordinates(String)
+
+}
+
 // Plot carries design only if not within a larger panel.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Plot {
@@ -1547,7 +2136,19 @@ pub struct Plot {
 
     pub design : Option<Design>,
 
-    pub layout : Option<Layout>
+    pub layout : Option<Layout>,
+
+    pub annotations : Option<Vec<Annotation>>,
+
+    pub title : Option<String>,
+
+    pub subtitle : Option<String>,
+
+    // Caption or source line, drawn below the plot.
+    pub caption : Option<String>,
+
+    // Must be cartesian|polar (defaults to cartesian).
+    pub coords : Option<String>
 
 }
 
@@ -1573,6 +2174,13 @@ impl Plot {
         for m in &self.mappings {
             m.validate()?;
         }
+        for a in self.annotations.iter().flatten() {
+            a.validate()?;
+        }
+        if let Some(coords) = &self.coords {
+            Coordinates::from_str(coords)
+                .map_err(|_| PlotError::InvalidCoordinates(coords.to_string()) )?;
+        }
         Ok(())
     }
 
@@ -1636,6 +2244,33 @@ impl PlotBuilder {
         self
     }
 
+    pub fn title(mut self, title : &str) -> Self {
+        self.0.title = Some(title.to_string());
+        self
+    }
+
+    pub fn subtitle(mut self, subtitle : &str) -> Self {
+        self.0.subtitle = Some(subtitle.to_string());
+        self
+    }
+
+    pub fn caption(mut self, caption : &str) -> Self {
+        self.0.caption = Some(caption.to_string());
+        self
+    }
+
+    /// Appends a single annotation, drawn on top of the mappings.
+    pub fn annotation(mut self, annotation : Annotation) -> Self {
+        self.0.annotations.get_or_insert_with(Vec::new).push(annotation);
+        self
+    }
+
+    /// Coordinate system (cartesian or polar).
+    pub fn coords(mut self, coords : &str) -> Self {
+        self.0.coords = Some(coords.to_string());
+        self
+    }
+
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -1645,7 +2280,13 @@ pub struct Panel {
 
     pub design : Option<Design>,
 
-    pub layout : Option<Layout>
+    pub layout : Option<Layout>,
+
+    // Supertitle, drawn above all plots.
+    pub title : Option<String>,
+
+    // If true, each plot is tagged with an upper-case letter (A, B, C...) at its top-left corner.
+    pub tags : Option<bool>
 
 }
 
@@ -1655,7 +2296,9 @@ impl Default for Panel {
         Panel {
             plots : vec![Plot::default()],
             design : Some(Design::default()),
-            layout : Some(Layout::default())
+            layout : Some(Layout::default()),
+            title : None,
+            tags : None
         }
     }
     
@@ -1716,6 +2359,16 @@ impl PanelBuilder {
         self
     }
 
+    pub fn title(mut self, title : &str) -> Self {
+        self.0.title = Some(title.to_string());
+        self
+    }
+
+    pub fn tags(mut self, tags : bool) -> Self {
+        self.0.tags = Some(tags);
+        self
+    }
+
 }
 
 impl fmt::Display for Panel {
@@ -1726,4 +2379,34 @@ impl fmt::Display for Panel {
 
 }
 
+#[test]
+fn layout_drawing_size() {
+    let layout = |width : f64, height : f64, unit : Option<&str>, dpi : Option<f64>| Layout {
+        width,
+        height,
+        unit : unit.map(String::from),
+        dpi,
+        ..Default::default()
+    };
+    let close = |((w, h), s) : ((f64, f64), f64), expected : ((f64, f64), f64)| {
+        (w - expected.0.0).abs() < 1e-9 && (h - expected.0.1).abs() < 1e-9 && (s - expected.1).abs() < 1e-9
+    };
+
+    // Pixel layouts are drawn in pixels, and rasterized at 96 dpi unless informed otherwise.
+    assert!(close(layout(800.0, 600.0, None, None).drawing_size(), ((800.0, 600.0), 1.0)));
+    assert!(close(layout(800.0, 600.0, Some("px"), Some(192.0)).drawing_size(), ((800.0, 600.0), 2.0)));
+
+    // Physical layouts are drawn in points.
+    assert!(close(layout(25.4, 50.8, Some("mm"), None).drawing_size(), ((72.0, 144.0), 96.0 / 72.0)));
+    assert!(close(layout(2.0, 1.0, Some("in"), Some(300.0)).drawing_size(), ((144.0, 72.0), 300.0 / 72.0)));
+    assert!(close(layout(144.0, 72.0, Some("pt"), Some(72.0)).drawing_size(), ((144.0, 72.0), 1.0)));
+    assert!(layout(1.0, 1.0, Some("cm"), None).validate().is_err());
+    assert!(layout(1.0, 1.0, Some("in"), Some(0.0)).validate().is_err());
 
+    // Non-finite sizes and resolutions are rejected.
+    assert!(layout(1.0, 1.0, Some("in"), Some(300.0)).validate().is_ok());
+    for (w, h, dpi) in [(f64::NAN, 1.0, None), (1.0, f64::INFINITY, None), (0.0, 1.0, None), (1.0, 1.0, Some(f64::NAN)), (1.0, 1.0, Some(f64::INFINITY))] {
+        assert!(layout(w, h, Some("mm"), dpi).validate().is_err(), "{} {} {:?}", w, h, dpi);
+    }
+    assert!(Layout { hratio : f64::NAN, ..layout(1.0, 1.0, None, None) }.validate().is_err());
+}
@@ -0,0 +1,628 @@
+/*Copyright (c) 2022 Diego da Silva Lima. All rights reserved.
+
+This work is licensed under the terms of the MIT license.  
+For a copy, see <https://opensource.org/licenses/MIT>.*/
+
+/*! Python bindings (python feature). The papyri extension module exposes the model
+definitions (Mapping, Scale, Plot, Layout and Design) as keyword-argument constructors,
+and Panel, which renders them. Panels display inline in Jupyter through _repr_svg_ and
+_repr_png_ (PNG requires the cairo-rs feature).
+
+Data columns are accepted as float64 numpy arrays or as any sequence of numbers (None
+representing missing values). Panel.update reads contiguous arrays directly from their
+buffers, while the mapping functions copy them, since model::Map owns its columns as Vec<f64>. !*/
+
+// Functions take one argument per Python keyword argument.
+#![allow(clippy::too_many_arguments)]
+
+use pyo3::prelude::*;
+use pyo3::exceptions::{PyValueError, PyIndexError};
+use pyo3::types::PyList;
+use numpy::PyReadonlyArray1;
+
+#[cfg(feature="cairo-rs")]
+use pyo3::types::PyBytes;
+use crate::model;
+use crate::render;
+
+fn value_error(e : impl ToString) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
+
+// A data column, borrowing the buffer of contiguous float64 arrays.
+enum Column<'py> {
+    Array(PyReadonlyArray1<'py, f64>),
+    Values(Vec<f64>)
+}
+
+impl AsRef<[f64]> for Column<'_> {
+    fn as_ref(&self) -> &[f64] {
+        match self {
+            // Only contiguous arrays are kept as arrays.
+            Column::Array(arr) => arr.as_slice().unwrap_or_default(),
+            Column::Values(vals) => &vals[..]
+        }
+    }
+}
+
+// Reads a data column from a numpy array or sequence of numbers.
+fn borrow_column<'py>(obj : &Bound<'py, PyAny>) -> PyResult<Column<'py>> {
+
+    // Only objects exposing the array interface are checked against numpy, so that numpy is
+    // not required when plain sequences are used. Arrays of other types are read as sequences.
+    if obj.hasattr("__array_interface__")? {
+        if let Ok(arr) = obj.extract::<PyReadonlyArray1<'py, f64>>() {
+
+            // Non-contiguous arrays (such as strided views) are copied element-wise.
+            return Ok(match arr.as_slice() {
+                Ok(_) => Column::Array(arr),
+                Err(_) => Column::Values(arr.as_array().to_vec())
+            });
+        }
+    }
+    let vals : Vec<Option<f64>> = obj.extract()?;
+    Ok(Column::Values(vals.into_iter().map(|v| v.unwrap_or(f64::NAN) ).collect()))
+}
+
+// Reads a data column to be owned by a definition.
+fn column(obj : &Bound<'_, PyAny>) -> PyResult<Vec<f64>> {
+    Ok(match borrow_column(obj)? {
+        Column::Values(vals) => vals,
+        col => col.as_ref().to_vec()
+    })
+}
+
+fn labels(obj : &Bound<'_, PyAny>) -> PyResult<Vec<String>> {
+    let vals : Vec<Option<String>> = obj.extract()?;
+    Ok(vals.into_iter().map(|t| t.unwrap_or_default() ).collect())
+}
+
+/// A mapping definition (see model::Mapping), built by the line, scatter, bar, interval,
+/// area, text and wedge functions.
+#[pyclass(name = "Mapping", module = "papyri")]
+#[derive(Clone)]
+pub struct PyMapping(model::Mapping);
+
+#[pymethods]
+impl PyMapping {
+
+    #[getter]
+    fn kind(&self) -> String {
+        self.0.kind.clone()
+    }
+
+    fn to_json(&self) -> PyResult<String> {
+        serde_json::to_string(&self.0).map_err(value_error)
+    }
+
+    fn _repr_svg_(&self) -> PyResult<String> {
+        panel_from_model(model::Panel { plots : vec![plot_of(vec![self.0.clone()])], ..Default::default() })?.svg()
+    }
+
+    #[cfg(feature="cairo-rs")]
+    fn _repr_png_<'py>(&self, py : Python<'py>) -> PyResult<Bound<'py, PyBytes>> {
+        panel_from_model(model::Panel { plots : vec![plot_of(vec![self.0.clone()])], ..Default::default() })?.png(py)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Mapping(kind={:?}, map={})", self.0.kind, self.0.map.description())
+    }
+
+}
+
+fn mapping(kind : &str, map : model::Map, color : Option<String>) -> model::Mapping {
+    model::Mapping { kind : kind.to_string(), map, color, ..Default::default() }
+}
+
+// Mapping properties are validated as soon as the mapping is built.
+fn checked(mapping : model::Mapping) -> PyResult<PyMapping> {
+    mapping.validate().map_err(value_error)?;
+    Ok(PyMapping(mapping))
+}
+
+#[pyfunction]
+#[pyo3(signature = (x, y, *, color=None, width=None, spacing=None, closed=None))]
+fn line(
+    x : &Bound<'_, PyAny>,
+    y : &Bound<'_, PyAny>,
+    color : Option<String>,
+    width : Option<f64>,
+    spacing : Option<f64>,
+    closed : Option<bool>
+) -> PyResult<PyMapping> {
+    let map = model::Map { x : Some(column(x)?), y : Some(column(y)?), ..Default::default() };
+    checked(model::Mapping { width, spacing, closed, ..mapping("line", map, color) })
+}
+
+#[pyfunction]
+#[pyo3(signature = (x, y, *, color=None, radius=None))]
+fn scatter(x : &Bound<'_, PyAny>, y : &Bound<'_, PyAny>, color : Option<String>, radius : Option<f64>) -> PyResult<PyMapping> {
+    let map = model::Map { x : Some(column(x)?), y : Some(column(y)?), ..Default::default() };
+    checked(model::Mapping { radius, ..mapping("scatter", map, color) })
+}
+
+#[pyfunction]
+#[pyo3(signature = (x, *, color=None, width=None, spacing=None, origin=None, center=None, vertical=None))]
+fn bar(
+    x : &Bound<'_, PyAny>,
+    color : Option<String>,
+    width : Option<f64>,
+    spacing : Option<f64>,
+    origin : Option<f64>,
+    center : Option<bool>,
+    vertical : Option<bool>
+) -> PyResult<PyMapping> {
+    let map = model::Map { x : Some(column(x)?), ..Default::default() };
+    checked(model::Mapping { width, spacing, origin, center, vertical, ..mapping("bar", map, color) })
+}
+
+/// Intervals at the positions x, spanning from the values at low to the values at high.
+#[pyfunction]
+#[pyo3(signature = (x, low, high, *, color=None, width=None, spacing=None, limits=None, vertical=None))]
+fn interval(
+    x : &Bound<'_, PyAny>,
+    low : &Bound<'_, PyAny>,
+    high : &Bound<'_, PyAny>,
+    color : Option<String>,
+    width : Option<f64>,
+    spacing : Option<f64>,
+    limits : Option<f64>,
+    vertical : Option<bool>
+) -> PyResult<PyMapping> {
+    let map = model::Map { x : Some(column(x)?), y : Some(column(low)?), z : Some(column(high)?), ..Default::default() };
+    checked(model::Mapping { width, spacing, limits, vertical, ..mapping("interval", map, color) })
+}
+
+/// Area filled between the values at low and high, along x.
+#[pyfunction]
+#[pyo3(signature = (x, low, high, *, color=None))]
+fn area(x : &Bound<'_, PyAny>, low : &Bound<'_, PyAny>, high : &Bound<'_, PyAny>, color : Option<String>) -> PyResult<PyMapping> {
+    let map = model::Map { x : Some(column(x)?), y : Some(column(low)?), z : Some(column(high)?), ..Default::default() };
+    checked(mapping("area", map, color))
+}
+
+#[pyfunction]
+#[pyo3(signature = (x, y, text, *, color=None, font=None))]
+fn text(
+    x : &Bound<'_, PyAny>,
+    y : &Bound<'_, PyAny>,
+    text : &Bound<'_, PyAny>,
+    color : Option<String>,
+    font : Option<String>
+) -> PyResult<PyMapping> {
+    let map = model::Map { x : Some(column(x)?), y : Some(column(y)?), text : Some(labels(text)?), ..Default::default() };
+    checked(model::Mapping { font, ..mapping("text", map, color) })
+}
+
+#[pyfunction]
+#[pyo3(signature = (x, *, color=None, color_final=None, origin=None))]
+fn wedge(x : &Bound<'_, PyAny>, color : Option<String>, color_final : Option<String>, origin : Option<f64>) -> PyResult<PyMapping> {
+    let map = model::Map { x : Some(column(x)?), ..Default::default() };
+    checked(model::Mapping { color_final, origin, ..mapping("wedge", map, color) })
+}
+
+#[pyclass(name = "Scale", module = "papyri")]
+#[derive(Clone)]
+pub struct PyScale(model::Scale);
+
+#[pymethods]
+impl PyScale {
+
+    /// The scale start is given by start (from is a Python keyword).
+    #[new]
+    #[pyo3(signature = (*, label=None, start=None, end=None, precision=None, intervals=None, offset=None, log=None, invert=None, adjust=None, guide=None))]
+    fn new(
+        label : Option<&str>,
+        start : Option<f64>,
+        end : Option<f64>,
+        precision : Option<i32>,
+        intervals : Option<i32>,
+        offset : Option<i32>,
+        log : Option<bool>,
+        invert : Option<bool>,
+        adjust : Option<&str>,
+        guide : Option<bool>
+    ) -> PyResult<Self> {
+        let mut builder = model::Scale::builder();
+        if let Some(label) = label {
+            builder = builder.label(label);
+        }
+        if let Some(start) = start {
+            builder = builder.from(start);
+        }
+        if let Some(end) = end {
+            builder = builder.to(end);
+        }
+        if let Some(precision) = precision {
+            builder = builder.precision(precision);
+        }
+        if let Some(intervals) = intervals {
+            builder = builder.intervals(intervals);
+        }
+        if let Some(offset) = offset {
+            builder = builder.offset(offset);
+        }
+        if let Some(log) = log {
+            builder = builder.log(log);
+        }
+        if let Some(invert) = invert {
+            builder = builder.invert(invert);
+        }
+        if let Some(adjust) = adjust {
+            builder = builder.adjust(adjust);
+        }
+        if let Some(guide) = guide {
+            builder = builder.guide(guide);
+        }
+        let scale = builder.build();
+        scale.validate().map_err(value_error)?;
+        Ok(PyScale(scale))
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Scale(label={:?}, start={}, end={})", self.0.label, self.0.from, self.0.to)
+    }
+
+}
+
+#[pyclass(name = "Layout", module = "papyri")]
+#[derive(Clone)]
+pub struct PyLayout(model::Layout);
+
+#[pymethods]
+impl PyLayout {
+
+    #[new]
+    #[pyo3(signature = (*, width=None, height=None, unit=None, dpi=None, hratio=None, vratio=None, split=None))]
+    fn new(
+        width : Option<f64>,
+        height : Option<f64>,
+        unit : Option<&str>,
+        dpi : Option<f64>,
+        hratio : Option<f64>,
+        vratio : Option<f64>,
+        split : Option<&str>
+    ) -> PyResult<Self> {
+        let mut builder = model::Layout::builder();
+        if let Some(width) = width {
+            builder = builder.width(width);
+        }
+        if let Some(height) = height {
+            builder = builder.height(height);
+        }
+        if let Some(unit) = unit {
+            builder = builder.unit(unit);
+        }
+        if let Some(dpi) = dpi {
+            builder = builder.dpi(dpi);
+        }
+        if let Some(hratio) = hratio {
+            builder = builder.hratio(hratio);
+        }
+        if let Some(vratio) = vratio {
+            builder = builder.vratio(vratio);
+        }
+        if let Some(split) = split {
+            builder = builder.split(split);
+        }
+        let layout = builder.build();
+        layout.validate().map_err(value_error)?;
+        Ok(PyLayout(layout))
+    }
+
+}
+
+#[pyclass(name = "Design", module = "papyri")]
+#[derive(Clone)]
+pub struct PyDesign(model::Design);
+
+#[pymethods]
+impl PyDesign {
+
+    #[new]
+    #[pyo3(signature = (*, bgcolor=None, fgcolor=None, font=None, width=None, panel_bgcolor=None, plot_bgcolor=None, transparent=None))]
+    fn new(
+        bgcolor : Option<&str>,
+        fgcolor : Option<&str>,
+        font : Option<&str>,
+        width : Option<i32>,
+        panel_bgcolor : Option<&str>,
+        plot_bgcolor : Option<&str>,
+        transparent : Option<bool>
+    ) -> PyResult<Self> {
+        let mut builder = model::Design::builder();
+        if let Some(bgcolor) = bgcolor {
+            builder = builder.bgcolor(bgcolor);
+        }
+        if let Some(fgcolor) = fgcolor {
+            builder = builder.fgcolor(fgcolor);
+        }
+        if let Some(font) = font {
+            builder = builder.font(font);
+        }
+        if let Some(width) = width {
+            builder = builder.width(width);
+        }
+        if let Some(panel_bgcolor) = panel_bgcolor {
+            builder = builder.panel_bgcolor(panel_bgcolor);
+        }
+        if let Some(plot_bgcolor) = plot_bgcolor {
+            builder = builder.plot_bgcolor(plot_bgcolor);
+        }
+        if let Some(transparent) = transparent {
+            builder = builder.transparent(transparent);
+        }
+        let design = builder.build();
+        design.validate().map_err(value_error)?;
+        Ok(PyDesign(design))
+    }
+
+    #[staticmethod]
+    fn dark() -> Self {
+        PyDesign(model::Design::default_dark())
+    }
+
+}
+
+fn plot_of(mappings : Vec<model::Mapping>) -> model::Plot {
+    model::Plot { mappings, ..Default::default() }
+}
+
+#[pyclass(name = "Plot", module = "papyri")]
+#[derive(Clone)]
+pub struct PyPlot(model::Plot);
+
+#[pymethods]
+impl PyPlot {
+
+    #[new]
+    #[pyo3(signature = (mappings, *, x=None, y=None, title=None, subtitle=None, caption=None, coords=None))]
+    fn new(
+        mappings : Vec<PyRef<'_, PyMapping>>,
+        x : Option<PyRef<'_, PyScale>>,
+        y : Option<PyRef<'_, PyScale>>,
+        title : Option<&str>,
+        subtitle : Option<&str>,
+        caption : Option<&str>,
+        coords : Option<&str>
+    ) -> PyResult<Self> {
+        let mut builder = model::Plot::builder().mappings(mappings.iter().map(|m| m.0.clone() ));
+        if let Some(x) = x {
+            builder = builder.x(x.0.clone());
+        }
+        if let Some(y) = y {
+            builder = builder.y(y.0.clone());
+        }
+        if let Some(title) = title {
+            builder = builder.title(title);
+        }
+        if let Some(subtitle) = subtitle {
+            builder = builder.subtitle(subtitle);
+        }
+        if let Some(caption) = caption {
+            builder = builder.caption(caption);
+        }
+        if let Some(coords) = coords {
+            builder = builder.coords(coords);
+        }
+        let plot = builder.build();
+        plot.validate().map_err(value_error)?;
+        Ok(PyPlot(plot))
+    }
+
+    fn to_json(&self) -> PyResult<String> {
+        serde_json::to_string(&self.0).map_err(value_error)
+    }
+
+    fn _repr_svg_(&self) -> PyResult<String> {
+        panel_from_model(model::Panel { plots : vec![self.0.clone()], ..Default::default() })?.svg()
+    }
+
+    #[cfg(feature="cairo-rs")]
+    fn _repr_png_<'py>(&self, py : Python<'py>) -> PyResult<Bound<'py, PyBytes>> {
+        panel_from_model(model::Panel { plots : vec![self.0.clone()], ..Default::default() })?.png(py)
+    }
+
+}
+
+/// A panel ready for rendering (see render::Panel). Panels are built from a list of plots
+/// (or a single plot or mapping), or from a JSON definition with Panel.from_json.
+#[pyclass(name = "Panel", module = "papyri", unsendable)]
+pub struct PyPanel(render::Panel);
+
+fn panel_from_model(panel : model::Panel) -> PyResult<PyPanel> {
+    render::Panel::new_from_model(panel).map(PyPanel).map_err(value_error)
+}
+
+fn plots_from(obj : &Bound<'_, PyAny>) -> PyResult<Vec<model::Plot>> {
+    if let Ok(plot) = obj.extract::<PyRef<'_, PyPlot>>() {
+        return Ok(vec![plot.0.clone()]);
+    }
+    if let Ok(mapping) = obj.extract::<PyRef<'_, PyMapping>>() {
+        return Ok(vec![plot_of(vec![mapping.0.clone()])]);
+    }
+    let mut plots = Vec::new();
+    for item in obj.cast::<PyList>()?.iter() {
+        plots.extend(plots_from(&item)?);
+    }
+    Ok(plots)
+}
+
+#[pymethods]
+impl PyPanel {
+
+    #[new]
+    #[pyo3(signature = (plots, *, design=None, layout=None, title=None, tags=None))]
+    fn new(
+        plots : &Bound<'_, PyAny>,
+        design : Option<PyRef<'_, PyDesign>>,
+        layout : Option<PyRef<'_, PyLayout>>,
+        title : Option<String>,
+        tags : Option<bool>
+    ) -> PyResult<Self> {
+        let mut panel = model::Panel { plots : plots_from(plots)?, title, tags, ..Default::default() };
+        if let Some(design) = design {
+            panel.design = Some(design.0.clone());
+        }
+        if let Some(layout) = layout {
+            panel.layout = Some(layout.0.clone());
+        }
+        panel_from_model(panel)
+    }
+
+    #[staticmethod]
+    fn from_json(json : &str) -> PyResult<Self> {
+        render::Panel::new_from_json(json).map(PyPanel).map_err(value_error)
+    }
+
+    fn svg(&mut self) -> PyResult<String> {
+        self.0.svg().map_err(value_error)
+    }
+
+    #[cfg(feature="cairo-rs")]
+    fn png<'py>(&mut self, py : Python<'py>) -> PyResult<Bound<'py, PyBytes>> {
+        let png = self.0.png().map_err(value_error)?;
+        Ok(PyBytes::new(py, &png))
+    }
+
+    #[cfg(feature="cairo-rs")]
+    fn pdf<'py>(&mut self, py : Python<'py>) -> PyResult<Bound<'py, PyBytes>> {
+        let pdf = self.0.pdf().map_err(value_error)?;
+        Ok(PyBytes::new(py, &pdf))
+    }
+
+    /// Writes the panel to a file, with the format given by its extension.
+    fn save(&mut self, path : &str) -> PyResult<()> {
+        self.0.draw_to_file(path).map_err(value_error)
+    }
+
+    /// Replaces the data of a mapping with the given columns. Contiguous float64 arrays are
+    /// read without intermediate copies.
+    #[pyo3(signature = (plot, mapping, *columns))]
+    fn update(&mut self, plot : usize, mapping : usize, columns : Vec<Bound<'_, PyAny>>) -> PyResult<()> {
+        let data = columns.iter().map(borrow_column).collect::<PyResult<Vec<_>>>()?;
+        self.0.update_mapping(plot, &mapping.to_string(), &data).map_err(|e| PyIndexError::new_err(e.to_string()) )
+    }
+
+    fn update_text(&mut self, plot : usize, mapping : usize, text : &Bound<'_, PyAny>) -> PyResult<()> {
+        let text = labels(text)?;
+        self.0.update_mapping_text(plot, &mapping.to_string(), &text).map_err(|e| PyIndexError::new_err(e.to_string()) )
+    }
+
+    /// Sets layout, design, scale or mapping properties from a dictionary with the same
+    /// fields as the update requests of papyri session, e.g.
+    /// panel.set({"plot": 0, "x": {"label": "Time"}, "mapping": 0, "set": {"color": "red"}}).
+    fn set(&mut self, py : Python<'_>, update : &Bound<'_, PyAny>) -> PyResult<usize> {
+        let json : String = py.import("json")?.call_method1("dumps", (update,))?.extract()?;
+        let update : serde_json::Value = serde_json::from_str(&json).map_err(value_error)?;
+        self.0.update_from_json(update).map_err(value_error)
+    }
+
+    fn _repr_svg_(&mut self) -> PyResult<String> {
+        self.svg()
+    }
+
+    #[cfg(feature="cairo-rs")]
+    fn _repr_png_<'py>(&mut self, py : Python<'py>) -> PyResult<Bound<'py, PyBytes>> {
+        self.png(py)
+    }
+
+    fn __repr__(&self) -> String {
+        self.0.description()
+    }
+
+}
+
+#[pymodule]
+fn papyri(m : &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyMapping>()?;
+    m.add_class::<PyScale>()?;
+    m.add_class::<PyLayout>()?;
+    m.add_class::<PyDesign>()?;
+    m.add_class::<PyPlot>()?;
+    m.add_class::<PyPanel>()?;
+    m.add_function(wrap_pyfunction!(line, m)?)?;
+    m.add_function(wrap_pyfunction!(scatter, m)?)?;
+    m.add_function(wrap_pyfunction!(bar, m)?)?;
+    m.add_function(wrap_pyfunction!(interval, m)?)?;
+    m.add_function(wrap_pyfunction!(area, m)?)?;
+    m.add_function(wrap_pyfunction!(text, m)?)?;
+    m.add_function(wrap_pyfunction!(wedge, m)?)?;
+    Ok(())
+}
+
+
+// Runs Python code with the papyri module in scope.
+#[cfg(test)]
+fn run_python(code : &str) {
+    Python::initialize();
+    Python::attach(|py| {
+        let globals = pyo3::types::PyDict::new(py);
+        globals.set_item("papyri", pyo3::wrap_pymodule!(papyri)(py)).unwrap();
+        if let Err(e) = py.run(&std::ffi::CString::new(code).unwrap(), Some(&globals), None) {
+            let trace = e.traceback(py).and_then(|t| t.format().ok() ).unwrap_or_default();
+            panic!("{}{}", trace, e);
+        }
+    });
+}
+
+#[test]
+fn python_columns() {
+    Python::initialize();
+    Python::attach(|py| {
+        let eval = |expr : &str| py.eval(&std::ffi::CString::new(expr).unwrap(), None, None).unwrap();
+        let seq = eval("(0, 1.5, None, 3)");
+        let col = column(&seq).unwrap();
+        assert_eq!(col[..2], [0.0, 1.5]);
+        assert!(col[2].is_nan());
+        assert_eq!(col[3], 3.0);
+        assert!(matches!(borrow_column(&eval("[0.5, 1]")).unwrap(), Column::Values(ref vals) if vals[..] == [0.5, 1.0]));
+        assert!(column(&eval("['a', 'b']")).is_err());
+        assert_eq!(labels(&eval("['a', None]")).unwrap(), ["a", ""]);
+    });
+}
+
+#[test]
+fn python_module() {
+    run_python("
+import json
+line = papyri.line([0, 1, 2], [0, None, 4], color='red')
+assert line.kind == 'line'
+assert json.loads(line.to_json())['map']['y'] == [0, None, 4]
+assert not hasattr(papyri, 'surface')
+try:
+    papyri.line([0, 1], [0, 1], color='nocolor')
+    assert False
+except ValueError:
+    pass
+
+plot = papyri.Plot([line, papyri.scatter((0, 1), (1, 0))], x=papyri.Scale(label='Time'), title='Title')
+assert json.loads(plot.to_json())['x']['label'] == 'Time'
+panel = papyri.Panel(plot, layout=papyri.Layout(width=300, height=200))
+svg = panel.svg()
+assert '<svg' in svg and 'width=\"300\"' in svg
+
+panel.update(0, 0, [0, 1], [1, 0])
+try:
+    panel.update(0, 5, [0, 1], [1, 0])
+    assert False
+except IndexError:
+    pass
+assert panel.set({'plot': 0, 'mapping': 0, 'set': {'color': 'blue'}}) == 1
+assert panel.svg() != svg
+
+panel = papyri.Panel.from_json('{\"kind\": \"scatter\", \"map\": {\"x\": [1, 2], \"y\": [2, 1]}}')
+assert '<svg' in panel._repr_svg_()
+");
+}
+
+#[cfg(feature="cairo-rs")]
+#[test]
+fn python_raster() {
+    run_python("
+panel = papyri.Panel([papyri.wedge([1, 2, 3])])
+assert panel.png().startswith(b'\\x89PNG')
+assert panel.pdf().startswith(b'%PDF')
+assert papyri.area([0, 1], [0, 0], [1, 2])._repr_png_().startswith(b'\\x89PNG')
+");
+}
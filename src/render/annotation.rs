@@ -0,0 +1,389 @@
+/*Copyright (c) 2022 Diego da Silva Lima. All rights reserved.
+
+This work is licensed under the terms of the MIT license.  
+For a copy, see <https://opensource.org/licenses/MIT>.*/
+
+use crate::color::Color;
+use super::backend::DrawingBackend;
+use super::context_mapper::{ContextMapper, Coord2D};
+use super::text::{FontData, draw_label};
+use std::error::Error;
+use std::str::FromStr;
+use crate::model::{AnnotationType, AnnotationError};
+
+// Length of the arrow head segments, in pixels.
+const ARROW_HEAD_LEN : f64 = 10.0;
+
+fn ordered(a : f64, b : f64) -> (f64, f64) {
+    (a.min(b), a.max(b))
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum AnnotationKind {
+
+    HLine(f64),
+
+    VLine(f64),
+
+    // Holds (from, to) at the y scale.
+    HSpan(f64, f64),
+
+    // Holds (from, to) at the x scale.
+    VSpan(f64, f64),
+
+    // Holds the bottom-left and top-right corners.
+    Rect((f64, f64), (f64, f64)),
+
+    // Holds the point the arrow is pointing at and the arrow tail (where the text goes).
+    Arrow { tip : (f64, f64), tail : (f64, f64) }
+
+}
+
+/// Non-data element drawn on top of the mappings. Annotations are kept separate
+/// from the mappings so that they are ignored when the plot scales are adjusted
+/// to the data, unless adjust is explicitly set.
+#[derive(Debug, Clone)]
+pub struct Annotation {
+    kind : AnnotationKind,
+    color : Color,
+    width : f64,
+    dash_n : i32,
+    text : Option<String>,
+    font : FontData,
+    adjust : bool
+}
+
+impl Annotation {
+
+    fn new(kind : AnnotationKind) -> Self {
+        let color = match kind {
+            AnnotationKind::HSpan(..) | AnnotationKind::VSpan(..) => Color::from_str("#00000022").unwrap(),
+            _ => Color::BLACK
+        };
+        Self {
+            kind,
+            color,
+            width : 1.0,
+            dash_n : 1,
+            text : None,
+            font : Default::default(),
+            adjust : false
+        }
+    }
+
+    pub fn hline(y : f64) -> Self {
+        Self::new(AnnotationKind::HLine(y))
+    }
+
+    pub fn vline(x : f64) -> Self {
+        Self::new(AnnotationKind::VLine(x))
+    }
+
+    pub fn hspan(from : f64, to : f64) -> Self {
+        Self::new(AnnotationKind::HSpan(from, to))
+    }
+
+    pub fn vspan(from : f64, to : f64) -> Self {
+        Self::new(AnnotationKind::VSpan(from, to))
+    }
+
+    pub fn rect(bottom_left : (f64, f64), top_right : (f64, f64)) -> Self {
+        Self::new(AnnotationKind::Rect(bottom_left, top_right))
+    }
+
+    pub fn arrow(tip : (f64, f64), tail : (f64, f64), text : &str) -> Self {
+        Self::new(AnnotationKind::Arrow { tip, tail }).text(text)
+    }
+
+    pub fn color(mut self, color : String) -> Self {
+        self.color = color.parse().unwrap();
+        self
+    }
+
+    pub fn width(mut self, width : f64) -> Self {
+        self.width = width;
+        self
+    }
+
+    pub fn dash_n(mut self, dash_n : i32) -> Self {
+        self.dash_n = dash_n;
+        self
+    }
+
+    pub fn text(mut self, text : &str) -> Self {
+        self.text = Some(text.to_string());
+        self
+    }
+
+    pub fn font(mut self, font : String) -> Self {
+        self.font = FontData::new_from_string(&font);
+        self
+    }
+
+    pub fn adjust(mut self, adjust : bool) -> Self {
+        self.adjust = adjust;
+        self
+    }
+
+    pub fn kind(&self) -> AnnotationKind {
+        self.kind
+    }
+
+    pub fn new_from_json(rep : crate::model::Annotation) -> Result<Self, AnnotationError> {
+        rep.validate()?;
+        let ty = AnnotationType::from_str(&rep.kind)
+            .map_err(|_| AnnotationError::InvalidKind(rep.kind.clone()) )?;
+
+        // Coordinates were verified to be present at validate()
+        let (x, y) = (rep.x.unwrap_or(0.0), rep.y.unwrap_or(0.0));
+        let (x_end, y_end) = (rep.x_end.unwrap_or(0.0), rep.y_end.unwrap_or(0.0));
+        let kind = match ty {
+            AnnotationType::HLine => AnnotationKind::HLine(y),
+            AnnotationType::VLine => AnnotationKind::VLine(x),
+            AnnotationType::HSpan => AnnotationKind::HSpan(y, y_end),
+            AnnotationType::VSpan => AnnotationKind::VSpan(x, x_end),
+            AnnotationType::Rect => AnnotationKind::Rect((x, y), (x_end, y_end)),
+            AnnotationType::Arrow => AnnotationKind::Arrow { tip : (x, y), tail : (x_end, y_end) }
+        };
+        let mut ann = Self::new(kind);
+        if let Some(color) = rep.color {
+            ann.color = color.parse().or(Err(AnnotationError::InvalidColor))?;
+        }
+        if let Some(width) = rep.width {
+            ann.width = width;
+        }
+        if let Some(spacing) = rep.spacing {
+            ann.dash_n = spacing as i32;
+        }
+        if let Some(font) = &rep.font {
+            ann.font = FontData::new_from_string(font);
+        }
+        ann.text = rep.text;
+        ann.adjust = rep.adjust.unwrap_or(false);
+        Ok(ann)
+    }
+
+    pub fn to_model(&self) -> crate::model::Annotation {
+        let (ty, (x, y), end) = match self.kind {
+            AnnotationKind::HLine(y) => (AnnotationType::HLine, (None, Some(y)), (None, None)),
+            AnnotationKind::VLine(x) => (AnnotationType::VLine, (Some(x), None), (None, None)),
+            AnnotationKind::HSpan(y0, y1) => (AnnotationType::HSpan, (None, Some(y0)), (None, Some(y1))),
+            AnnotationKind::VSpan(x0, x1) => (AnnotationType::VSpan, (Some(x0), None), (Some(x1), None)),
+            AnnotationKind::Rect(bl, tr) => (AnnotationType::Rect, (Some(bl.0), Some(bl.1)), (Some(tr.0), Some(tr.1))),
+            AnnotationKind::Arrow { tip, tail } => (AnnotationType::Arrow, (Some(tip.0), Some(tip.1)), (Some(tail.0), Some(tail.1)))
+        };
+        crate::model::Annotation {
+            kind : ty.name().to_string(),
+            x,
+            y,
+            x_end : end.0,
+            y_end : end.1,
+            text : self.text.clone(),
+            color : Some(self.color.to_string()),
+            width : Some(self.width),
+            spacing : Some(self.dash_n as f64),
+            font : Some(self.font.description()),
+            adjust : Some(self.adjust)
+        }
+    }
+
+    /// Returns the horizontal data limits for this annotation, if it should be
+    /// considered at scale adjustment. Horizontal lines and spans have no limits
+    /// at this dimension.
+    pub fn x_limits(&self) -> Option<(f64, f64)> {
+        if !self.adjust {
+            return None;
+        }
+        match self.kind {
+            AnnotationKind::VLine(x) => Some((x, x)),
+            AnnotationKind::VSpan(x0, x1) => Some(ordered(x0, x1)),
+            AnnotationKind::Rect((x0, _), (x1, _)) => Some(ordered(x0, x1)),
+            AnnotationKind::Arrow { tip, tail } => Some(ordered(tip.0, tail.0)),
+            AnnotationKind::HLine(_) | AnnotationKind::HSpan(..) => None
+        }
+    }
+
+    /// Returns the vertical data limits for this annotation, if it should be
+    /// considered at scale adjustment. Vertical lines and spans have no limits
+    /// at this dimension.
+    pub fn y_limits(&self) -> Option<(f64, f64)> {
+        if !self.adjust {
+            return None;
+        }
+        match self.kind {
+            AnnotationKind::HLine(y) => Some((y, y)),
+            AnnotationKind::HSpan(y0, y1) => Some(ordered(y0, y1)),
+            AnnotationKind::Rect((_, y0), (_, y1)) => Some(ordered(y0, y1)),
+            AnnotationKind::Arrow { tip, tail } => Some(ordered(tip.1, tail.1)),
+            AnnotationKind::VLine(_) | AnnotationKind::VSpan(..) => None
+        }
+    }
+
+    fn build_dash(n : i32) -> Vec<f64> {
+        let dash_sz = 10.0 / (n as f64);
+        let mut dashes = Vec::<f64>::new();
+        for _i in 1..n {
+            dashes.push(dash_sz);
+        }
+        dashes
+    }
+
+    fn draw_text(&self, ctx : &mut dyn DrawingBackend, pos : Coord2D) -> Result<(), Box<dyn Error>> {
+        if let Some(text) = &self.text {
+            draw_label(ctx, &self.font, text, pos, false, (true, true), None, None)?;
+        }
+        Ok(())
+    }
+
+    fn draw_segment(&self, ctx : &mut dyn DrawingBackend, from : Coord2D, to : Coord2D) -> Result<(), Box<dyn Error>> {
+        ctx.move_to(from.x, from.y);
+        ctx.line_to(to.x, to.y);
+        ctx.stroke()?;
+        Ok(())
+    }
+
+    pub fn draw(&self, mapper : &ContextMapper, ctx : &mut dyn DrawingBackend) -> Result<(), Box<dyn Error>> {
+        ctx.save()?;
+        ctx.set_color(&self.color);
+        ctx.set_line_width(self.width);
+        let dashes = Annotation::build_dash(self.dash_n);
+        ctx.set_dash(&dashes[..]);
+
+        // Clamp coordinates to the current data limits, so spans and rects
+        // partially outside the visible region are still drawn.
+        let clamp_x = |x : f64| x.max(mapper.xmin).min(mapper.xmax);
+        let clamp_y = |y : f64| y.max(mapper.ymin).min(mapper.ymax);
+        let x_visible = |x : f64| x >= mapper.xmin && x <= mapper.xmax;
+        let y_visible = |y : f64| y >= mapper.ymin && y <= mapper.ymax;
+
+        match self.kind {
+            AnnotationKind::HLine(y) => {
+                if y_visible(y) {
+                    let from = mapper.map(mapper.xmin, y);
+                    let to = mapper.map(mapper.xmax, y);
+                    self.draw_segment(ctx, from, to)?;
+                    let text_pos = mapper.map(mapper.xmin + 0.9*(mapper.xmax - mapper.xmin), y);
+                    self.draw_text(ctx, Coord2D::new(text_pos.x, text_pos.y - self.font.font_size as f64))?;
+                }
+            },
+            AnnotationKind::VLine(x) => {
+                if x_visible(x) {
+                    let from = mapper.map(x, mapper.ymin);
+                    let to = mapper.map(x, mapper.ymax);
+                    self.draw_segment(ctx, from, to)?;
+                    let text_pos = mapper.map(x, mapper.ymin + 0.95*(mapper.ymax - mapper.ymin));
+                    self.draw_text(ctx, text_pos)?;
+                }
+            },
+            AnnotationKind::HSpan(y0, y1) => {
+                if y_visible(y0) || y_visible(y1) || (y0.min(y1) < mapper.ymin && y0.max(y1) > mapper.ymax) {
+                    let a = mapper.map(mapper.xmin, clamp_y(y0));
+                    let b = mapper.map(mapper.xmax, clamp_y(y1));
+                    ctx.rectangle(a.x.min(b.x), a.y.min(b.y), (b.x - a.x).abs(), (b.y - a.y).abs());
+                    ctx.fill()?;
+                    self.draw_text(ctx, Coord2D::new((a.x + b.x) / 2., (a.y + b.y) / 2.))?;
+                }
+            },
+            AnnotationKind::VSpan(x0, x1) => {
+                if x_visible(x0) || x_visible(x1) || (x0.min(x1) < mapper.xmin && x0.max(x1) > mapper.xmax) {
+                    let a = mapper.map(clamp_x(x0), mapper.ymin);
+                    let b = mapper.map(clamp_x(x1), mapper.ymax);
+                    ctx.rectangle(a.x.min(b.x), a.y.min(b.y), (b.x - a.x).abs(), (b.y - a.y).abs());
+                    ctx.fill()?;
+                    self.draw_text(ctx, Coord2D::new((a.x + b.x) / 2., (a.y + b.y) / 2.))?;
+                }
+            },
+            AnnotationKind::Rect((x0, y0), (x1, y1)) => {
+
+                // Rects not overlapping the data limits would be clamped to a degenerate path.
+                let x_overlaps = x0.max(x1) >= mapper.xmin && x0.min(x1) <= mapper.xmax;
+                let y_overlaps = y0.max(y1) >= mapper.ymin && y0.min(y1) <= mapper.ymax;
+                if !(x_overlaps && y_overlaps) {
+                    ctx.restore()?;
+                    return Ok(());
+                }
+                let a = mapper.map(clamp_x(x0), clamp_y(y0));
+                let b = mapper.map(clamp_x(x1), clamp_y(y1));
+                ctx.rectangle(a.x.min(b.x), a.y.min(b.y), (b.x - a.x).abs(), (b.y - a.y).abs());
+                ctx.stroke()?;
+                self.draw_text(ctx, Coord2D::new((a.x + b.x) / 2., (a.y + b.y) / 2.))?;
+            },
+            AnnotationKind::Arrow { tip, tail } => {
+                if mapper.check_bounds(tip.0, tip.1) && mapper.check_bounds(tail.0, tail.1) {
+                    let to = mapper.map(tip.0, tip.1);
+                    let text_pos = mapper.map(tail.0, tail.1);
+                    let dist = to.distance(text_pos);
+                    if dist > 0.0 {
+                        let (dx, dy) = ((to.x - text_pos.x) / dist, (to.y - text_pos.y) / dist);
+
+                        // Start the arrow just outside the text, so they do not overlap.
+                        let gap = if self.text.is_some() { self.font.font_size as f64 } else { 0.0 };
+                        let from = Coord2D::new(text_pos.x + dx*gap, text_pos.y + dy*gap);
+                        self.draw_segment(ctx, from, to)?;
+
+                        // Arrow head, as two segments rotated +-30 degrees from the arrow body.
+                        ctx.set_dash(&[]);
+                        let angle = dy.atan2(dx);
+                        for side in [-1.0, 1.0] {
+                            let head_angle = angle + std::f64::consts::PI + side * std::f64::consts::PI / 6.0;
+                            let head = Coord2D::new(
+                                to.x + ARROW_HEAD_LEN*head_angle.cos(),
+                                to.y + ARROW_HEAD_LEN*head_angle.sin()
+                            );
+                            self.draw_segment(ctx, to, head)?;
+                        }
+                    }
+                    self.draw_text(ctx, text_pos)?;
+                }
+            }
+        }
+        ctx.restore()?;
+        Ok(())
+    }
+
+}
+
+#[cfg(test)]
+fn draw_to_svg(ann : &Annotation) -> String {
+    let mut mapper = ContextMapper::new(0.0, 10.0, 0.0, 10.0, false, false, false, false);
+    mapper.update_dimensions(100, 100);
+    let mut writer = super::svg::SvgWriter::new(100.0, 100.0, crate::model::Unit::Px);
+    ann.draw(&mapper, &mut writer).unwrap();
+    writer.finish()
+}
+
+#[test]
+fn annotation_limits() {
+    assert_eq!(Annotation::hline(2.0).y_limits(), None);
+    assert_eq!(Annotation::hline(2.0).adjust(true).y_limits(), Some((2.0, 2.0)));
+    assert_eq!(Annotation::hline(2.0).adjust(true).x_limits(), None);
+    assert_eq!(Annotation::vline(3.0).adjust(true).x_limits(), Some((3.0, 3.0)));
+    assert_eq!(Annotation::vline(3.0).adjust(true).y_limits(), None);
+    assert_eq!(Annotation::hspan(4.0, 1.0).adjust(true).y_limits(), Some((1.0, 4.0)));
+    assert_eq!(Annotation::vspan(5.0, 2.0).adjust(true).x_limits(), Some((2.0, 5.0)));
+    let rect = Annotation::rect((1.0, 8.0), (6.0, 2.0)).adjust(true);
+    assert_eq!((rect.x_limits(), rect.y_limits()), (Some((1.0, 6.0)), Some((2.0, 8.0))));
+    let arrow = Annotation::arrow((1.0, 1.0), (3.0, 0.5), "A").adjust(true);
+    assert_eq!((arrow.x_limits(), arrow.y_limits()), (Some((1.0, 3.0)), Some((0.5, 1.0))));
+}
+
+#[test]
+fn annotation_draw() {
+    let empty = super::svg::SvgWriter::new(100.0, 100.0, crate::model::Unit::Px).finish();
+    let paths = |ann : Annotation| draw_to_svg(&ann).matches("<path").count() - empty.matches("<path").count();
+    assert_eq!(paths(Annotation::hline(5.0)), 1);
+    assert_eq!(paths(Annotation::hline(20.0)), 0);
+    assert_eq!(paths(Annotation::vline(5.0)), 1);
+    assert_eq!(paths(Annotation::vline(-1.0)), 0);
+    assert_eq!(paths(Annotation::hspan(2.0, 20.0)), 1);
+    assert_eq!(paths(Annotation::hspan(12.0, 20.0)), 0);
+    assert_eq!(paths(Annotation::vspan(-5.0, 20.0)), 1);
+    assert_eq!(paths(Annotation::vspan(-5.0, -1.0)), 0);
+    assert_eq!(paths(Annotation::rect((2.0, 2.0), (20.0, 4.0))), 1);
+    assert_eq!(paths(Annotation::rect((12.0, 2.0), (20.0, 4.0))), 0);
+    assert_eq!(paths(Annotation::rect((2.0, -4.0), (4.0, -2.0))), 0);
+
+    // Arrow body and the two head segments.
+    assert_eq!(paths(Annotation::arrow((5.0, 5.0), (8.0, 8.0), "A")), 3);
+    assert_eq!(paths(Annotation::arrow((5.0, 5.0), (18.0, 8.0), "A")), 0);
+}
@@ -0,0 +1,287 @@
+/*Copyright (c) 2022 Diego da Silva Lima. All rights reserved.
+
+This work is licensed under the terms of the MIT license.  
+For a copy, see <https://opensource.org/licenses/MIT>.*/
+
+use crate::color::Color;
+use std::error::Error;
+use super::text::FontData;
+
+/// Extents of a text line, in the units of the drawing surface.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TextExtents {
+
+    // Horizontal distance the current point advances after the text is drawn.
+    pub x_advance : f64,
+
+    // Height of the inked area of the text.
+    pub height : f64
+
+}
+
+/// Vector drawing operations used by the panel, plots and mappings, following the cairo
+/// drawing model: paths are built with move_to, line_to, arc, rectangle and close_path,
+/// then consumed by fill, stroke or clip. The source color, line width, dash pattern,
+/// clip region and transformations are part of the drawing state, which is pushed by save
+/// and popped by restore. Angles are in radians, increasing clockwise from the positive x
+/// axis, since the y axis points down.
+pub trait DrawingBackend {
+
+    fn save(&mut self) -> Result<(), Box<dyn Error>>;
+
+    fn restore(&mut self) -> Result<(), Box<dyn Error>>;
+
+    fn translate(&mut self, x : f64, y : f64);
+
+    fn rotate(&mut self, angle : f64);
+
+    fn scale(&mut self, sx : f64, sy : f64);
+
+    fn set_color(&mut self, color : &Color);
+
+    fn set_line_width(&mut self, width : f64);
+
+    /// Sets the lengths of the alternating on and off segments of strokes. An
+    /// empty slice draws solid lines.
+    fn set_dash(&mut self, dashes : &[f64]);
+
+    fn move_to(&mut self, x : f64, y : f64);
+
+    fn line_to(&mut self, x : f64, y : f64);
+
+    /// Adds a circular arc to the path. If there is a current point, a line
+    /// connects it to the start of the arc.
+    fn arc(&mut self, xc : f64, yc : f64, radius : f64, angle1 : f64, angle2 : f64);
+
+    fn rectangle(&mut self, x : f64, y : f64, w : f64, h : f64);
+
+    fn close_path(&mut self);
+
+    /// Starts a new sub-path without a current point, so the next arc is not
+    /// connected to the previous segment.
+    fn new_sub_path(&mut self);
+
+    fn fill(&mut self) -> Result<(), Box<dyn Error>>;
+
+    /// Fills the current path without clearing it, so it can also be stroked.
+    fn fill_preserve(&mut self) -> Result<(), Box<dyn Error>>;
+
+    fn stroke(&mut self) -> Result<(), Box<dyn Error>>;
+
+    /// Intersects the clip region with the current path, and clears the path.
+    fn clip(&mut self);
+
+    fn text_extents(&mut self, font : &FontData, text : &str) -> TextExtents;
+
+    /// Draws a text line with its baseline starting at (x, y). Does not affect the current path.
+    fn show_text(&mut self, font : &FontData, x : f64, y : f64, text : &str) -> Result<(), Box<dyn Error>>;
+
+    /// Starts a logical group of elements (a plot, axis, mapping, etc.), identified by a
+    /// document-wide unique id, a space-separated list of classes and an optional human-readable
+    /// title. Groups push the drawing state like save, and must be closed with end_group.
+    /// Backends without a notion of structure just save the state.
+    fn begin_group(&mut self, _id : &str, _class : &str, _title : Option<&str>) -> Result<(), Box<dyn Error>> {
+        self.save()
+    }
+
+    /// Closes the last group opened by begin_group, restoring the drawing state.
+    fn end_group(&mut self) -> Result<(), Box<dyn Error>> {
+        self.restore()
+    }
+
+    /// Associates the data values (e.g. [("x", 1.0), ("y", 2.0)]) of an element drawn
+    /// by a mapping to a position, so interactive outputs can show them on hover. Backends
+    /// that only produce images ignore it.
+    fn data_point(&mut self, _x : f64, _y : f64, _values : &[(&str, f64)]) { }
+
+}
+
+#[cfg(feature="cairo-rs")]
+pub use cairo_backend::CairoBackend;
+
+#[cfg(feature="cairo-rs")]
+mod cairo_backend {
+
+    use cairo::{Context, FontFace, FontSlant, FontWeight, ScaledFont};
+    use crate::color::Color;
+    use std::collections::HashMap;
+    use std::error::Error;
+    use super::{DrawingBackend, TextExtents};
+    use crate::render::text::{self, FontData};
+
+    /// Draws to a cairo context, which might target any cairo surface (image, SVG,
+    /// PDF, Postscript) or the context of a GTK drawing area.
+    pub struct CairoBackend<'a> {
+        ctx : &'a Context,
+
+        // Toy font faces, by family, slant and weight.
+        faces : HashMap<(String, text::FontSlant, text::FontWeight), FontFace>
+    }
+
+    impl<'a> CairoBackend<'a> {
+
+        pub fn new(ctx : &'a Context) -> Self {
+            Self { ctx, faces : HashMap::new() }
+        }
+
+        fn font_face(&mut self, font : &FontData) -> Result<FontFace, Box<dyn Error>> {
+            let key = (font.font_family.clone(), font.font_slant, font.font_weight);
+            if let Some(face) = self.faces.get(&key) {
+                return Ok(face.clone());
+            }
+            let face = FontFace::toy_create(&font.font_family, slant(font), weight(font))?;
+            self.faces.insert(key, face.clone());
+            Ok(face)
+        }
+
+        fn scaled_font(&mut self, font : &FontData) -> Result<ScaledFont, Box<dyn Error>> {
+            let mut font_m = cairo::Matrix::identity();
+            let ctm = cairo::Matrix::identity();
+            font_m.scale(font.font_size as f64, font.font_size as f64);
+            let opts = cairo::FontOptions::new()?;
+            Ok(ScaledFont::new(&self.font_face(font)?, &font_m, &ctm, &opts)?)
+        }
+
+    }
+
+    fn slant(font : &FontData) -> FontSlant {
+        match font.font_slant {
+            text::FontSlant::Normal => FontSlant::Normal,
+            text::FontSlant::Italic => FontSlant::Italic,
+            text::FontSlant::Oblique => FontSlant::Oblique
+        }
+    }
+
+    fn weight(font : &FontData) -> FontWeight {
+        match font.font_weight {
+            text::FontWeight::Normal => FontWeight::Normal,
+            text::FontWeight::Bold => FontWeight::Bold
+        }
+    }
+
+    impl<'a> DrawingBackend for CairoBackend<'a> {
+
+        fn save(&mut self) -> Result<(), Box<dyn Error>> {
+            Ok(self.ctx.save()?)
+        }
+
+        fn restore(&mut self) -> Result<(), Box<dyn Error>> {
+            Ok(self.ctx.restore()?)
+        }
+
+        fn translate(&mut self, x : f64, y : f64) {
+            self.ctx.translate(x, y);
+        }
+
+        fn rotate(&mut self, angle : f64) {
+            self.ctx.rotate(angle);
+        }
+
+        fn scale(&mut self, sx : f64, sy : f64) {
+            self.ctx.scale(sx, sy);
+        }
+
+        fn set_color(&mut self, color : &Color) {
+            self.ctx.set_source_rgba(
+                color.red().into(),
+                color.green().into(),
+                color.blue().into(),
+                color.alpha().into()
+            );
+        }
+
+        fn set_line_width(&mut self, width : f64) {
+            self.ctx.set_line_width(width);
+        }
+
+        fn set_dash(&mut self, dashes : &[f64]) {
+            self.ctx.set_dash(dashes, 0.0);
+        }
+
+        fn move_to(&mut self, x : f64, y : f64) {
+            self.ctx.move_to(x, y);
+        }
+
+        fn line_to(&mut self, x : f64, y : f64) {
+            self.ctx.line_to(x, y);
+        }
+
+        fn arc(&mut self, xc : f64, yc : f64, radius : f64, angle1 : f64, angle2 : f64) {
+            self.ctx.arc(xc, yc, radius, angle1, angle2);
+        }
+
+        fn rectangle(&mut self, x : f64, y : f64, w : f64, h : f64) {
+            self.ctx.rectangle(x, y, w, h);
+        }
+
+        fn close_path(&mut self) {
+            self.ctx.close_path();
+        }
+
+        fn new_sub_path(&mut self) {
+            self.ctx.new_sub_path();
+        }
+
+        fn fill(&mut self) -> Result<(), Box<dyn Error>> {
+            Ok(self.ctx.fill()?)
+        }
+
+        fn fill_preserve(&mut self) -> Result<(), Box<dyn Error>> {
+            Ok(self.ctx.fill_preserve()?)
+        }
+
+        fn stroke(&mut self) -> Result<(), Box<dyn Error>> {
+            Ok(self.ctx.stroke()?)
+        }
+
+        fn clip(&mut self) {
+            self.ctx.clip();
+        }
+
+        fn text_extents(&mut self, font : &FontData, text : &str) -> TextExtents {
+            match self.scaled_font(font) {
+                Ok(sf) => {
+                    let ext = sf.text_extents(text);
+                    TextExtents { x_advance : ext.x_advance(), height : ext.height() }
+                },
+                Err(_) => TextExtents::default()
+            }
+        }
+
+        fn show_text(&mut self, font : &FontData, x : f64, y : f64, text : &str) -> Result<(), Box<dyn Error>> {
+
+            // Positioning the text changes the current path (which is not part of the
+            // saved state), so the path of the caller is recovered afterwards.
+            let path = self.ctx.copy_path()?;
+            let face = self.font_face(font)?;
+            self.ctx.save()?;
+            self.ctx.set_font_face(&face);
+            self.ctx.set_font_size(font.font_size as f64);
+            self.ctx.move_to(x, y);
+            let res = self.ctx.show_text(text);
+            self.ctx.restore()?;
+            self.ctx.new_path();
+            self.ctx.append_path(&path);
+            Ok(res?)
+        }
+
+    }
+
+}
+
+#[cfg(feature="cairo-rs")]
+#[test]
+fn cairo_text_keeps_path() {
+    let surf = cairo::ImageSurface::create(cairo::Format::ARgb32, 50, 50).unwrap();
+    let ctx = cairo::Context::new(&surf).unwrap();
+    let mut backend = CairoBackend::new(&ctx);
+    let font = FontData::new_from_string("Sans 10");
+    backend.move_to(1.0, 2.0);
+    backend.line_to(5.0, 6.0);
+    backend.show_text(&font, 20.0, 20.0, "text").unwrap();
+    assert_eq!(ctx.current_point().unwrap(), (5.0, 6.0));
+    assert_eq!(ctx.copy_path().unwrap().iter().count(), 2);
+    let ext = backend.text_extents(&font, "text");
+    assert!(ext.x_advance > 0.0);
+    assert_eq!(backend.text_extents(&font, "text").x_advance, ext.x_advance);
+}
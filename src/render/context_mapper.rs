@@ -4,6 +4,8 @@ This work is licensed under the terms of the MIT license.
 For a copy, see <https://opensource.org/licenses/MIT>.*/
 
 use std::ops::Add;
+use std::f64::consts::PI;
+use super::backend::DrawingBackend;
 
 pub const REL_X_OFFSET : f64 = 0.12; // 0.1
 
@@ -57,6 +59,15 @@ pub struct ContextMapper {
     pub yext : f64,
     pub w : i32,
     pub h : i32,
+
+    // Extra space (in pixels) reserved above and below the data area,
+    // in addition to the relative offsets (used by titles and captions).
+    pub top_margin : f64,
+    pub bottom_margin : f64,
+
+    // If true, x is mapped to the angle (clockwise from the top) and y to the
+    // distance from the center of the data area.
+    pub polar : bool
 }
 
 impl Default for ContextMapper {
@@ -74,7 +85,10 @@ impl Default for ContextMapper {
             xext : 0.0,
             yext : 0.0,
             w : 800,
-            h : 600
+            h : 600,
+            top_margin : 0.0,
+            bottom_margin : 0.0,
+            polar : false
         };
         mapper.update();
         mapper
@@ -93,7 +107,7 @@ impl ContextMapper {
         let (xext, yext) = ContextMapper::calc_ext(
             xmax, xmin, ymax, ymin, xlog, ylog);
         ContextMapper{ xmin, xmax, ymin, ymax,
-        xext, yext, w, h, xlog, ylog, xinv, yinv}
+        xext, yext, w, h, xlog, ylog, xinv, yinv, top_margin : 0.0, bottom_margin : 0.0, polar : false }
     }
 
     pub fn update(&mut self) {
@@ -116,6 +130,27 @@ impl ContextMapper {
         self.update();
     }
 
+    pub fn update_margins(&mut self, top : f64, bottom : f64) {
+        self.top_margin = top;
+        self.bottom_margin = bottom;
+    }
+
+    /// Returns the top-left corner, width and height of the data area, in pixels.
+    pub fn data_area(&self) -> (f64, f64, f64, f64) {
+        let padw = REL_X_OFFSET*(self.w as f64);
+        let padh = REL_Y_OFFSET*(self.h as f64);
+        let dataw = (self.w as f64) - 2.0*padw;
+        let datah = (self.h as f64) - 2.0*padh - self.top_margin - self.bottom_margin;
+        (padw, padh + self.top_margin, dataw, datah)
+    }
+
+    /// Returns the center and radius of the circle inscribed in the data area,
+    /// which is the region used at polar coordinates.
+    pub fn polar_area(&self) -> (f64, f64, f64) {
+        let (x, y, w, h) = self.data_area();
+        (x + w / 2.0, y + h / 2.0, w.min(h) / 2.0)
+    }
+
     pub fn calc_ext(xmax : f64, xmin : f64, ymax : f64, ymin : f64,
         xlog : bool, ylog : bool) -> (f64, f64) {
         let xext = match xlog {
@@ -142,24 +177,30 @@ impl ContextMapper {
         // but we sould increase it in either or both the horizontal
         // or vertical dimension if they are shared by more than one plot
         // (to leave enough room for labels under the minimum aspect ratio).
-        let padw = REL_X_OFFSET*(self.w as f64);
-        let padh = REL_Y_OFFSET*(self.h as f64);
-        let dataw = (self.w as f64) - 2.0*padw;
-        let datah = (self.h as f64) - 2.0*padh;
+        let (padw, padh, dataw, datah) = self.data_area();
         let xprop = match (self.xlog, self.xinv) {
             (false, false) => (x - self.xmin) / self.xext,
             (false, true)  => (self.xmax - x) / self.xext,
             (true, false)  => (x.log10() - self.xmin.log10()) / self.xext,
             (true, true)   => (self.xmax.log10() - x.log10()) / self.xext
         };
-        let yprop = 1.0 - match (self.ylog, self.yinv) { 
+        let yprop = match (self.ylog, self.yinv) {
             (false, false) => (y - self.ymin) / self.yext,
             (false, true)  => (self.ymax - y) / self.yext,
             (true, false)  => (y.log10() - self.ymin.log10()) / self.yext,
             (true, true)   => (self.ymax.log10() - y.log10()) / self.yext
         };
 
-        Coord2D::new(padw + dataw*xprop, padh + datah*yprop)
+        if self.polar {
+            // Values below the radius scale start are mapped to the center, instead
+            // of being mirrored to the opposite angle (missing values remain NaN).
+            let (cx, cy, r) = self.polar_area();
+            let angle = 2.0*PI*xprop;
+            let radius = if yprop < 0.0 { 0.0 } else { r*yprop };
+            return Coord2D::new(cx + radius*angle.sin(), cy - radius*angle.cos());
+        }
+
+        Coord2D::new(padw + dataw*xprop, padh + datah*(1.0 - yprop))
     }
 
     pub fn check_bounds(&self, x : f64, y : f64) -> bool {
@@ -168,6 +209,30 @@ impl ContextMapper {
         x_ok && y_ok
     }
 
+    /// Verifies if the point maps to a finite coordinate, which is not the case
+    /// for missing values or non-positive values at logarithmic scales. Unlike
+    /// check_bounds, points outside the data area are accepted.
+    pub fn check_mappable(&self, x : f64, y : f64) -> bool {
+        let c = self.map(x, y);
+        c.x.is_finite() && c.y.is_finite()
+    }
+
+    /// Restricts drawing to the data area (or the polar area, at polar coordinates),
+    /// extended by margin pixels at each side,
+    /// so partially visible geometry is cut at the plot boundary. Callers should
+    /// save and restore the drawing state around the clipped drawing.
+    pub fn clip(&self, ctx : &mut dyn DrawingBackend, margin : f64) {
+        if self.polar {
+            let (cx, cy, r) = self.polar_area();
+            ctx.new_sub_path();
+            ctx.arc(cx, cy, r + margin, 0.0, 2.0*PI);
+        } else {
+            let (x, y, w, h) = self.data_area();
+            ctx.rectangle(x - margin, y - margin, w + 2.0*margin, h + 2.0*margin);
+        }
+        ctx.clip();
+    }
+
     pub fn coord_bounds(&self) -> (Coord2D, Coord2D, Coord2D, Coord2D) {
         (
             self.map(self.xmin, self.ymin),
@@ -239,3 +304,22 @@ fn scales() {
     println!("{:?}", round_to_most_extreme(10201.0, 11101.0));
     println!("{:?}", round_to_most_extreme(612.0, 625.0));
 }
+
+#[test]
+fn polar_mapping() {
+    let mut mapper = ContextMapper::new(0.0, 1.0, 0.0, 1.0, false, false, false, false);
+    mapper.polar = true;
+    mapper.update_dimensions(200, 100);
+    let (cx, cy, r) = mapper.polar_area();
+    let close = |c : Coord2D, x : f64, y : f64| (c.x - x).abs() < 1e-9 && (c.y - y).abs() < 1e-9;
+
+    // Angles start at the top and run clockwise; radii start at the center.
+    assert!(close(mapper.map(0.0, 1.0), cx, cy - r));
+    assert!(close(mapper.map(0.25, 1.0), cx + r, cy));
+    assert!(close(mapper.map(0.5, 0.5), cx, cy + r / 2.0));
+    assert!(close(mapper.map(0.75, 0.0), cx, cy));
+
+    // Radii below the scale start are not mirrored to the opposite angle.
+    assert!(close(mapper.map(0.25, -1.0), cx, cy));
+    assert!(!mapper.check_mappable(f64::NAN, 0.5));
+}
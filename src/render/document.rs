@@ -0,0 +1,147 @@
+/*Copyright (c) 2022 Diego da Silva Lima. All rights reserved.
+
+This work is licensed under the terms of the MIT license.  
+For a copy, see <https://opensource.org/licenses/MIT>.*/
+
+use cairo::{Context, PdfSurface, PdfMetadata};
+use std::error::Error;
+use std::path::Path;
+use super::{Panel, FileError};
+
+/// A sequence of panels written as the pages of a single PDF document, with
+/// optional document metadata. Each page has the dimensions of its panel.
+#[derive(Debug, Clone, Default)]
+pub struct Document {
+
+    pages : Vec<Panel>,
+
+    title : Option<String>,
+
+    author : Option<String>,
+
+    subject : Option<String>,
+
+    // ISO-8601 date (YYYY-MM-DDThh:mm:ss, optionally followed by Z or
+    // an UTC offset). Cairo uses the current date when this is absent.
+    creation_date : Option<String>
+
+}
+
+impl Document {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn page(mut self, panel : Panel) -> Self {
+        self.pages.push(panel);
+        self
+    }
+
+    pub fn pages(mut self, panels : impl IntoIterator<Item=Panel>) -> Self {
+        self.pages.extend(panels);
+        self
+    }
+
+    pub fn title(mut self, title : &str) -> Self {
+        self.title = Some(title.to_string());
+        self
+    }
+
+    pub fn author(mut self, author : &str) -> Self {
+        self.author = Some(author.to_string());
+        self
+    }
+
+    pub fn subject(mut self, subject : &str) -> Self {
+        self.subject = Some(subject.to_string());
+        self
+    }
+
+    pub fn creation_date(mut self, date : &str) -> Self {
+        self.creation_date = Some(date.to_string());
+        self
+    }
+
+    pub fn n_pages(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// Renders the document to an in-memory PDF.
+    pub fn pdf(&mut self) -> Result<Vec<u8>, Box<dyn Error>> {
+        let (w, h) = self.first_page_dimensions()?;
+        let surf = PdfSurface::for_stream(w, h, Vec::<u8>::new())
+            .map_err(|e| format!("Error creating PDF surface: {}", e) )?;
+        self.draw_pages(&surf)?;
+        let stream = surf.finish_output_stream()
+            .map_err(|e| format!("Error writing PDF content: {}", e) )?;
+        Ok(stream.downcast_ref::<Vec<u8>>().cloned().unwrap_or_default())
+    }
+
+    pub fn draw_to_file(&mut self, path : &str) -> Result<(), Box<dyn Error>> {
+        let path = Path::new(path);
+        if !path.parent().map(|par| par.exists() ).unwrap_or(false) {
+            Err(FileError(format!("Parent directory for document path {} does not exists", path.to_str().unwrap())))?;
+        }
+        let (w, h) = self.first_page_dimensions()?;
+        let surf = PdfSurface::new(w, h, path)
+            .map_err(|e| FileError(format!("Error creating PDF surface: {}", e) ))?;
+        self.draw_pages(&surf)?;
+        surf.finish();
+        Ok(())
+    }
+
+    fn first_page_dimensions(&self) -> Result<(f64, f64), Box<dyn Error>> {
+        let first = self.pages.first()
+            .ok_or_else(|| FileError(String::from("Document does not have any pages")) )?;
+        Ok((first.dimensions.0 as f64, first.dimensions.1 as f64))
+    }
+
+    fn draw_pages(&mut self, surf : &PdfSurface) -> Result<(), Box<dyn Error>> {
+        surf.set_metadata(PdfMetadata::Creator, "papyri")?;
+        let meta = [
+            (PdfMetadata::Title, &self.title),
+            (PdfMetadata::Author, &self.author),
+            (PdfMetadata::Subject, &self.subject),
+            (PdfMetadata::CreateDate, &self.creation_date)
+        ];
+        for (key, value) in meta {
+            if let Some(value) = value {
+                surf.set_metadata(key, value)?;
+            }
+        }
+        let ctx = Context::new(surf)?;
+        for panel in self.pages.iter_mut() {
+            let (w, h) = panel.dimensions;
+
+            // The page size must be set before anything is drawn to the page.
+            surf.set_size(w as f64, h as f64)?;
+            panel.draw_to_context(&ctx, 0, 0, w as i32, h as i32)?;
+            ctx.show_page()?;
+        }
+        Ok(())
+    }
+
+}
+
+#[test]
+fn multi_page_metadata() {
+    let page = |w, h| Panel::new_from_json(r#"{"kind":"line","map":{"x":[0,1],"y":[0,1]}}"#).unwrap().dimensions(w, h);
+    let mut doc = Document::new()
+        .page(page(400, 300))
+        .pages([page(200, 100), page(400, 300)])
+        .title("Report")
+        .author("Someone")
+        .subject("Tests")
+        .creation_date("2022-01-02T03:04:05Z");
+    assert_eq!(doc.n_pages(), 3);
+    let pdf = String::from_utf8_lossy(&doc.pdf().unwrap()).to_string();
+    assert_eq!(pdf.matches("/Type /Page ").count(), 3);
+    assert!(pdf.contains("/Count 3"));
+    let boxes : Vec<_> = pdf.match_indices("/MediaBox [ 0 0 ").map(|(ix, _)| pdf[ix..].split(']').next().unwrap() ).collect();
+    assert_eq!(boxes, ["/MediaBox [ 0 0 400 300 ", "/MediaBox [ 0 0 200 100 ", "/MediaBox [ 0 0 400 300 "]);
+    for meta in ["/Title (Report)", "/Author (Someone)", "/Subject (Tests)", "/Creator (papyri)", "/CreationDate (20220102030405Z)"] {
+        assert!(pdf.contains(meta), "{} not found", meta);
+    }
+    assert!(Document::new().pdf().is_err());
+}
@@ -0,0 +1,188 @@
+/*Copyright (c) 2022 Diego da Silva Lima. All rights reserved.
+
+This work is licensed under the terms of the MIT license.  
+For a copy, see <https://opensource.org/licenses/MIT>.*/
+
+use std::error::Error;
+use super::Panel;
+use super::svg::escape;
+
+const STYLE : &str = r##"
+body { font-family: sans-serif; margin: 1em; }
+.papyri { position: relative; display: inline-block; }
+.papyri svg { display: block; cursor: crosshair; user-select: none; }
+.papyri .mapping path, .papyri .mapping text { pointer-events: none; }
+.papyri .datum:hover { fill-opacity: 0.35; }
+.papyri .hidden { display: none; }
+.papyri-band { fill: rgba(52, 101, 164, 0.15); stroke: #3465a4; vector-effect: non-scaling-stroke; }
+.papyri-tooltip { position: absolute; display: none; pointer-events: none; white-space: pre; font-size: 12px;
+    background: rgba(255, 255, 255, 0.95); border: 1px solid #888; border-radius: 3px; padding: 2px 6px; }
+.papyri-legend { list-style: none; padding: 0; margin: 0.5em 0; }
+.papyri-legend li { display: inline-block; margin-right: 1em; cursor: pointer; }
+.papyri-legend li.off { opacity: 0.4; text-decoration: line-through; }
+.papyri-legend .swatch { display: inline-block; width: 0.8em; height: 0.8em; margin-right: 0.3em; vertical-align: middle; }
+"##;
+
+// Reads the structure of the SVG written by Panel::semantic_svg: mapping groups (g.mapping)
+// and their <title>, and the data-* attributes of the .datum elements.
+const SCRIPT : &str = r##"
+(function() {
+    var root = document.currentScript.previousElementSibling;
+    var svg = root.querySelector("svg");
+    var tooltip = root.querySelector(".papyri-tooltip");
+    var legend = root.querySelector(".papyri-legend");
+    var initial = svg.getAttribute("viewBox");
+
+    function title(group) {
+        var t = group ? group.querySelector(":scope > title") : null;
+        return t ? t.textContent : "";
+    }
+
+    // Legend entries toggle the visibility of each mapping. The swatch takes the color of
+    // the first element painted by the mapping.
+    svg.querySelectorAll("g.mapping").forEach(function(group) {
+        var item = document.createElement("li");
+        var swatch = document.createElement("span");
+        var shape = group.querySelector("path, text");
+        var color = "#888";
+        if (shape) {
+            var fill = shape.getAttribute("fill");
+            color = (fill && fill !== "none") ? fill : (shape.getAttribute("stroke") || color);
+        }
+        swatch.className = "swatch";
+        swatch.style.background = color;
+        item.appendChild(swatch);
+        var plot = title(group.closest("g.plot"));
+        item.appendChild(document.createTextNode((plot ? plot + ": " : "") + title(group)));
+        item.addEventListener("click", function() {
+            group.classList.toggle("hidden");
+            item.classList.toggle("off");
+        });
+        legend.appendChild(item);
+    });
+
+    function moveTooltip(ev) {
+        var rect = root.getBoundingClientRect();
+        tooltip.style.left = (ev.clientX - rect.left + 12) + "px";
+        tooltip.style.top = (ev.clientY - rect.top + 12) + "px";
+    }
+    svg.addEventListener("mouseover", function(ev) {
+        if (!ev.target.classList.contains("datum")) {
+            return;
+        }
+        var lines = [title(ev.target.closest("g.mapping"))];
+        for (var key in ev.target.dataset) {
+            lines.push(key + ": " + ev.target.dataset[key]);
+        }
+        tooltip.textContent = lines.join("\n");
+        tooltip.style.display = "block";
+        moveTooltip(ev);
+    });
+    svg.addEventListener("mousemove", function(ev) {
+        if (ev.target.classList.contains("datum")) {
+            moveTooltip(ev);
+        }
+    });
+    svg.addEventListener("mouseout", function(ev) {
+        if (ev.target.classList.contains("datum")) {
+            tooltip.style.display = "none";
+        }
+    });
+
+    // Box-zoom sets the view box to the dragged rectangle.
+    var start = null;
+    var band = null;
+    function svgPoint(ev) {
+        var p = svg.createSVGPoint();
+        p.x = ev.clientX;
+        p.y = ev.clientY;
+        return p.matrixTransform(svg.getScreenCTM().inverse());
+    }
+    function bandRect(p) {
+        return [Math.min(start.x, p.x), Math.min(start.y, p.y), Math.abs(p.x - start.x), Math.abs(p.y - start.y)];
+    }
+    svg.addEventListener("mousedown", function(ev) {
+        if (ev.button !== 0) {
+            return;
+        }
+        start = svgPoint(ev);
+        band = document.createElementNS("http://www.w3.org/2000/svg", "rect");
+        band.setAttribute("class", "papyri-band");
+        svg.appendChild(band);
+        ev.preventDefault();
+    });
+    window.addEventListener("mousemove", function(ev) {
+        if (!start) {
+            return;
+        }
+        var r = bandRect(svgPoint(ev));
+        band.setAttribute("x", r[0]);
+        band.setAttribute("y", r[1]);
+        band.setAttribute("width", r[2]);
+        band.setAttribute("height", r[3]);
+    });
+    window.addEventListener("mouseup", function(ev) {
+        if (!start) {
+            return;
+        }
+        var r = bandRect(svgPoint(ev));
+        svg.removeChild(band);
+        start = null;
+        band = null;
+        if (r[2] > 4 && r[3] > 4) {
+            svg.setAttribute("viewBox", r.join(" "));
+        }
+    });
+    function reset() {
+        svg.setAttribute("viewBox", initial);
+    }
+    root.querySelector(".papyri-reset").addEventListener("click", reset);
+    svg.addEventListener("dblclick", reset);
+})();
+"##;
+
+impl Panel {
+
+    /// Renders the panel to a self-contained HTML document, with the semantic SVG inline
+    /// and a small embedded script, which does not require network access. Hovering a data point
+    /// shows its values, clicking a legend entry toggles its mapping, and dragging over
+    /// the plot zooms to the selected box (reset by the button or a double click).
+    pub fn html_interactive(&mut self) -> Result<String, Box<dyn Error>> {
+        let mut writer = self.svg_writer().data_points(true);
+        self.draw_to_backend(&mut writer, 0, 0, self.dimensions.0 as i32, self.dimensions.1 as i32)?;
+        let svg = writer.finish();
+
+        // The XML declaration is not valid inside HTML.
+        let svg = svg.split_once('\n').map(|(_, body)| body ).unwrap_or(&svg[..]);
+        let title = escape(self.title.as_deref().unwrap_or("Plot"));
+        let mut html = String::new();
+        html += "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n";
+        html += &format!("<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n", title, STYLE);
+        html += "<div class=\"papyri\">\n";
+        html += "<div class=\"papyri-toolbar\"><button type=\"button\" class=\"papyri-reset\">Reset zoom</button></div>\n";
+        html += svg;
+        html += "<ul class=\"papyri-legend\"></ul>\n<div class=\"papyri-tooltip\"></div>\n</div>\n";
+        html += &format!("<script>{}</script>\n</body>\n</html>\n", SCRIPT);
+        Ok(html)
+    }
+
+}
+
+#[test]
+fn html_interactive_document() {
+    let mut panel = Panel::new_from_json(r#"{"kind":"scatter","map":{"x":[0,1,2],"y":[0,1,4]}}"#).unwrap()
+        .title("A & B");
+    let html = panel.html_interactive().unwrap();
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<title>A &amp; B</title>"));
+    assert!(!html.contains("<?xml"));
+    assert_eq!(html.matches("<svg ").count(), 1);
+    assert_eq!(html.matches("class=\"datum\"").count(), 3);
+    assert!(html.contains("data-x=\"2\" data-y=\"4\""));
+    assert!(html.contains("class=\"mapping scatter\""));
+    assert!(html.contains("<ul class=\"papyri-legend\"></ul>"));
+    assert!(html.trim_end().ends_with("</script>\n</body>\n</html>"));
+
+    // Data points are only annotated in interactive documents.
+    assert!(!panel.semantic_svg().unwrap().contains("datum"));
+}
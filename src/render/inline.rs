@@ -0,0 +1,207 @@
+/*Copyright (c) 2022 Diego da Silva Lima. All rights reserved.
+
+This work is licensed under the terms of the MIT license.  
+For a copy, see <https://opensource.org/licenses/MIT>.*/
+
+use std::error::Error;
+use std::str::FromStr;
+use std::fmt::Write;
+use super::{Panel, RgbaOptions, AlphaMode};
+
+// Kitty limits the payload of each escape sequence to 4096 bytes of base64 data.
+const KITTY_CHUNK : usize = 4096;
+
+// Levels of each channel at the sixel color cube (6x6x6 = 216 color registers).
+const SIXEL_LEVELS : u32 = 6;
+
+/// Graphics protocols for showing images inline at terminal emulators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InlineProtocol {
+
+    /// DEC Sixel (xterm -ti vt340, mlterm, foot, WezTerm, mintty and others).
+    Sixel,
+
+    /// Kitty graphics protocol (kitty, WezTerm, Konsole, ghostty).
+    Kitty
+}
+
+impl FromStr for InlineProtocol {
+
+    type Err = ();
+
+    fn from_str(s : &str) -> Result<Self, ()> {
+        match s {
+            "sixel" => Ok(Self::Sixel),
+            "kitty" => Ok(Self::Kitty),
+            _ => Err(())
+        }
+    }
+
+}
+
+impl InlineProtocol {
+
+    /// Guesses the protocol supported by the current terminal from the environment
+    /// (TERM, TERM_PROGRAM and variables set by specific terminals). Returns None
+    /// if the terminal is not known to support inline images.
+    pub fn detect() -> Option<Self> {
+        let var = |name : &str| std::env::var(name).unwrap_or_default().to_lowercase();
+        let term = var("TERM");
+        let program = var("TERM_PROGRAM");
+        let kitty = std::env::var_os("KITTY_WINDOW_ID").is_some() || term.contains("kitty") || term.contains("ghostty");
+        let kitty_compatible = program == "ghostty" || program == "wezterm" || std::env::var_os("KONSOLE_VERSION").is_some();
+        if kitty || kitty_compatible {
+            Some(Self::Kitty)
+        } else if term.contains("sixel") || term.contains("mlterm") || term.starts_with("foot") || term.contains("yaft") || program == "mintty" {
+            Some(Self::Sixel)
+        } else {
+            None
+        }
+    }
+
+}
+
+/// Encodes a PNG image as a Kitty graphics protocol escape sequence, which displays
+/// it at the cursor position.
+pub fn encode_kitty(png : &[u8]) -> String {
+    let data = base64::encode(png);
+    let chunks : Vec<&[u8]> = data.as_bytes().chunks(KITTY_CHUNK).collect();
+    let mut out = String::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = if i + 1 < chunks.len() { 1 } else { 0 };
+
+        // Only the first chunk carries the image format (f=100 for PNG) and action (a=T to transmit and display).
+        if i == 0 {
+            let _ = write!(out, "\x1b_Gf=100,a=T,m={};", more);
+        } else {
+            let _ = write!(out, "\x1b_Gm={};", more);
+        }
+        out += std::str::from_utf8(chunk).unwrap_or_default();
+        out += "\x1b\\";
+    }
+    out
+}
+
+/// Encodes straight-alpha RGBA pixels (4 bytes per pixel, rows not padded) as a DEC Sixel
+/// escape sequence. Colors are quantized to a 6x6x6 color cube, and translucent pixels
+/// are blended over white.
+pub fn encode_sixel(rgba : &[u8], width : usize, height : usize) -> String {
+    let levels = SIXEL_LEVELS;
+    let index_of = |px : &[u8]| -> usize {
+        let a = px[3] as u32;
+        let q = |c : u8| {
+            let blended = (c as u32 * a + 255 * (255 - a)) / 255;
+            (blended * (levels - 1) + 127) / 255
+        };
+        ((q(px[0]) * levels + q(px[1])) * levels + q(px[2])) as usize
+    };
+    let indices : Vec<usize> = rgba.chunks_exact(4).take(width * height).map(index_of).collect();
+
+    let mut out = String::new();
+    let _ = write!(out, "\x1bPq\"1;1;{};{}", width, height);
+
+    // Only registers used by the image are defined, with channels as percentages.
+    let mut used = vec![false; (levels * levels * levels) as usize];
+    for ix in indices.iter() {
+        used[*ix] = true;
+    }
+    for (ix, _) in used.iter().enumerate().filter(|(_, u)| **u ) {
+        let ix = ix as u32;
+        let pct = |level : u32| level * 100 / (levels - 1);
+        let _ = write!(out, "#{};2;{};{};{}", ix, pct(ix / (levels * levels)), pct((ix / levels) % levels), pct(ix % levels));
+    }
+
+    // Each band covers six rows, and is drawn once for each color it contains.
+    for band in (0..height).step_by(6) {
+        let rows = (height - band).min(6);
+        let mut colors : Vec<usize> = (band..band + rows)
+            .flat_map(|y| indices[y*width..(y+1)*width].iter().cloned() )
+            .collect();
+        colors.sort_unstable();
+        colors.dedup();
+        for (i, color) in colors.iter().enumerate() {
+            if i > 0 {
+                out += "$";
+            }
+            let _ = write!(out, "#{}", color);
+            let sixels = (0..width).map(|x| {
+                let mut bits = 0u8;
+                for dy in 0..rows {
+                    if indices[(band + dy)*width + x] == *color {
+                        bits |= 1 << dy;
+                    }
+                }
+                (63 + bits) as char
+            });
+            write_run_length(&mut out, sixels);
+        }
+        out += "-";
+    }
+    out += "\x1b\\";
+    out
+}
+
+// Writes repeated sixels as !<count><sixel>.
+fn write_run_length(out : &mut String, sixels : impl Iterator<Item=char>) {
+    let mut run : Option<(char, usize)> = None;
+    let flush = |out : &mut String, ch : char, n : usize| {
+        if n > 3 {
+            let _ = write!(out, "!{}{}", n, ch);
+        } else {
+            for _ in 0..n {
+                out.push(ch);
+            }
+        }
+    };
+    for ch in sixels {
+        run = match run {
+            Some((prev, n)) if prev == ch => Some((prev, n + 1)),
+            Some((prev, n)) => {
+                flush(out, prev, n);
+                Some((ch, 1))
+            },
+            None => Some((ch, 1))
+        };
+    }
+    if let Some((ch, n)) = run {
+        flush(out, ch, n);
+    }
+}
+
+impl Panel {
+
+    /// Renders the panel at its raster dimensions as a DEC Sixel escape sequence.
+    pub fn sixel(&mut self) -> Result<String, Box<dyn Error>> {
+        let (w, h) = self.raster_dimensions();
+        let opts = RgbaOptions::default().alpha(AlphaMode::Straight).scale(self.scale_factor);
+        let (buf, _) = self.render_rgba_with(self.dimensions.0 as u32, self.dimensions.1 as u32, opts)?;
+        Ok(encode_sixel(&buf, w as usize, h as usize))
+    }
+
+    /// Renders the panel as PNG, wrapped in a Kitty graphics protocol escape sequence.
+    pub fn kitty(&mut self) -> Result<String, Box<dyn Error>> {
+        Ok(encode_kitty(&self.png()?))
+    }
+
+    /// Renders the panel as an escape sequence of the informed terminal graphics protocol.
+    pub fn inline_image(&mut self, protocol : InlineProtocol) -> Result<String, Box<dyn Error>> {
+        match protocol {
+            InlineProtocol::Sixel => self.sixel(),
+            InlineProtocol::Kitty => self.kitty()
+        }
+    }
+
+}
+
+#[test]
+fn encode_inline_images() {
+    // A 2x2 image with red pixels at the top row and transparent pixels at the bottom row.
+    let rgba = [255, 0, 0, 255, 255, 0, 0, 255, 0, 0, 0, 0, 0, 0, 0, 0];
+    assert_eq!(
+        encode_sixel(&rgba, 2, 2),
+        "\x1bPq\"1;1;2;2#180;2;100;0;0#215;2;100;100;100#180@@$#215AA-\x1b\\"
+    );
+    let kitty = encode_kitty(&[0u8; 4000]);
+    assert!(kitty.starts_with("\x1b_Gf=100,a=T,m=1;"));
+    assert!(kitty.contains("\x1b\\\x1b_Gm=0;"));
+}
@@ -3,12 +3,11 @@
 This work is licensed under the terms of the MIT license.  
 For a copy, see <https://opensource.org/licenses/MIT>.*/
 
-use gdk4::RGBA;
-use cairo::Context;
+use crate::color::Color;
+use super::super::backend::DrawingBackend;
 use super::super::context_mapper::ContextMapper;
 use std::collections::HashMap;
 use super::*;
-use std::cmp::*;
 use super::super::MappingProperty;
 use std::borrow::Borrow;
 
@@ -17,7 +16,7 @@ pub struct AreaMapping {
     x : Vec<f64>,
     ymin : Vec<f64>,
     ymax : Vec<f64>,
-    color : RGBA,
+    color : Color,
     col_names : [String; 3],
     source : String
 }
@@ -26,7 +25,7 @@ impl Default for AreaMapping {
 
     fn default() -> Self {
         Self {
-            color : RGBA::BLACK,
+            color : Color::BLACK,
             x : Vec::new(),
             ymin : Vec::new(),
             ymax : Vec::new(),
@@ -59,17 +58,15 @@ impl AreaMapping {
     pub fn draw_bound<'a>(
         pts : impl Iterator<Item = ((&'a f64, &'a f64), (&'a f64, &'a f64))>,
         mapper : &ContextMapper,
-        ctx : &Context
+        ctx : &mut dyn DrawingBackend
     ) {
         for ((x0, y0), (x1, y1)) in pts {
-            let bounds_ok = mapper.check_bounds(*x0, *y0) &&
-                mapper.check_bounds(*x1, *y1);
+            // Out-of-bounds vertices are kept, since the area is clipped to the data area.
+            let bounds_ok = mapper.check_mappable(*x0, *y0) &&
+                mapper.check_mappable(*x1, *y1);
             if bounds_ok {
-                let _from = mapper.map(*x0, *y0);
                 let to   = mapper.map(*x1, *y1);
                 ctx.line_to(to.x, to.y);
-            } else {
-                // eprintln!("Out of bounds mapping");
             }
         }
     }
@@ -99,43 +96,51 @@ impl Mapping for AreaMapping {
         super::update_data_triplet_from_json(&mut self.x, &mut self.ymin, &mut self.ymax, rep);
     }
 
+    fn to_model(&self) -> crate::model::Mapping {
+        crate::model::Mapping {
+            kind : self.mapping_type(),
+            map : crate::model::Map {
+                x : Some(self.x.clone()),
+                y : Some(self.ymin.clone()),
+                z : Some(self.ymax.clone()),
+                ..Default::default()
+            },
+            color : Some(self.color.to_string()),
+            ..Default::default()
+        }
+    }
+
     // Mapping-specific impl.
-    fn draw(&self, mapper : &ContextMapper, ctx : &Context) -> Result<(), Box<dyn Error>> {
+    fn draw(&self, mapper : &ContextMapper, ctx : &mut dyn DrawingBackend) -> Result<(), Box<dyn Error>> {
         ctx.save()?;
-        ctx.set_source_rgba(
-            self.color.red().into(),
-            self.color.green().into(),
-            self.color.blue().into(),
-            self.color.alpha().into()
-        );
-        ctx.set_fill_rule(cairo::FillRule::Winding);
-        if self.x.len() == 0 {
-            ctx.restore()?;
-            return Ok(());
-        }
-        let pt0 = mapper.map(self.x[0], self.ymin[0]);
-        ctx.move_to(pt0.x, pt0.y);
-        let zip_xy0 = self.x.iter().zip(self.ymin.iter());
-        let zip_xy1 = self.x.iter().skip(1).zip(self.ymin.iter().skip(1));
-        AreaMapping::draw_bound(zip_xy0.zip(zip_xy1), mapper, ctx);
-        match (self.x.last(), self.ymin.last(), self.ymax.last()) {
-            (Some(x), Some(_ymin), Some(ymax)) => {
-                let to = mapper.map(*x, *ymax);
+        ctx.set_color(&self.color);
+        mapper.clip(ctx, 0.0);
+
+        // The area is broken at missing values, so each run of valid points
+        // is filled as a separate polygon.
+        for run in valid_runs(&[&self.x[..], &self.ymin[..], &self.ymax[..]]) {
+            let (x, ymin, ymax) = (&self.x[run.clone()], &self.ymin[run.clone()], &self.ymax[run]);
+            let pt0 = mapper.map(x[0], ymin[0]);
+            ctx.move_to(pt0.x, pt0.y);
+            let zip_xy0 = x.iter().zip(ymin.iter());
+            let zip_xy1 = x.iter().skip(1).zip(ymin.iter().skip(1));
+            AreaMapping::draw_bound(zip_xy0.zip(zip_xy1), mapper, ctx);
+            if let (Some(last_x), Some(last_ymax)) = (x.last(), ymax.last()) {
+                let to = mapper.map(*last_x, *last_ymax);
                 ctx.line_to(to.x, to.y);
-            },
-            _ => {
-                ctx.restore()?;
-                return Ok(());
+            }
+            let zip_xy0_rev = x.iter().rev().zip(ymax.iter().rev());
+            let zip_xy1_rev = x.iter().rev().skip(1).zip(ymax.iter().rev().skip(1));
+            AreaMapping::draw_bound(zip_xy0_rev.zip(zip_xy1_rev), mapper, ctx);
+            let pt = mapper.map(x[0], ymin[0]);
+            ctx.line_to(pt.x, pt.y);
+            ctx.close_path();
+            ctx.fill()?;
+            for ((x, ymin), ymax) in x.iter().zip(ymin.iter()).zip(ymax.iter()) {
+                let pt = mapper.map(*x, *ymax);
+                ctx.data_point(pt.x, pt.y, &[("x", *x), ("ymin", *ymin), ("ymax", *ymax)]);
             }
         }
-        let zip_xy0_rev = self.x.iter().rev().zip(self.ymax.iter().rev());
-        let zip_xy1_rev = self.x.iter().rev().skip(1).zip(self.ymax.iter().rev().skip(1));
-        AreaMapping::draw_bound(zip_xy0_rev.zip(zip_xy1_rev), mapper, ctx);
-        let _pt0 = mapper.map(self.x[0], self.ymax[0]);
-        let pt = mapper.map(self.x[0], self.ymin[0]);
-        ctx.line_to(pt.x, pt.y);
-        ctx.close_path();
-        ctx.fill()?;
         ctx.restore()?;
         Ok(())
     }
@@ -209,11 +214,10 @@ impl Mapping for AreaMapping {
     }
 
     fn data_limits(&self) -> Option<((f64, f64), (f64, f64))> {
-        let xmin = self.x.iter().min_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal) )?;
-        let xmax = self.x.iter().max_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal))?;
-        let ymin = self.ymin.iter().min_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal))?;
-        let ymax = self.ymax.iter().max_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal))?;
-        Some(((*xmin, *xmax), (*ymin, *ymax)))
+        let (xmin, xmax) = data_range(&self.x[..])?;
+        let (ymin, _) = data_range(&self.ymin[..])?;
+        let (_, ymax) = data_range(&self.ymax[..])?;
+        Some(((xmin, xmax), (ymin, ymax)))
     }
 }
 
@@ -4,8 +4,8 @@ This work is licensed under the terms of the MIT license.
 For a copy, see <https://opensource.org/licenses/MIT>.*/
 
 // use libxml::tree::node::Node;
-use gdk4::RGBA;
-use cairo::Context;
+use crate::color::Color;
+use super::super::backend::DrawingBackend;
 use super::super::context_mapper::ContextMapper;
 use std::collections::HashMap;
 use super::*;
@@ -17,7 +17,7 @@ use std::borrow::Borrow;
 /// is fully determined by its order in the data array, the informed offset and bar spacing (at data scale).
 #[derive(Debug, Clone)]
 pub struct BarMapping {
-    color : RGBA,
+    color : Color,
     center_anchor : bool,
 
     // x and y hold the coordinates of the bar base, increasing at a fixed
@@ -52,7 +52,7 @@ impl Default for BarMapping {
 
     fn default() -> Self {
         let mut bar = Self {
-            color : RGBA::BLACK,
+            color : Color::BLACK,
             x : Vec::new(),
             y : Vec::new(),
             h : Vec::new(),
@@ -216,18 +216,41 @@ impl Mapping for BarMapping {
         self.update_data(vec![new_data]);
     }
 
-    fn draw(&self, mapper : &ContextMapper, ctx : &Context) -> Result<(), Box<dyn Error>> {
+    fn to_model(&self) -> crate::model::Mapping {
+
+        // The mapped data is held at w or h (see update_data), and the origin at the
+        // coordinate set by update_from_json.
+        let (data, origin) = if self.horizontal {
+            (self.w.clone(), self.origin.1)
+        } else {
+            (self.h.clone(), self.origin.0)
+        };
+        crate::model::Mapping {
+            kind : self.mapping_type(),
+            map : crate::model::Map { x : Some(data), ..Default::default() },
+            color : Some(self.color.to_string()),
+            width : Some(self.bar_width),
+            spacing : Some(self.bar_spacing),
+            vertical : Some(!self.horizontal),
+            center : Some(self.center_anchor),
+            origin : Some(origin),
+            ..Default::default()
+        }
+    }
+
+    fn draw(&self, mapper : &ContextMapper, ctx : &mut dyn DrawingBackend) -> Result<(), Box<dyn Error>> {
         ctx.save()?;
-        ctx.set_source_rgb(self.color.red().into(), self.color.green().into(), self.color.blue().into());
+        ctx.set_color(&self.color.with_alpha(1.0));
+        mapper.clip(ctx, 0.0);
         let r_iter = self.x.iter().zip(self.y.iter()
             .zip(self.w.iter()
             .zip(self.h.iter()))
         );
         for (x, (y, (w, h))) in r_iter {
-            let tl_ok = mapper.check_bounds(*x, y + h);
-            let tr_ok = mapper.check_bounds(x + w, y + h);
-            let bl_ok = mapper.check_bounds(*x, *y);
-            let br_ok = mapper.check_bounds(x + *w, *y);
+            let tl_ok = mapper.check_mappable(*x, y + h);
+            let tr_ok = mapper.check_mappable(x + w, y + h);
+            let bl_ok = mapper.check_mappable(*x, *y);
+            let br_ok = mapper.check_mappable(x + *w, *y);
             if  tl_ok && tr_ok && bl_ok && br_ok {
                 let bottom_left = mapper.map(*x, *y);
                 let bottom_right = mapper.map(x + *w, *y);
@@ -237,6 +260,7 @@ impl Mapping for BarMapping {
                 ctx.rectangle(top_left.x, top_left.y, coord_w, coord_h);
                 ctx.fill()?;
                 ctx.stroke()?;
+                ctx.data_point(top_left.x + coord_w / 2., top_left.y, &[("x", *x), ("y", *y + *h)]);
             } else {
                 // println!("Out of bounds mapping");
             }
@@ -274,8 +298,8 @@ impl Mapping for BarMapping {
             xmin -= self.bar_spacing / 2.0
         }
         let xmax = if self.horizontal {
-            let max_w = self.w.iter().max_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal))?;
-            xmin + *max_w
+            let (_, max_w) = data_range(&self.w[..])?;
+            xmin + max_w
         } else {
             let n = self.x.len();
             xmin + n as f64 * self.bar_spacing
@@ -284,8 +308,8 @@ impl Mapping for BarMapping {
             let n = self.y.len();
             ymin + n as f64 * self.bar_spacing
         } else {
-            let max_h = self.h.iter().max_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal))?;
-            ymin + *max_h
+            let (_, max_h) = data_range(&self.h[..])?;
+            ymin + max_h
         };
         Some(((xmin, xmax), (ymin, ymax)))
     }
@@ -3,19 +3,18 @@
 This work is licensed under the terms of the MIT license.  
 For a copy, see <https://opensource.org/licenses/MIT>.*/
 
-use gdk4::RGBA;
-use cairo::Context;
+use crate::color::Color;
+use super::super::backend::DrawingBackend;
 use super::super::context_mapper::ContextMapper;
 use std::collections::HashMap;
 use super::*;
-use std::cmp::*;
 use std::default::Default;
 use super::super::{MappingProperty, IntervalProperty};
 use std::borrow::Borrow;
 
 #[derive(Debug, Clone)]
 pub struct IntervalMapping {
-    color : RGBA,
+    color : Color,
     x : Vec<f64>,
     ymin : Vec<f64>,
     ymax : Vec<f64>,
@@ -31,7 +30,7 @@ impl Default for IntervalMapping {
 
     fn default() -> Self {
         Self {
-            color : RGBA::BLACK,
+            color : Color::BLACK,
             x : Vec::new(),
             ymin : Vec::new(),
             ymax : Vec::new(),
@@ -121,26 +120,29 @@ impl Mapping for IntervalMapping {
         Box::new(self.clone())
     }
 
-    fn draw(&self, mapper : &ContextMapper, ctx : &Context) -> Result<(), Box<dyn Error>> {
+    fn draw(&self, mapper : &ContextMapper, ctx : &mut dyn DrawingBackend) -> Result<(), Box<dyn Error>> {
         if self.x.len() < 1 || self.ymin.len() < 1 || self.ymax.len() < 1 {
             return Ok(());
         }
         ctx.save()?;
-        ctx.set_source_rgb(
-            self.color.red().into(),
-            self.color.green().into(),
-            self.color.blue().into()
-        );
+        ctx.set_color(&self.color.with_alpha(1.0));
         ctx.set_line_width(self.width);
+        mapper.clip(ctx, self.width / 2.0);
         let dashes = IntervalMapping::build_dash(self.dash_n);
-        ctx.set_dash(&dashes[..], 0.0);
+        ctx.set_dash(&dashes[..]);
 
         let zip_xy = self.x.iter().zip(self.ymin.iter().zip(self.ymax.iter()));
 
         for (curr_x, (curr_ymin, curr_ymax)) in zip_xy {
+
+            // Intervals with missing values are skipped.
+            if curr_x.is_nan() || curr_ymin.is_nan() || curr_ymax.is_nan() {
+                continue;
+            }
+
             assert!(*curr_ymin <= *curr_ymax);
             if self.vertical {
-                if mapper.check_bounds(*curr_x, *curr_ymin) && mapper.check_bounds(*curr_x, *curr_ymax) {
+                if mapper.check_mappable(*curr_x, *curr_ymin) && mapper.check_mappable(*curr_x, *curr_ymax) {
                     let from_low = mapper.map(*curr_x - self.lim_sz / 2., *curr_ymin);
                     let to_low = mapper.map(*curr_x + self.lim_sz / 2., *curr_ymin);
                     ctx.move_to(from_low.x, from_low.y);
@@ -158,9 +160,11 @@ impl Mapping for IntervalMapping {
                     ctx.move_to(from_bar.x, from_bar.y);
                     ctx.line_to(to_bar.x, to_bar.y);
                     ctx.stroke()?;
+                    let mid = mapper.map(*curr_x, (*curr_ymin + *curr_ymax) / 2.);
+                    ctx.data_point(mid.x, mid.y, &[("x", *curr_x), ("ymin", *curr_ymin), ("ymax", *curr_ymax)]);
                 }
             } else {
-                if mapper.check_bounds(*curr_ymin, *curr_x) && mapper.check_bounds(*curr_ymax, *curr_x) {
+                if mapper.check_mappable(*curr_ymin, *curr_x) && mapper.check_mappable(*curr_ymax, *curr_x) {
                     let from_low = mapper.map(*curr_ymin, *curr_x - self.lim_sz / 2.);
                     let to_low = mapper.map(*curr_ymin, *curr_x + self.lim_sz / 2.);
                     ctx.move_to(from_low.x, from_low.y);
@@ -178,6 +182,8 @@ impl Mapping for IntervalMapping {
                     ctx.move_to(from_bar.x, from_bar.y);
                     ctx.line_to(to_bar.x, to_bar.y);
                     ctx.stroke()?;
+                    let mid = mapper.map((*curr_ymin + *curr_ymax) / 2., *curr_x);
+                    ctx.data_point(mid.x, mid.y, &[("y", *curr_x), ("xmin", *curr_ymin), ("xmax", *curr_ymax)]);
                 }
             }
         }
@@ -211,6 +217,24 @@ impl Mapping for IntervalMapping {
         super::update_data_triplet_from_json(&mut self.x, &mut self.ymin, &mut self.ymax, rep);
     }
 
+    fn to_model(&self) -> crate::model::Mapping {
+        crate::model::Mapping {
+            kind : self.mapping_type(),
+            map : crate::model::Map {
+                x : Some(self.x.clone()),
+                y : Some(self.ymin.clone()),
+                z : Some(self.ymax.clone()),
+                ..Default::default()
+            },
+            color : Some(self.color.to_string()),
+            width : Some(self.width),
+            spacing : Some(self.dash_n as f64),
+            limits : Some(self.lim_sz),
+            vertical : Some(self.vertical),
+            ..Default::default()
+        }
+    }
+
     fn update_extra_data(&mut self, _values : Vec<Vec<String>>) {
 
     }
@@ -246,17 +270,15 @@ impl Mapping for IntervalMapping {
 
     fn data_limits(&self) -> Option<((f64, f64), (f64, f64))> {
         if self.vertical {
-            let xmin = self.x.iter().min_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal) )? - self.lim_sz / 2.;
-            let xmax = self.x.iter().max_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal))? + self.lim_sz / 2.;
-            let ymin = self.ymin.iter().min_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal))?;
-            let ymax = self.ymax.iter().max_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal))?;
-            Some(((xmin, xmax), (*ymin, *ymax)))
+            let (xmin, xmax) = data_range(&self.x[..])?;
+            let (ymin, _) = data_range(&self.ymin[..])?;
+            let (_, ymax) = data_range(&self.ymax[..])?;
+            Some(((xmin - self.lim_sz / 2., xmax + self.lim_sz / 2.), (ymin, ymax)))
         } else {
-            let ymin = self.x.iter().min_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal) )? - self.lim_sz / 2.;
-            let ymax = self.x.iter().max_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal))? + self.lim_sz / 2.;
-            let xmin = self.ymin.iter().min_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal))?;
-            let xmax = self.ymax.iter().max_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal))?;
-            Some(((*xmin, *xmax), (ymin, ymax)))
+            let (ymin, ymax) = data_range(&self.x[..])?;
+            let (xmin, _) = data_range(&self.ymin[..])?;
+            let (_, xmax) = data_range(&self.ymax[..])?;
+            Some(((xmin, xmax), (ymin - self.lim_sz / 2., ymax + self.lim_sz / 2.)))
         }
     }
 
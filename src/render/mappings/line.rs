@@ -3,23 +3,25 @@
 This work is licensed under the terms of the MIT license.  
 For a copy, see <https://opensource.org/licenses/MIT>.*/
 
-use gdk4::RGBA;
-use cairo::Context;
+use crate::color::Color;
+use super::super::backend::DrawingBackend;
 use super::super::context_mapper::ContextMapper;
 use std::collections::HashMap;
 use super::*;
-use std::cmp::*;
 use std::default::Default;
 use super::super::{MappingProperty, LineProperty};
 use std::borrow::Borrow;
 
 #[derive(Debug, Clone)]
 pub struct LineMapping {
-    color : RGBA,
+    color : Color,
     x : Vec<f64>,
     y : Vec<f64>,
     width : f64,
     dash_n : i32,
+
+    // If true, the last point is connected back to the first (as in radar charts).
+    closed : bool,
     col_names : [String; 2],
     source : String
 }
@@ -28,11 +30,12 @@ impl Default for LineMapping {
 
     fn default() -> Self {
         Self {
-            color : RGBA::BLACK,
+            color : Color::BLACK,
             x : Vec::new(),
             y : Vec::new(),
             width : 1.0,
             dash_n : 1,
+            closed : false,
             col_names : [String::new(), String::new()],
             source : String::new()
         }
@@ -57,6 +60,11 @@ impl LineMapping {
         self
     }
 
+    pub fn closed(mut self, closed : bool) -> Self {
+        self.closed = closed;
+        self
+    }
+
     // TODO rename to data.
     pub fn map<D>(x : impl IntoIterator<Item=D>, y : impl IntoIterator<Item=D>) -> Self
     where
@@ -102,33 +110,52 @@ impl Mapping for LineMapping {
         Box::new(self.clone())
     }
 
-    fn draw(&self, mapper : &ContextMapper, ctx : &Context) -> Result<(), Box<dyn Error>> {
+    fn draw(&self, mapper : &ContextMapper, ctx : &mut dyn DrawingBackend) -> Result<(), Box<dyn Error>> {
         //println!("{:?}", self);
         if self.x.len() < 2 || self.y.len() < 2 {
             return Ok(());
         }
         ctx.save()?;
-        ctx.set_source_rgb(
-            self.color.red().into(),
-            self.color.green().into(),
-            self.color.blue().into()
-        );
+        ctx.set_color(&self.color.with_alpha(1.0));
         ctx.set_line_width(self.width);
+
+        // Half the line width is left outside the clip region, so lines at the
+        // limits of the scale are not thinned.
+        mapper.clip(ctx, self.width / 2.0);
         let dashes = LineMapping::build_dash(self.dash_n);
-        ctx.set_dash(&dashes[..], 0.0);
-        let zip_xy = self.x[1..].iter().zip(self.y[1..].iter());
-        let from = mapper.map(self.x[0], self.y[0]);
-        ctx.move_to(from.x, from.y);
-
-        for (curr_x, curr_y) in zip_xy {
-            if mapper.check_bounds(*curr_x, *curr_y) {
-                let to = mapper.map(*curr_x, *curr_y);
-                ctx.line_to(to.x, to.y);
-            } else {
-                // eprintln!("Out of bounds mapping");
+        ctx.set_dash(&dashes[..]);
+
+        // The path is broken at missing values, so each run of valid points
+        // is drawn as a separate segment.
+        let runs = valid_runs(&[&self.x[..], &self.y[..]]);
+
+        // Closed lines are only closed when there are no missing values.
+        let close = self.closed && runs.len() == 1;
+        for run in runs {
+            let zip_xy = self.x[run.clone()].iter().zip(self.y[run].iter());
+            let mut started = false;
+            for (curr_x, curr_y) in zip_xy {
+                if mapper.check_mappable(*curr_x, *curr_y) {
+                    let to = mapper.map(*curr_x, *curr_y);
+                    if started {
+                        ctx.line_to(to.x, to.y);
+                    } else {
+                        ctx.move_to(to.x, to.y);
+                        started = true;
+                    }
+                }
+            }
+            if close {
+                ctx.close_path();
             }
         }
         ctx.stroke()?;
+        for (x, y) in self.x.iter().zip(self.y.iter()) {
+            if mapper.check_bounds(*x, *y) {
+                let pt = mapper.map(*x, *y);
+                ctx.data_point(pt.x, pt.y, &[("x", *x), ("y", *y)]);
+            }
+        }
         ctx.restore()?;
         Ok(())
     }
@@ -148,10 +175,25 @@ impl Mapping for LineMapping {
         if let Some(color) = rep.color.clone() {
             self.color = color.parse().unwrap();
         }
+        if let Some(closed) = rep.closed {
+            self.closed = closed;
+        }
 
         super::update_data_pair_from_json(&mut self.x, &mut self.y, rep);
     }
 
+    fn to_model(&self) -> crate::model::Mapping {
+        crate::model::Mapping {
+            kind : self.mapping_type(),
+            map : crate::model::Map { x : Some(self.x.clone()), y : Some(self.y.clone()), ..Default::default() },
+            color : Some(self.color.to_string()),
+            width : Some(self.width),
+            spacing : Some(self.dash_n as f64),
+            closed : Some(self.closed),
+            ..Default::default()
+        }
+    }
+
     fn update_extra_data(&mut self, _values : Vec<Vec<String>>) {
 
     }
@@ -201,11 +243,9 @@ impl Mapping for LineMapping {
     }
 
     fn data_limits(&self) -> Option<((f64, f64), (f64, f64))> {
-        let xmin = self.x.iter().min_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal) )?;
-        let xmax = self.x.iter().max_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal))?;
-        let ymin = self.y.iter().min_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal))?;
-        let ymax = self.y.iter().max_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal))?;
-        Some(((*xmin, *xmax), (*ymin, *ymax)))
+        let (xmin, xmax) = data_range(&self.x[..])?;
+        let (ymin, ymax) = data_range(&self.y[..])?;
+        Some(((xmin, xmax), (ymin, ymax)))
     }
 
     fn set_source(&mut self, source : String) {
@@ -217,3 +257,23 @@ impl Mapping for LineMapping {
     }
 }
 
+#[test]
+fn closed_lines() {
+    use super::super::svg::SvgWriter;
+    let mut mapper = ContextMapper::new(0.0, 1.0, 0.0, 1.0, false, false, false, false);
+    mapper.update_dimensions(100, 100);
+    let draw = |line : LineMapping| {
+        let mut writer = SvgWriter::new(100.0, 100.0, crate::model::Unit::Px);
+        line.draw(&mapper, &mut writer).unwrap();
+        writer.finish()
+    };
+    let x = [0.0, 1.0, 1.0];
+    let y = [0.0, 0.0, 1.0];
+    let closed = |svg : String| svg.contains("Z\" fill=\"none\"");
+    assert!(closed(draw(LineMapping::map(x, y).closed(true))));
+    assert!(!closed(draw(LineMapping::map(x, y))));
+
+    // Lines broken at missing values are left open.
+    let svg = draw(LineMapping::map([0.0, 1.0, f64::NAN, 1.0], [0.0, 0.0, 0.5, 1.0]).closed(true));
+    assert!(svg.contains(" M") && !svg.contains("Z\" fill=\"none\""));
+}
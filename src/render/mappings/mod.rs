@@ -4,8 +4,8 @@ This work is licensed under the terms of the MIT license.
 For a copy, see <https://opensource.org/licenses/MIT>.*/
 
 // use libxml::tree::node::Node;
-use cairo::Context;
 use super::context_mapper::ContextMapper;
+use super::backend::DrawingBackend;
 use std::collections::HashMap;
 // use super::utils;
 use super::text::{FontData, draw_label};
@@ -15,6 +15,7 @@ use std::default::Default;
 use std::mem;
 use std::error::Error;
 use std::fmt::Debug;
+use std::ops::Range;
 use crate::render::PlotError;
 
 pub mod area;
@@ -31,6 +32,8 @@ pub mod text;
 
 pub mod interval;
 
+pub mod wedge;
+
 fn update_single_data_from_json(x : &mut Vec<f64>, mut rep : crate::model::Mapping) {
     if let Some(new_x) = mem::take(&mut rep.map.x) {
         *x = new_x;
@@ -77,7 +80,7 @@ fn update_textual_data_from_json(x : &mut Vec<f64>, y : &mut Vec<f64>, z : &mut
 }
 
 pub fn new_from_json(rep : crate::model::Mapping) -> Result<Box<dyn Mapping>, Box<dyn Error>> {
-    // Must be line|scatter|area|bar|surface|text
+    // Must be line|scatter|area|bar|surface|text|interval|wedge
     let mut mapping : Box<dyn Mapping> = match &rep.kind[..] {
         "line" => {
             let line : line::LineMapping = Default::default();
@@ -107,6 +110,10 @@ pub fn new_from_json(rep : crate::model::Mapping) -> Result<Box<dyn Mapping>, Bo
             let intv : interval::IntervalMapping = Default::default();
             Box::new(intv)
         },
+        "wedge" => {
+            let wedge : wedge::WedgeMapping = Default::default();
+            Box::new(wedge)
+        },
         _ => {
             return Err(Box::new(PlotError::InvalidData("Invalid mapping type")));
         }
@@ -128,7 +135,7 @@ where
 {
 
     // Mapping-specific impl.
-    fn draw(&self, mapper : &ContextMapper, ctx : &Context) -> Result<(), Box<dyn Error>> ;// { }
+    fn draw(&self, mapper : &ContextMapper, ctx : &mut dyn DrawingBackend) -> Result<(), Box<dyn Error>> ;// { }
 
     // We cannot simply require Self : Clone because this
     // assumes Self : Sized, which makes it impossible to use
@@ -168,6 +175,10 @@ where
 
     fn update_from_json(&mut self, rep : crate::model::Mapping);
 
+    // Returns the definition of this mapping, with all properties set. Building a mapping
+    // from this definition should yield the same mapping.
+    fn to_model(&self) -> crate::model::Mapping;
+
 }
 
 impl Clone for Box<dyn Mapping> {
@@ -178,8 +189,56 @@ impl Clone for Box<dyn Mapping> {
 
 }
 
-//fn compare(a : &f64, b : &f64) -> Ordering {
-//    a.partial_cmp(b).unwrap_or(Ordering::Equal)
-//}
+/// Returns the minimum and maximum of the data, ignoring missing (NaN) values.
+fn data_range(vs : &[f64]) -> Option<(f64, f64)> {
+    let min = vs.iter().filter(|v| !v.is_nan() ).min_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal) )?;
+    let max = vs.iter().filter(|v| !v.is_nan() ).max_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal) )?;
+    Some((*min, *max))
+}
 
+/// Splits the data into ranges of consecutive rows without missing (NaN) values at
+/// any of the informed columns. Mappings that draw paths are broken between those ranges.
+fn valid_runs(cols : &[&[f64]]) -> Vec<Range<usize>> {
+    let n = cols.iter().map(|c| c.len() ).min().unwrap_or(0);
+    let mut runs = Vec::new();
+    let mut start : Option<usize> = None;
+    for i in 0..n {
+        let missing = cols.iter().any(|c| c[i].is_nan() );
+        match (missing, start) {
+            (true, Some(s)) => {
+                runs.push(s..i);
+                start = None;
+            },
+            (false, None) => {
+                start = Some(i);
+            },
+            _ => { }
+        }
+    }
+    if let Some(s) = start {
+        runs.push(s..n);
+    }
+    runs
+}
 
+#[test]
+fn clip_to_data_area() {
+    use super::svg::SvgWriter;
+    let mut mapper = ContextMapper::new(0.0, 10.0, 0.0, 10.0, false, false, false, false);
+    mapper.update_dimensions(100, 100);
+
+    // Points beyond the fixed scale are drawn within the clip region instead of dropped.
+    let x = [5.0, 10.2, 50.0, f64::NAN];
+    let y = [5.0, 5.0, 5.0, 5.0];
+    let mut writer = SvgWriter::new(100.0, 100.0, crate::model::Unit::Px);
+    scatter::ScatterMapping::map(x, y).draw(&mapper, &mut writer).unwrap();
+    let svg = writer.finish();
+    assert_eq!(svg.matches("<clipPath").count(), 1);
+    assert_eq!(svg.matches(" A").count(), 3*2);
+
+    let mut writer = SvgWriter::new(100.0, 100.0, crate::model::Unit::Px);
+    text::TextMapping::map(x, y, ["a", "b", "c", "d"]).draw(&mapper, &mut writer).unwrap();
+    let svg = writer.finish();
+    assert_eq!(svg.matches("<clipPath").count(), 1);
+    assert_eq!(svg.matches("<text").count(), 3);
+}
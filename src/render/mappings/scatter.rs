@@ -3,20 +3,19 @@
 This work is licensed under the terms of the MIT license.  
 For a copy, see <https://opensource.org/licenses/MIT>.*/
 
-use gdk4::RGBA;
-use cairo::Context;
+use crate::color::Color;
+use super::super::backend::DrawingBackend;
 use super::super::context_mapper::ContextMapper;
 use std::collections::HashMap;
 use std::f64::consts::PI;
 use super::*;
-use std::cmp::*;
 use std::str::FromStr;
 use super::super::{MappingProperty, ScatterProperty};
 use std::borrow::Borrow;
 
 #[derive(Debug, Clone)]
 pub struct ScatterMapping {
-    color : RGBA,
+    color : Color,
     x : Vec<f64>,
     y : Vec<f64>,
     radius : f64,
@@ -28,7 +27,7 @@ impl Default for ScatterMapping {
 
     fn default() -> Self {
         Self {
-            color : RGBA::BLACK,
+            color : Color::BLACK,
             x : Vec::new(),
             y : Vec::new(),
             radius : 5.0,
@@ -88,7 +87,7 @@ impl Mapping for ScatterMapping {
     fn update_from_json(&mut self, rep : crate::model::Mapping) {
         // TODO check properties of other mappings are None.
         if let Some(color) = rep.color.clone() {
-            self.color = RGBA::from_str(&color).unwrap();
+            self.color = Color::from_str(&color).unwrap();
         }
         if let Some(radius) = rep.radius {
             self.radius = radius;
@@ -99,20 +98,31 @@ impl Mapping for ScatterMapping {
         super::update_data_pair_from_json(&mut self.x, &mut self.y, rep);
     }
 
-    fn draw(&self, mapper : &ContextMapper, ctx : &Context) -> Result<(), Box<dyn Error>> {
+    fn to_model(&self) -> crate::model::Mapping {
+        crate::model::Mapping {
+            kind : self.mapping_type(),
+            map : crate::model::Map { x : Some(self.x.clone()), y : Some(self.y.clone()), ..Default::default() },
+            color : Some(self.color.to_string()),
+            radius : Some(self.radius),
+            ..Default::default()
+        }
+    }
+
+    fn draw(&self, mapper : &ContextMapper, ctx : &mut dyn DrawingBackend) -> Result<(), Box<dyn Error>> {
         ctx.save()?;
-        ctx.set_source_rgba(
-            self.color.red().into(),
-            self.color.green().into(),
-            self.color.blue().into(),
-            self.color.alpha().into()
-        );
+        ctx.set_color(&self.color);
+
+        // Points at the limits of the scale are drawn whole, while points beyond them are cut.
+        mapper.clip(ctx, self.radius);
         for (x, y) in self.x.iter().zip(self.y.iter()) {
-            if mapper.check_bounds(*x, *y) {
+            if mapper.check_mappable(*x, *y) {
                 let pos = mapper.map(*x, *y);
                 ctx.arc(pos.x, pos.y, self.radius, 0.0, 2.0*PI);
                 ctx.fill()?;
                 ctx.stroke()?;
+                if mapper.check_bounds(*x, *y) {
+                    ctx.data_point(pos.x, pos.y, &[("x", *x), ("y", *y)]);
+                }
             }
         }
         ctx.restore()?;
@@ -142,11 +152,9 @@ impl Mapping for ScatterMapping {
     }
 
     fn data_limits(&self) -> Option<((f64, f64), (f64, f64))> {
-        let xmin = self.x.iter().min_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal) )?;
-        let xmax = self.x.iter().max_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal) )?;
-        let ymin = self.y.iter().min_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal) )?;
-        let ymax = self.y.iter().max_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal) )?;
-        Some(((*xmin, *xmax), (*ymin, *ymax)))
+        let (xmin, xmax) = data_range(&self.x[..])?;
+        let (ymin, ymax) = data_range(&self.y[..])?;
+        Some(((xmin, xmax), (ymin, ymax)))
     }
 
     fn get_ordered_col_names(&self) -> Vec<(String,String)> {
@@ -3,7 +3,7 @@
 This work is licensed under the terms of the MIT license.  
 For a copy, see <https://opensource.org/licenses/MIT>.*/
 
-use gdk4::RGBA;
+use crate::color::Color;
 use cairo::{Context, MeshCorner};
 use super::super::context_mapper::ContextMapper;
 use std::collections::HashMap;
@@ -19,8 +19,8 @@ pub struct SurfaceMapping {
     y : Vec<f64>,
     z : Vec<f64>,
     z_lims : (f64, f64),
-    color : RGBA,
-    color_final : RGBA,
+    color : Color,
+    color_final : Color,
     col_names : [String; 3],
     source : String
 }
@@ -29,8 +29,8 @@ impl Default for SurfaceMapping {
 
     fn default() -> Self {
         Self {
-            color : RGBA::BLACK,
-            color_final : RGBA::white(),
+            color : Color::BLACK,
+            color_final : Color::WHITE,
             x : Vec::new(),
             y : Vec::new(),
             z : Vec::new(),
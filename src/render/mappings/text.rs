@@ -3,13 +3,12 @@
 This work is licensed under the terms of the MIT license.  
 For a copy, see <https://opensource.org/licenses/MIT>.*/
 
-use gdk4::RGBA;
-use cairo::Context;
+use crate::color::Color;
+use super::super::backend::DrawingBackend;
 use super::super::context_mapper::ContextMapper;
 use std::collections::HashMap;
 use super::text::{FontData, draw_label};
 use super::*;
-use std::cmp::*;
 use super::super::{MappingProperty, TextProperty};
 use std::fmt::Display;
 use std::borrow::Borrow;
@@ -20,7 +19,7 @@ pub struct TextMapping {
     y : Vec<f64>,
     text : Vec<String>,
     font : FontData,
-    color : RGBA,
+    color : Color,
     col_names : [String; 3],
     source : String
 }
@@ -29,7 +28,7 @@ impl Default for TextMapping {
 
     fn default() -> Self {
         Self {
-            color : RGBA::BLACK,
+            color : Color::BLACK,
             x : Vec::new(),
             y : Vec::new(),
             text : Vec::new(),
@@ -110,23 +109,36 @@ impl Mapping for TextMapping {
         super::update_textual_data_from_json(&mut self.x, &mut self.y, &mut self.text, rep);
     }
 
-    fn draw(&self, mapper : &ContextMapper, ctx : &Context) -> Result<(), Box<dyn Error>> {
+    fn to_model(&self) -> crate::model::Mapping {
+        crate::model::Mapping {
+            kind : self.mapping_type(),
+            map : crate::model::Map {
+                x : Some(self.x.clone()),
+                y : Some(self.y.clone()),
+                text : Some(self.text.clone()),
+                ..Default::default()
+            },
+            color : Some(self.color.to_string()),
+            font : Some(self.font.description()),
+            ..Default::default()
+        }
+    }
+
+    fn draw(&self, mapper : &ContextMapper, ctx : &mut dyn DrawingBackend) -> Result<(), Box<dyn Error>> {
         ctx.save()?;
         if !((self.x.len() == self.y.len()) && (self.x.len() == self.text.len())) {
             // eprintln!("Invalid dimensions at textual mapping");
         }
-        ctx.set_source_rgb(
-            self.color.red().into(),
-            self.color.green().into(),
-            self.color.blue().into()
-        );
-        self.font.set_font_into_context(&ctx);
+        ctx.set_color(&self.color.with_alpha(1.0));
+
+        // Labels centered at the limits of the scale are kept legible.
+        mapper.clip(ctx, self.font.font_size as f64);
         for ((x, y), t) in self.x.iter().zip(self.y.iter()).zip(self.text.iter()) {
-            if mapper.check_bounds(*x, *y) {
+            if mapper.check_mappable(*x, *y) {
                 let pos = mapper.map(*x, *y);
                 draw_label(
-                    &self.font.sf,
                     ctx,
+                    &self.font,
                     t,
                     pos,
                     false,
@@ -134,6 +146,9 @@ impl Mapping for TextMapping {
                     None,
                     None
                 )?;
+                if mapper.check_bounds(*x, *y) {
+                    ctx.data_point(pos.x, pos.y, &[("x", *x), ("y", *y)]);
+                }
             } else {
                 // eprintln!("Out of bounds mapping");
             }
@@ -201,11 +216,9 @@ impl Mapping for TextMapping {
 
         // let (glyphs, _) = sf.text_to_glyphs(pos.x, pos.y, label)
         // sf.glyph_extents(&glyphs[..]);
-        let xmin = self.x.iter().min_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal) )?;
-        let xmax = self.x.iter().max_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal))?;
-        let ymin = self.y.iter().min_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal))?;
-        let ymax = self.y.iter().max_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal))?;
-        Some(((*xmin, *xmax), (*ymin, *ymax)))
+        let (xmin, xmax) = data_range(&self.x[..])?;
+        let (ymin, ymax) = data_range(&self.y[..])?;
+        Some(((xmin, xmax), (ymin, ymax)))
     }
 
     fn set_col_names(&mut self, cols : Vec<String>) -> Result<(), &'static str> {
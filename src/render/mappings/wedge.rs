@@ -0,0 +1,278 @@
+/*Copyright (c) 2022 Diego da Silva Lima. All rights reserved.
+
+This work is licensed under the terms of the MIT license.  
+For a copy, see <https://opensource.org/licenses/MIT>.*/
+
+use crate::color::Color;
+use super::super::backend::DrawingBackend;
+use super::super::context_mapper::ContextMapper;
+use std::collections::HashMap;
+use super::*;
+use super::super::MappingProperty;
+use std::borrow::Borrow;
+
+// Number of segments used to approximate a wedge covering the full angle scale.
+const ARC_SEGMENTS : f64 = 128.0;
+
+// Wedges larger than the angle scale wrap around it, but are never approximated by
+// more than this number of segments.
+const MAX_ARC_SEGMENTS : f64 = ARC_SEGMENTS * 4.0;
+
+/// Represents consecutive wedges (circular sectors), as in pie and donut charts. The only
+/// map required is the wedge size: wedges are placed one after the other along the
+/// horizontal (angle) scale starting at zero, and span the vertical (radius) scale from
+/// the origin to the scale end. Wedges are drawn through the plot mapper, so at cartesian
+/// coordinates they are drawn as a single stacked bar.
+#[derive(Debug, Clone)]
+pub struct WedgeMapping {
+
+    // Colors of the first and last wedges. The colors of the
+    // remaining wedges are linearly interpolated between those.
+    color : Color,
+    color_final : Color,
+
+    // Wedge sizes, at data scale.
+    x : Vec<f64>,
+
+    // Inner radius, at data scale.
+    origin : f64,
+
+    col_names : [String; 1],
+    source : String
+}
+
+impl Default for WedgeMapping {
+
+    fn default() -> Self {
+        Self {
+            color : Color::BLACK,
+            color_final : Color::BLACK,
+            x : Vec::new(),
+            origin : 0.0,
+            col_names : [String::new()],
+            source : String::new()
+        }
+    }
+
+}
+
+impl WedgeMapping {
+
+    pub fn color(mut self, color : String) -> Self {
+        self.color = color.parse().unwrap();
+        self
+    }
+
+    pub fn color_final(mut self, color : String) -> Self {
+        self.color_final = color.parse().unwrap();
+        self
+    }
+
+    pub fn origin(mut self, origin : f64) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    pub fn map<D>(x : impl IntoIterator<Item=D>) -> Self
+    where
+        D : Borrow<f64>
+    {
+        let mut wedge : WedgeMapping = Default::default();
+        let x : Vec<_> = x.into_iter().map(|d| *d.borrow() ).collect();
+        wedge.update_data(vec![x]);
+        wedge
+    }
+
+    fn wedge_color(&self, ix : usize) -> Color {
+        let t = if self.x.len() > 1 { ix as f32 / (self.x.len() - 1) as f32 } else { 0.0 };
+        let interp = |a : f32, b : f32| a + (b - a) * t;
+        Color::new(
+            interp(self.color.red(), self.color_final.red()),
+            interp(self.color.green(), self.color_final.green()),
+            interp(self.color.blue(), self.color_final.blue()),
+            interp(self.color.alpha(), self.color_final.alpha())
+        )
+    }
+
+}
+
+impl Mapping for WedgeMapping {
+
+    fn clone_boxed(&self) -> Box<dyn Mapping> {
+        Box::new(self.clone())
+    }
+
+    fn update(&mut self, _prop : MappingProperty) -> bool {
+        false
+    }
+
+    fn update_from_json(&mut self, rep : crate::model::Mapping) {
+        if let Some(color) = rep.color.clone() {
+            self.color = color.parse().unwrap();
+        }
+        if let Some(color) = rep.color_final.clone() {
+            self.color_final = color.parse().unwrap();
+        }
+        if let Some(origin) = rep.origin {
+            self.origin = origin;
+        }
+        super::update_single_data_from_json(&mut self.x, rep);
+    }
+
+    fn to_model(&self) -> crate::model::Mapping {
+        crate::model::Mapping {
+            kind : self.mapping_type(),
+            map : crate::model::Map { x : Some(self.x.clone()), ..Default::default() },
+            color : Some(self.color.to_string()),
+            color_final : Some(self.color_final.to_string()),
+            origin : Some(self.origin),
+            ..Default::default()
+        }
+    }
+
+    fn draw(&self, mapper : &ContextMapper, ctx : &mut dyn DrawingBackend) -> Result<(), Box<dyn Error>> {
+        let angle_ext = (mapper.xmax - mapper.xmin).abs();
+        if !(angle_ext > 0.0 && angle_ext.is_finite()) {
+            return Ok(());
+        }
+        ctx.save()?;
+        mapper.clip(ctx, 0.0);
+        let mut start = 0.0;
+        for (ix, size) in self.x.iter().enumerate() {
+
+            // Missing values do not take any space.
+            if size.is_nan() {
+                continue;
+            }
+            let end = start + size;
+            let n_segments = (ARC_SEGMENTS * size.abs() / angle_ext).ceil().clamp(1.0, MAX_ARC_SEGMENTS) as usize;
+            let angles : Vec<f64> = (0..=n_segments)
+                .map(|i| start + (end - start) * i as f64 / n_segments as f64 )
+                .collect();
+
+            // Outer arc, followed by the inner arc in the opposite direction.
+            let mut first = true;
+            let outer = angles.iter().map(|a| (*a, mapper.ymax) );
+            let inner = angles.iter().rev().map(|a| (*a, self.origin) );
+            for (a, r) in outer.chain(inner) {
+                if !mapper.check_mappable(a, r) {
+                    continue;
+                }
+                let pt = mapper.map(a, r);
+                if first {
+                    ctx.move_to(pt.x, pt.y);
+                    first = false;
+                } else {
+                    ctx.line_to(pt.x, pt.y);
+                }
+            }
+            ctx.close_path();
+            ctx.set_color(&self.wedge_color(ix));
+            ctx.fill_preserve()?;
+
+            // Consecutive wedges are separated by a thin outline.
+            ctx.set_color(&Color::WHITE);
+            ctx.set_line_width(1.0);
+            ctx.stroke()?;
+
+            // Values are shown at the middle of the wedge.
+            let mid = mapper.map(start + size / 2., (self.origin + mapper.ymax) / 2.);
+            ctx.data_point(mid.x, mid.y, &[("value", *size)]);
+            start = end;
+        }
+        ctx.restore()?;
+        Ok(())
+    }
+
+    fn update_data(&mut self, mut values : Vec<Vec<f64>>) {
+        self.x = values.remove(0);
+    }
+
+    fn update_extra_data(&mut self, _values : Vec<Vec<String>>) {
+
+    }
+
+    fn data_limits(&self) -> Option<((f64, f64), (f64, f64))> {
+
+        // The angle scale covers all wedges; the radius scale starts at zero and
+        // ends at one, unless the origin is outside this interval.
+        data_range(&self.x[..])?;
+        let total : f64 = self.x.iter().filter(|v| !v.is_nan() ).sum();
+        Some(((0.0, total), (self.origin.min(0.0), self.origin.max(1.0))))
+    }
+
+    fn mapping_type(&self) -> String {
+        "wedge".into()
+    }
+
+    fn get_col_name(&self, col : &str) -> String {
+        match col {
+            "x" => self.col_names[0].clone(),
+            _ => String::new()
+        }
+    }
+
+    fn get_ordered_col_names(&self) -> Vec<(String, String)> {
+        vec![
+            (String::from("x"), self.get_col_name("x"))
+        ]
+    }
+
+    fn get_hash_col_names(&self) -> HashMap<String, String> {
+        let mut cols = HashMap::new();
+        cols.insert("x".into(), self.col_names[0].clone());
+        cols
+    }
+
+    fn set_col_name(&mut self, col : &str, name : &str) {
+        if col == "x" {
+            self.col_names[0] = name.into();
+        }
+    }
+
+    fn set_col_names(&mut self, cols : Vec<String>) -> Result<(), &'static str> {
+        if cols.len() != 1 {
+            Err("Wrong number of columns.")
+        } else {
+            self.set_col_name("x", &cols[0]);
+            Ok(())
+        }
+    }
+
+    fn set_source(&mut self, source : String) {
+        self.source = source;
+    }
+
+    fn get_source(&self) -> String {
+        self.source.clone()
+    }
+
+}
+
+#[test]
+fn wedge_segments() {
+    use super::super::svg::SvgWriter;
+    let mut mapper = ContextMapper::new(0.0, 6.0, 0.0, 1.0, false, false, false, false);
+    mapper.polar = true;
+    mapper.update_dimensions(100, 100);
+    let wedge = WedgeMapping::map([1.0, 2.0, 3.0]);
+    let mut writer = SvgWriter::new(100.0, 100.0, crate::model::Unit::Px);
+    wedge.draw(&mapper, &mut writer).unwrap();
+    assert_eq!(writer.finish().matches("Z\" fill=\"none\"").count(), 3);
+
+    // Tiny angle scales would otherwise require an unbounded number of segments.
+    mapper.update_data_extensions(0.0, 1e-300, 0.0, 1.0);
+    let mut writer = SvgWriter::new(100.0, 100.0, crate::model::Unit::Px);
+    wedge.draw(&mapper, &mut writer).unwrap();
+    let svg = writer.finish();
+    assert_eq!(svg.matches("Z\" fill=\"none\"").count(), 3);
+
+    // Each wedge is written twice (filled and outlined), with the outer and inner arcs.
+    assert!(svg.matches(" L").count() <= 3 * 2 * 2 * (MAX_ARC_SEGMENTS as usize + 1));
+
+    // Empty angle scales draw nothing.
+    mapper.update_data_extensions(0.0, 0.0, 0.0, 1.0);
+    let mut writer = SvgWriter::new(100.0, 100.0, crate::model::Unit::Px);
+    wedge.draw(&mapper, &mut writer).unwrap();
+    assert_eq!(writer.finish().matches("Z\" fill=\"none\"").count(), 0);
+}
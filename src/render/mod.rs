@@ -3,26 +3,33 @@
 This work is licensed under the terms of the MIT license.  
 For a copy, see <https://opensource.org/licenses/MIT>.*/
 
+#[cfg(feature="cairo-rs")]
 use cairo::Context;
 use std::default::Default;
 use std::collections::HashMap;
 use std::error::Error;
 use std::result::Result;
-use std::io::{ErrorKind, Write};
+use std::io::ErrorKind;
+#[cfg(feature="cairo-rs")]
+use std::io::Write;
 use mappings::*;
 use std::fmt::Display;
 use std::any::Any;
 use std::error;
-use std::{fmt, fs::File};
-use cairo::{SvgSurface, PsSurface, ImageSurface, Format};
+use std::fmt;
+#[cfg(feature="cairo-rs")]
+use std::fs::File;
+#[cfg(feature="cairo-rs")]
+use cairo::{SvgSurface, PsSurface, PdfSurface, ImageSurface, Format};
 use std::path::Path;
 use std::cmp::Ordering;
 use std::mem;
 use std::str::FromStr;
+#[cfg(feature="cairo-rs")]
 use std::process::Command;
-use tempfile;
 use std::fs;
-use crate::model::Adjustment;
+use crate::model::{Adjustment, Coordinates};
+use crate::color::Color;
 
 pub mod mappings;
 
@@ -50,9 +57,53 @@ pub use mappings::area::*;
 
 pub use mappings::interval::*;
 
+pub use mappings::wedge::*;
+
 pub mod text;
 
-use text::FontData;
+use text::{FontData, FontWeight};
+
+pub mod annotation;
+
+pub use annotation::*;
+
+pub mod backend;
+
+pub use backend::*;
+
+pub mod svg;
+
+pub use svg::SvgWriter;
+
+#[cfg(feature="cairo-rs")]
+pub mod document;
+
+#[cfg(feature="cairo-rs")]
+pub use document::*;
+
+pub mod html;
+
+pub mod terminal;
+
+pub use terminal::TerminalCanvas;
+
+mod update;
+
+pub mod notebook;
+
+pub use notebook::*;
+
+#[cfg(feature="cairo-rs")]
+pub mod raster;
+
+#[cfg(feature="cairo-rs")]
+pub use raster::*;
+
+#[cfg(feature="cairo-rs")]
+pub mod inline;
+
+#[cfg(feature="cairo-rs")]
+pub use inline::*;
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum GroupSplit {
@@ -86,6 +137,23 @@ impl FromStr for GroupSplit {
 
 }
 
+impl GroupSplit {
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Unique => "unique",
+            Self::Four => "four",
+            Self::Horizontal => "horizontal",
+            Self::Vertical => "vertical",
+            Self::ThreeLeft => "threeleft",
+            Self::ThreeTop => "threetop",
+            Self::ThreeRight => "threeright",
+            Self::ThreeBottom => "threebottom"
+        }
+    }
+
+}
+
 fn n_plots_for_split(split : &GroupSplit) -> usize {
     match split {
         GroupSplit::Unique => 1,
@@ -95,6 +163,96 @@ fn n_plots_for_split(split : &GroupSplit) -> usize {
     }
 }
 
+// Plot and panel titles (and subplot tags) are drawn at TITLE_SCALE times the
+// design font size, and captions at CAPTION_SCALE times the design font size.
+const TITLE_SCALE : f64 = 1.25;
+
+const CAPTION_SCALE : f64 = 0.75;
+
+// Vertical space reserved for each line of title, subtitle or caption, as a multiple of its font size.
+const TITLE_LINE_SPACING : f64 = 1.6;
+
+// Titles, labels and grid values share this color.
+const LABEL_GRAY : f32 = 0.2666;
+
+fn label_color() -> Color {
+    Color::new(LABEL_GRAY, LABEL_GRAY, LABEL_GRAY, 1.0)
+}
+
+// CRC-32 (ISO 3309) of PNG chunks.
+#[cfg(feature="cairo-rs")]
+fn png_crc(data : &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for b in data {
+        crc ^= *b as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+// Inserts a pHYs chunk (physical pixel dimensions) right after the IHDR chunk, which is
+// the first chunk of any PNG written by cairo (cairo itself does not record the resolution).
+#[cfg(feature="cairo-rs")]
+fn set_png_dpi(png : &mut Vec<u8>, dpi : f64) {
+    const IHDR_END : usize = 8 + 4 + 4 + 13 + 4;
+    if png.len() < IHDR_END || &png[12..16] != b"IHDR" || !dpi.is_finite() || dpi <= 0.0 {
+        return;
+    }
+    let ppm = (dpi / 0.0254).round() as u32;
+    let mut chunk = b"pHYs".to_vec();
+    chunk.extend_from_slice(&ppm.to_be_bytes());
+    chunk.extend_from_slice(&ppm.to_be_bytes());
+
+    // Unit is the meter.
+    chunk.push(1);
+    let crc = png_crc(&chunk);
+    let mut bytes = 9u32.to_be_bytes().to_vec();
+    bytes.extend(chunk);
+    bytes.extend_from_slice(&crc.to_be_bytes());
+    png.splice(IHDR_END..IHDR_END, bytes);
+}
+
+// Tags plots as A, B, ... Z, followed by AA, AB and so on.
+fn plot_tag(mut i : usize) -> String {
+    let mut tag = Vec::new();
+    loop {
+        tag.insert(0, b'A' + (i % 26) as u8);
+        if i < 26 {
+            break;
+        }
+        i = i / 26 - 1;
+    }
+    String::from_utf8(tag).unwrap()
+}
+
+// Describes a mapping by its kind and the names of the columns it maps, if any (e.g. "line (x: time, y: value)").
+fn mapping_label(kind : &str, cols : &[(String, String)]) -> String {
+    let named : Vec<String> = cols.iter()
+        .filter(|(_, name)| !name.is_empty() )
+        .map(|(col, name)| format!("{}: {}", col, name) )
+        .collect();
+    if named.is_empty() {
+        kind.to_string()
+    } else {
+        format!("{} ({})", kind, named.join(", "))
+    }
+}
+
+fn fill_rect(ctx : &mut dyn DrawingBackend, color : &Color, x : f64, y : f64, w : f64, h : f64) -> Result<(), Box<dyn Error>> {
+    ctx.save()?;
+    ctx.set_color(color);
+    ctx.rectangle(x, y, w, h);
+    ctx.fill()?;
+    ctx.restore()?;
+    Ok(())
+}
+
+fn title_font(design : &PlotDesign, scale : f64, weight : FontWeight) -> FontData {
+    design.font.resized((design.font.font_size as f64 * scale).round() as i32, weight)
+}
+
 pub enum LayoutProperty {
     Width(i32),
     Height(i32),
@@ -165,6 +323,7 @@ pub enum MappingProperty {
     Interval(IntervalProperty)
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ScaleMode {
     Horizontal,
     Vertical
@@ -189,6 +348,15 @@ pub enum GroupProperty {
     Plot(usize, PlotProperty)
 }
 
+// Number of numeric columns expected by Plot::update_mapping, for each mapping kind.
+fn data_columns(kind : &str) -> usize {
+    match kind {
+        "bar" | "wedge" => 1,
+        "area" | "interval" | "surface" => 3,
+        _ => 2
+    }
+}
+
 /// A Panel is a set of 1-4 plots with a given layout. Regions with arbitrary
 /// number of plots can be built by splitting it according to some aspect
 /// ratio and drawing multiple panels to it. To draw a 3x3 grid, for example,
@@ -210,8 +378,24 @@ pub struct Panel {
 
     v_ratio : f64,
 
+    // Drawing size, in pixels (or points, for layouts with physical units).
     dimensions : (usize, usize),
 
+    // Ratio between the number of pixels of raster exports and the drawing size.
+    scale_factor : f64,
+
+    // Layout unit, which determines whether the drawing size is in pixels or points.
+    unit : crate::model::Unit,
+
+    // Layout width and height as informed by the definition (in the layout unit).
+    size : Option<(f64, f64)>,
+
+    // Supertitle drawn above all plots.
+    title : Option<String>,
+
+    // Whether plots should receive automatic tags (A, B, C...).
+    tags : bool
+
 }
 
 unsafe impl Send for Panel { }
@@ -231,7 +415,12 @@ impl Default for Panel {
             split : GroupSplit::Unique,
             h_ratio : 0.5,
             v_ratio : 0.5,
-            dimensions : (800, 600)
+            dimensions : (800, 600),
+            scale_factor : 1.0,
+            unit : Default::default(),
+            size : None,
+            title : None,
+            tags : false
         }
     }
 
@@ -242,13 +431,17 @@ impl fmt::Debug for Panel {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
         write!(
             f,
-            "{{ design : {:?}, plots : {:?}, split : {:?}, h_ratio : {:?}, v_ratio : {:?}, dimensions : {:?} }}",
+            "{{ design : {:?}, plots : {:?}, split : {:?}, h_ratio : {:?}, v_ratio : {:?}, dimensions : {:?}, scale_factor : {:?}, unit : {:?}, title : {:?}, tags : {:?} }}",
             self.design,
             self.plots,
             self.split,
             self.h_ratio,
             self.v_ratio,
-            self.dimensions
+            self.dimensions,
+            self.scale_factor,
+            self.unit,
+            self.title,
+            self.tags
         )
     }
 
@@ -264,15 +457,6 @@ pub enum Orientation {
     Vertical
 }
 
-fn update_dims_from_env(dims : &mut (usize, usize)) {
-    if let Ok(var) = std::env::var("PLOT_DEFAULT_WIDTH") {
-        dims.0 = var.parse().unwrap();
-    }
-    if let Ok(var) = std::env::var("PLOT_DEFAULT_HEIGHT") {
-        dims.1 = var.parse().unwrap();
-    }
-}
-
 impl Panel {
 
     pub fn get_dimensions(&self) -> (usize, usize) {
@@ -286,10 +470,38 @@ impl Panel {
         self
     }
 
+    /// Replaces the design of the panel, e.g. to apply a theme to an existing definition.
+    pub fn set_design(&mut self, design : crate::model::Design) -> Result<(), Box<dyn Error>> {
+        self.design = PlotDesign::new_from_json(design)?;
+        Ok(())
+    }
+
+    /// Scales geometry and fonts of raster exports by the informed factor, so a
+    /// panel with dimensions w x h is rasterized to (factor*w) x (factor*h) pixels.
+    pub fn scale_factor(mut self, factor : f64) -> Self {
+        self.scale_factor = factor;
+        self
+    }
+
+    /// Resolution of raster exports, in pixels per inch.
+    pub fn dpi(&self) -> f64 {
+        if self.unit.is_physical() {
+            self.scale_factor * 72.0
+        } else {
+            self.scale_factor * crate::model::DEFAULT_DPI
+        }
+    }
+
+    /// Returns the number of pixels of raster exports.
+    pub fn raster_dimensions(&self) -> (i32, i32) {
+        (
+            (self.dimensions.0 as f64 * self.scale_factor).round() as i32,
+            (self.dimensions.1 as f64 * self.scale_factor).round() as i32
+        )
+    }
+
     pub fn single(p1 : Plot) -> Self {
-        let mut panel = Self::default();
-        update_dims_from_env(&mut panel.dimensions);
-        panel.dimensions = (p1.mapper.w as usize, p1.mapper.h as usize);
+        let mut panel = Self { dimensions : (p1.mapper.w as usize, p1.mapper.h as usize), ..Self::default() };
         panel.plots[0] = p1;
         panel
     }
@@ -303,10 +515,20 @@ impl Panel {
         };
         group.plots[0] = p1;
         group.plots[1] = p2;
-        update_dims_from_env(&mut group.dimensions);
         group
     }
 
+    pub fn title(mut self, title : &str) -> Self {
+        self.title = Some(title.to_string());
+        self
+    }
+
+    /// Tag each plot with an upper-case letter (A, B, C...) according to its position.
+    pub fn tags(mut self, tags : bool) -> Self {
+        self.tags = tags;
+        self
+    }
+
     pub fn update(&mut self, prop : GroupProperty) {
         match prop {
             GroupProperty::Layout(layout) => {
@@ -332,8 +554,39 @@ impl Panel {
         }
     }
 
-    pub fn to_json(&self) -> String {
-        unimplemented!()
+    /// Returns the panel definition as JSON (see to_model).
+    pub fn to_json(&self) -> Result<String, Box<dyn Error>> {
+        Ok(serde_json::to_string_pretty(&self.to_model())?)
+    }
+
+    /// Returns the panel definition, including the changes made after the panel was
+    /// built (such as updates and new data). Building a panel from this definition
+    /// yields the same panel. Design and layout are set at the panel level, and the
+    /// layout keeps its unit and resolution.
+    pub fn to_model(&self) -> crate::model::Panel {
+
+        // The informed size is kept unless the panel was resized since.
+        let points = if self.unit.is_physical() { self.unit.points() } else { 1.0 };
+        let (width, height) = match self.size {
+            Some((w, h)) if ((w * points).round() as usize, (h * points).round() as usize) == self.dimensions => (w, h),
+            _ => (self.dimensions.0 as f64 / points, self.dimensions.1 as f64 / points)
+        };
+        let layout = crate::model::Layout {
+            width,
+            height,
+            hratio : self.h_ratio,
+            vratio : self.v_ratio,
+            split : Some(self.split.name().to_string()),
+            unit : Some(self.unit.name().to_string()).filter(|_| self.unit.is_physical() ),
+            dpi : Some(self.dpi())
+        };
+        crate::model::Panel {
+            plots : self.plots.iter().map(|pl| pl.to_model() ).collect(),
+            design : Some(self.design.to_model()),
+            layout : Some(layout),
+            title : self.title.clone(),
+            tags : Some(self.tags)
+        }
     }
 
     pub fn new() -> Self {
@@ -361,13 +614,19 @@ impl Panel {
             .map_err(|e| format!("Invalid design: {}", e))?;
         let area = Plot::new_from_model(plot)
             .map_err(|e| format!("Invalid plot: {}", e) )?;
+        let ((w, h), scale_factor) = layout_json.drawing_size();
         Ok(Self {
             design,
             plots : vec![area],
             split : GroupSplit::Unique,
             h_ratio : layout_json.hratio,
             v_ratio : layout_json.vratio,
-            dimensions : (layout_json.width as usize, layout_json.height as usize),
+            dimensions : (w.round() as usize, h.round() as usize),
+            scale_factor,
+            unit : layout_json.unit(),
+            size : Some((layout_json.width, layout_json.height)),
+            title : None,
+            tags : false
         })
     }
 
@@ -377,6 +636,8 @@ impl Panel {
         
         let mut panel : Panel = Default::default();
         panel.plots.clear();
+        panel.title = panel_def.title.take();
+        panel.tags = panel_def.tags.unwrap_or(false);
 
         if panel_def.plots.len() == 1 {
             panel.split = GroupSplit::Unique;
@@ -422,7 +683,11 @@ impl Panel {
         }
 
         if let Some(layout) = panel_def.layout {
-            panel.dimensions = (layout.width as usize, layout.height as usize);
+            let ((w, h), scale_factor) = layout.drawing_size();
+            panel.dimensions = (w.round() as usize, h.round() as usize);
+            panel.scale_factor = scale_factor;
+            panel.unit = layout.unit();
+            panel.size = Some((layout.width, layout.height));
             panel.h_ratio = layout.hratio;
             panel.v_ratio = layout.vratio;
 
@@ -493,24 +758,39 @@ impl Panel {
         }
     }
 
-    pub fn png(&mut self) -> Result<Vec<u8>, Box<dyn Error>> {
-        let surf = ImageSurface::create(
-            Format::ARgb32,
-            self.dimensions.0 as i32,
-            self.dimensions.1 as i32,
-        )?;
-        let ctx = Context::new(&surf).unwrap();
+    #[cfg(feature="cairo-rs")]
+    /// Rasterizes the panel at its raster dimensions, scaling geometry and fonts
+    /// by the scale factor.
+    fn draw_to_image(&mut self) -> Result<ImageSurface, Box<dyn Error>> {
+        let (w, h) = self.raster_dimensions();
+        let surf = ImageSurface::create(Format::ARgb32, w, h)
+            .map_err(|e| FileError(format!("Error creating PNG image surface: {}", e) ))?;
+        let ctx = Context::new(&surf)?;
+        ctx.scale(self.scale_factor, self.scale_factor);
         self.draw_to_context(&ctx, 0, 0, self.dimensions.0 as i32, self.dimensions.1 as i32)?;
+        Ok(surf)
+    }
+
+    #[cfg(feature="cairo-rs")]
+    /// Renders the panel as PNG, recording its resolution (see dpi) at the image.
+    pub fn png(&mut self) -> Result<Vec<u8>, Box<dyn Error>> {
+        let surf = self.draw_to_image()?;
         let mut buf = Vec::new();
         surf.write_to_png(&mut buf)?;
+        set_png_dpi(&mut buf, self.dpi());
         Ok(buf)
     }
 
+    #[cfg(feature="cairo-rs")]
     pub fn html_img_tag(&mut self) -> Result<String, Box<dyn Error>> {
         let png = self.png()?;
         Ok(format!("<img src='data:image/png;base64,{}' />", base64::encode(png)))
     }
 
+    /// Renders the panel as SVG. When compiled with cairo, text is rendered to glyph
+    /// outlines by the cairo SVG surface. Otherwise, the SVG is written by SvgWriter
+    /// (see semantic_svg).
+    #[cfg(feature="cairo-rs")]
     pub fn svg(&mut self) -> Result<String, Box<dyn Error>> {
         let svg_buf : Vec<u8> = Vec::new();
         let surf = SvgSurface::for_stream(
@@ -529,10 +809,95 @@ impl Panel {
         Ok(String::from_utf8(stream.downcast_ref::<Vec<u8>>().unwrap().clone())?)
     }
 
+    #[cfg(not(feature="cairo-rs"))]
+    pub fn svg(&mut self) -> Result<String, Box<dyn Error>> {
+        self.semantic_svg()
+    }
+
+    /// Renders the panel as structured SVG with SvgWriter, which does not depend on cairo.
+    /// Labels are written as <text> elements, and each plot is a <g> element with id
+    /// plot-{i} and class "plot", containing the groups:
+    /// plot-{i}-background (class "background"), plot-{i}-grid ("grid"),
+    /// plot-{i}-x-axis and plot-{i}-y-axis ("axis x" and "axis y"),
+    /// plot-{i}-mapping-{j} ("mapping" and the mapping kind, e.g. "mapping line"),
+    /// plot-{i}-annotation-{j} ("annotation") and plot-{i}-titles ("titles").
+    /// The panel background and title are the panel-background and panel-title groups.
+    /// Plot and mapping groups carry a <title>, and the document carries a
+    /// <title> and <desc> for accessibility.
+    pub fn semantic_svg(&mut self) -> Result<String, Box<dyn Error>> {
+        let mut writer = self.svg_writer();
+        self.draw_to_backend(&mut writer, 0, 0, self.dimensions.0 as i32, self.dimensions.1 as i32)?;
+        Ok(writer.finish())
+    }
+
+    // SVG writer with the panel dimensions, title and description.
+    fn svg_writer(&self) -> SvgWriter {
+        SvgWriter::new(self.dimensions.0 as f64, self.dimensions.1 as f64, self.unit)
+            .title(self.title.as_deref().unwrap_or("Plot"))
+            .description(&self.description())
+    }
+
+    /// Summarizes the plots of the panel and their mappings (e.g. "Panel with 2 plots.
+    /// Plot 1 (Growth): line, scatter. Plot 2: bar.").
+    pub fn description(&self) -> String {
+        let mut desc = if self.plots.len() == 1 {
+            String::from("Panel with 1 plot.")
+        } else {
+            format!("Panel with {} plots.", self.plots.len())
+        };
+        for (i, plot) in self.plots.iter().enumerate() {
+            desc += &format!(" Plot {}", i + 1);
+            if let Some(title) = &plot.title {
+                desc += &format!(" ({})", title);
+            }
+            let kinds : Vec<String> = plot.mappings.iter().map(|m| m.mapping_type() ).collect();
+            if kinds.is_empty() {
+                desc += ": no mappings.";
+            } else {
+                desc += &format!(": {}.", kinds.join(", "));
+            }
+        }
+        desc
+    }
+
+    #[cfg(feature="cairo-rs")]
+    /// Renders the panel as a single-page PDF document. To write several panels
+    /// as pages of the same document, use Document instead.
+    pub fn pdf(&mut self) -> Result<Vec<u8>, Box<dyn Error>> {
+        let surf = PdfSurface::for_stream(
+            self.dimensions.0 as f64,
+            self.dimensions.1 as f64,
+            Vec::<u8>::new()
+        ).map_err(|e| format!("Error creating PDF surface: {}", e) )?;
+        let ctx = Context::new(&surf)?;
+        self.draw_to_context(&ctx, 0, 0, self.dimensions.0 as i32, self.dimensions.1 as i32)?;
+        let stream = surf.finish_output_stream()
+            .map_err(|e| format!("Error writing PDF content: {}", e) )?;
+        Ok(stream.downcast_ref::<Vec<u8>>().cloned().unwrap_or_default())
+    }
+
+    #[cfg(feature="cairo-rs")]
+    /// Renders the panel as an Encapsulated Postscript document.
+    pub fn eps(&mut self) -> Result<Vec<u8>, Box<dyn Error>> {
+        let surf = PsSurface::for_stream(
+            self.dimensions.0 as f64,
+            self.dimensions.1 as f64,
+            Vec::<u8>::new()
+        ).map_err(|e| format!("Error creating Postscript surface: {}", e) )?;
+        surf.set_eps(true);
+        let ctx = Context::new(&surf)?;
+        self.draw_to_context(&ctx, 0, 0, self.dimensions.0 as i32, self.dimensions.1 as i32)?;
+        let stream = surf.finish_output_stream()
+            .map_err(|e| format!("Error writing Postscript content: {}", e) )?;
+        Ok(stream.downcast_ref::<Vec<u8>>().cloned().unwrap_or_default())
+    }
+
+    #[cfg(feature="cairo-rs")]
     pub fn show_with_eog(&mut self) -> Result<(), Box<dyn Error>> {
         self.show_with_app("eog")
     }
 
+    #[cfg(feature="cairo-rs")]
     /// Shows plot by saving it at a tempfile and opening with the
     /// informed application, which is assumed to receive the tempfile
     /// path as first argument.
@@ -558,6 +923,12 @@ impl Panel {
         }
 
         match path.extension().and_then(|e| e.to_str() ) {
+            #[cfg(not(feature="cairo-rs"))]
+            Some("svg") => {
+                fs::write(path, self.svg()?)
+                    .map_err(|e| FileError(format!("Unable to write SVG file: {}", e)))?;
+            },
+            #[cfg(feature="cairo-rs")]
             Some("svg") => {
                 let surf = SvgSurface::new(
                     self.dimensions.0 as f64,
@@ -567,19 +938,14 @@ impl Panel {
                 let ctx = Context::new(&surf).unwrap();
                 self.draw_to_context(&ctx, 0, 0, self.dimensions.0 as i32, self.dimensions.1 as i32)?;
             },
+            #[cfg(feature="cairo-rs")]
             Some("png") => {
-                let surf = ImageSurface::create(
-                    Format::ARgb32,
-                    self.dimensions.0 as i32,
-                    self.dimensions.1 as i32,
-                ).map_err(|e| FileError(format!("Error creating PNG image surface: {}", e) ))?;
-                let ctx = Context::new(&surf).unwrap();
-                // ctx.scale(3.0, 3.0);
-                self.draw_to_context(&ctx, 0, 0, self.dimensions.0 as i32, self.dimensions.1 as i32)?;
+                let png = self.png()?;
                 let mut f = File::create(path).map_err(|e| FileError(format!("Unable to open PNG file:{}", e)))?;
-                surf.write_to_png(&mut f)
+                f.write_all(&png)
                     .map_err(|e| format!("Error writing content to png: {}", e) )?;
             },
+            #[cfg(feature="cairo-rs")]
             Some("eps") => {
                 let surf = PsSurface::new(
                     self.dimensions.0 as f64,
@@ -590,6 +956,16 @@ impl Panel {
                 let ctx = Context::new(&surf).unwrap();
                 self.draw_to_context(&ctx, 0, 0, self.dimensions.0 as i32, self.dimensions.1 as i32)?;
             },
+            #[cfg(feature="cairo-rs")]
+            Some("pdf") => {
+                let surf = PdfSurface::new(
+                    self.dimensions.0 as f64,
+                    self.dimensions.1 as f64,
+                    path
+                ).map_err(|e| FileError(format!("Error creating PDF surface: {}", e) ))?;
+                let ctx = Context::new(&surf).unwrap();
+                self.draw_to_context(&ctx, 0, 0, self.dimensions.0 as i32, self.dimensions.1 as i32)?;
+            },
             Some(other) => {
                 Err(FileError(format!("Invalid image export extension: {}", other)))?;
             },
@@ -609,6 +985,7 @@ impl Panel {
     /// of a gtk::DrawingArea. Users can also retrive the context
     /// from cairo::ImageSurface::create() to plot directly to
     /// SVG/PNG/PDF files.
+    #[cfg(feature="cairo-rs")]
     pub fn draw_to_context(
         &mut self,
         ctx : &Context,
@@ -617,6 +994,42 @@ impl Panel {
         w : i32,
         h : i32
     ) -> Result<(), Box<dyn Error>> {
+        self.draw_to_backend(&mut CairoBackend::new(ctx), x, y, w, h)
+    }
+
+    /// Draws the current Plot definition with any drawing backend, at the
+    /// rectangle with top-left corner (x, y) and size (w, h).
+    pub fn draw_to_backend(
+        &mut self,
+        ctx : &mut dyn DrawingBackend,
+        x : i32,
+        y : i32,
+        w : i32,
+        h : i32
+    ) -> Result<(), Box<dyn Error>> {
+
+        if !self.design.transparent {
+            ctx.begin_group("panel-background", "background", None)?;
+            fill_rect(ctx, &self.design.panel_bg_color, x as f64, y as f64, w as f64, h as f64)?;
+            ctx.end_group()?;
+        }
+
+        // The panel title takes a horizontal strip at the top, and the plots are
+        // laid out in the remaining area.
+        let (y, h) = match &self.title {
+            Some(title) => {
+                let font = title_font(&self.design, TITLE_SCALE, FontWeight::Bold);
+                let line_h = font.font_size as f64 * TITLE_LINE_SPACING;
+                ctx.begin_group("panel-title", "title", None)?;
+                ctx.set_color(&label_color());
+                let pos = Coord2D::new(x as f64 + w as f64 / 2., y as f64 + line_h / 2.);
+                text::draw_label(ctx, &font, title, pos, false, (true, true), None, None)?;
+                ctx.end_group()?;
+                (y + line_h as i32, h - line_h as i32)
+            },
+            None => (y, h)
+        };
+
         let top_left = (0.05, 0.05);
         let top_right = (w as f64 * self.h_ratio, 0.05);
         let bottom_left = (0.05, h as f64 * self.v_ratio);
@@ -676,20 +1089,34 @@ impl Panel {
             };
             let origin = (x as f64 + origin_offset.0, y as f64 + origin_offset.1);
             let size = ((w as f64 * scale_factor.0) as i32, (h as f64 * scale_factor.1) as i32);
-            ctx.save()?;
+            plot.tag = if self.tags {
+                Some(plot_tag(i))
+            } else {
+                None
+            };
+            let id = format!("plot-{}", i);
+            ctx.begin_group(&id, "plot", plot.title.as_deref())?;
             ctx.translate(origin.0, origin.1);
-            plot.draw_plot(&ctx, &self.design, size.0, size.1)?;
-            ctx.restore()?;
+            plot.draw_plot(ctx, &self.design, &id, size.0, size.1)?;
+            ctx.end_group()?;
         }
         Ok(())
     }
 
-    pub fn update_mapping(&mut self, ix : usize, id : &str, data : &Vec<Vec<f64>>) -> Result<(), Box<dyn Error>> {
-        self.plots[ix].update_mapping(id, data)
+    /// Replaces the data of a mapping with the given columns, which are copied into the mapping.
+    pub fn update_mapping(&mut self, ix : usize, id : &str, data : &[impl AsRef<[f64]>]) -> Result<(), Box<dyn Error>> {
+        self.plot_mut(ix)?.update_mapping(id, data)
     }
 
     pub fn update_mapping_text(&mut self, ix : usize, id : &str, text : &Vec<String>) -> Result<(), Box<dyn Error>> {
-        self.plots[ix].update_mapping_text(id, text)
+        self.plot_mut(ix)?.update_mapping_text(id, text)
+    }
+
+    fn plot_mut(&mut self, ix : usize) -> Result<&mut Plot, Box<dyn Error>> {
+        let n = self.plots.len();
+        self.plots.get_mut(ix).ok_or_else(|| {
+            format!("Invalid plot {} (panel has {} plots)", ix, n).into()
+        })
     }
 
     pub fn update_mapping_columns(&mut self, ix : usize, id : &str, cols : Vec<String>) -> Result<(), Box<dyn Error>> {
@@ -743,6 +1170,11 @@ impl Panel {
         self.plots.len()
     }
 
+    // Kind of each mapping (line, scatter, etc.) of the plot at the informed position
+    pub fn mapping_kinds(&self, ix : usize) -> Vec<String> {
+        self.plots.get(ix).map(|p| p.mappings.iter().map(|m| m.mapping_type() ).collect() ).unwrap_or_default()
+    }
+
     pub fn view_all_sources(&self) -> Vec<String> {
         self.plots.iter().map(|plot| plot.view_sources() ).flatten().collect()
     }
@@ -769,18 +1201,26 @@ impl Panel {
 #[derive(Clone, Debug)]
 pub struct Plot {
     mappings : Vec<Box<dyn Mapping>>,
+    annotations : Vec<Annotation>,
     mapper : ContextMapper,
     x : Scale,
     y : Scale,
+    title : Option<String>,
+    subtitle : Option<String>,
+    caption : Option<String>,
+
+    // Set by the panel at drawing time, if it is configured to tag its plots.
+    tag : Option<String>
 }
 
 impl Default for Plot {
     fn default() -> Self {
         let mappings = Vec::new();
+        let annotations = Vec::new();
         let mapper : ContextMapper = Default::default();
         let x : Scale = Default::default();
         let y : Scale = Default::default();
-        Plot{ mappings, mapper, x, y, }
+        Plot{ mappings, annotations, mapper, x, y, title : None, subtitle : None, caption : None, tag : None }
     }
 }
 
@@ -857,6 +1297,34 @@ impl Plot {
         self
     }
 
+    pub fn title(mut self, title : &str) -> Self {
+        self.title = Some(title.to_string());
+        self
+    }
+
+    pub fn subtitle(mut self, subtitle : &str) -> Self {
+        self.subtitle = Some(subtitle.to_string());
+        self
+    }
+
+    /// Caption or source line, drawn at the bottom-right of the plot.
+    pub fn caption(mut self, caption : &str) -> Self {
+        self.caption = Some(caption.to_string());
+        self
+    }
+
+    pub fn annotate(mut self, ann : Annotation) -> Self {
+        self.annotations.push(ann);
+        self.adjust_scales();
+        self
+    }
+
+    /// At polar coordinates, the x scale maps to the angle and the y scale to the radius.
+    pub fn coords(mut self, coords : Coordinates) -> Self {
+        self.mapper.polar = coords == Coordinates::Polar;
+        self
+    }
+
     pub fn update(&mut self, prop : PlotProperty) {
         match prop {
             PlotProperty::Scale(mode, prop) => {
@@ -914,10 +1382,15 @@ impl Plot {
             mappings.push(mappings::new_from_json(mem::take(mapping))?);
         }
 
+        let mut annotations = Vec::new();
+        for ann in rep.annotations.take().unwrap_or_default() {
+            annotations.push(Annotation::new_from_json(ann)?);
+        }
+
         let x = Scale::new_from_json(rep.x.clone())?;
         let y = Scale::new_from_json(rep.y.clone())?;
 
-        let mapper = ContextMapper::new(
+        let mut mapper = ContextMapper::new(
             x.from,
             x.to,
             y.from,
@@ -927,12 +1400,22 @@ impl Plot {
             x.invert,
             y.invert
         );
+        if let Some(coords) = rep.coords.take() {
+            let coords = Coordinates::from_str(&coords)
+                .map_err(|_| crate::model::PlotError::InvalidCoordinates(coords.clone()) )?;
+            mapper.polar = coords == Coordinates::Polar;
+        }
 
         let mut area = Self {
             mappings,
+            annotations,
             mapper,
             x,
             y,
+            title : rep.title.take(),
+            subtitle : rep.subtitle.take(),
+            caption : rep.caption.take(),
+            tag : None
         };
         area.adjust_scales();
 
@@ -941,24 +1424,129 @@ impl Plot {
         Ok(area)
     }
 
-    pub fn new() -> Self {
-        let mut pl : Plot = Default::default();
-        if let Ok(var) = std::env::var("PLOT_DEFAULT_WIDTH") {
-            pl.mapper.w = var.parse().unwrap();
-        }
-        if let Ok(var) = std::env::var("PLOT_DEFAULT_HEIGHT") {
-            pl.mapper.h = var.parse().unwrap();
+    /// Returns the plot definition, without design and layout (which are set at the panel).
+    pub fn to_model(&self) -> crate::model::Plot {
+        let coords = if self.mapper.polar { Coordinates::Polar } else { Coordinates::Cartesian };
+        crate::model::Plot {
+            mappings : self.mappings.iter().map(|m| m.to_model() ).collect(),
+            x : self.x.to_model(),
+            y : self.y.to_model(),
+            design : None,
+            layout : None,
+            annotations : if self.annotations.is_empty() {
+                None
+            } else {
+                Some(self.annotations.iter().map(|ann| ann.to_model() ).collect())
+            },
+            title : self.title.clone(),
+            subtitle : self.subtitle.clone(),
+            caption : self.caption.clone(),
+            coords : Some(coords.name().to_string())
         }
-        pl
     }
 
-    fn draw_plot(&mut self, ctx: &Context, design : &PlotDesign, w : i32, h : i32) -> Result<(), Box<dyn Error>> {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Draws the plot elements, each wrapped in a group whose id is prefixed by the plot id.
+    fn draw_plot(
+        &mut self,
+        ctx : &mut dyn DrawingBackend,
+        design : &PlotDesign,
+        id : &str,
+        w : i32,
+        h : i32
+    ) -> Result<(), Box<dyn Error>> {
         self.mapper.update_dimensions(w, h);
+        self.update_margins(design);
+        ctx.begin_group(&format!("{}-background", id), "background", None)?;
+        if let (Some(color), false) = (&design.plot_bg_color, design.transparent) {
+            fill_rect(ctx, color, 0.0, 0.0, w as f64, h as f64)?;
+        }
         self.draw_background(ctx, design)?;
-        self.draw_grid(ctx, design)?;
-        for mapping in self.mappings.iter() {
-            mapping.draw(&self.mapper, &ctx)?;
+        ctx.end_group()?;
+        self.draw_grid(ctx, design, id)?;
+        for (i, mapping) in self.mappings.iter().enumerate() {
+            let kind = mapping.mapping_type();
+            ctx.begin_group(
+                &format!("{}-mapping-{}", id, i),
+                &format!("mapping {}", kind),
+                Some(&mapping_label(&kind, &mapping.get_ordered_col_names()))
+            )?;
+            mapping.draw(&self.mapper, ctx)?;
+            ctx.end_group()?;
         }
+        for (i, ann) in self.annotations.iter().enumerate() {
+            ctx.begin_group(&format!("{}-annotation-{}", id, i), "annotation", None)?;
+            ann.draw(&self.mapper, ctx)?;
+            ctx.end_group()?;
+        }
+        ctx.begin_group(&format!("{}-titles", id), "titles", None)?;
+        self.draw_titles(ctx, design)?;
+        ctx.end_group()?;
+        Ok(())
+    }
+
+    /// Reserves space above the data area for the title, subtitle and tag,
+    /// and below it for the caption.
+    fn update_margins(&mut self, design : &PlotDesign) {
+        let font_size = design.font.font_size as f64;
+        let mut top = 0.0;
+        if self.title.is_some() || self.tag.is_some() {
+            top += (font_size * TITLE_SCALE).round() * TITLE_LINE_SPACING;
+        }
+        if self.subtitle.is_some() {
+            top += font_size * TITLE_LINE_SPACING;
+        }
+        let mut bottom = 0.0;
+        if self.caption.is_some() {
+            bottom += (font_size * CAPTION_SCALE).round() * TITLE_LINE_SPACING;
+        }
+
+        // Never let the margins take more than half the plot height.
+        let max_margin = 0.5 * self.mapper.h as f64;
+        if top + bottom > max_margin {
+            let ratio = max_margin / (top + bottom);
+            top *= ratio;
+            bottom *= ratio;
+        }
+        self.mapper.update_margins(top, bottom);
+    }
+
+    fn draw_titles(&self, ctx : &mut dyn DrawingBackend, design : &PlotDesign) -> Result<(), Box<dyn Error>> {
+        ctx.save()?;
+        ctx.set_color(&label_color());
+        let (area_x, _, area_w, _) = self.mapper.data_area();
+        let center_x = area_x + area_w / 2.;
+        let mut line_top = 0.0;
+        if self.title.is_some() || self.tag.is_some() {
+            let font = title_font(design, TITLE_SCALE, FontWeight::Bold);
+            let line_h = font.font_size as f64 * TITLE_LINE_SPACING;
+            if let Some(title) = &self.title {
+                let pos = Coord2D::new(center_x, line_top + line_h / 2.);
+                text::draw_label(ctx, &font, title, pos, false, (true, true), None, None)?;
+            }
+            if let Some(tag) = &self.tag {
+                let pos = Coord2D::new(font.font_size as f64 * 0.5, line_top + line_h / 2.);
+                text::draw_label(ctx, &font, tag, pos, false, (false, true), None, None)?;
+            }
+            line_top += line_h;
+        }
+        if let Some(subtitle) = &self.subtitle {
+            let line_h = design.font.font_size as f64 * TITLE_LINE_SPACING;
+            let pos = Coord2D::new(center_x, line_top + line_h / 2.);
+            text::draw_label(ctx, &design.font, subtitle, pos, false, (true, true), None, None)?;
+        }
+        if let Some(caption) = &self.caption {
+            let font = title_font(design, CAPTION_SCALE, FontWeight::Normal);
+            let line_h = font.font_size as f64 * TITLE_LINE_SPACING;
+
+            // Right-aligned with the data area.
+            let pos = Coord2D::new(area_x + area_w, self.mapper.h as f64 - line_h / 2.);
+            text::draw_label(ctx, &font, caption, pos, false, (false, true), Some(-1.0), None)?;
+        }
+        ctx.restore()?;
         Ok(())
     }
 
@@ -969,6 +1557,10 @@ impl Plot {
             x_lims.push(xl);
             y_lims.push(yl);
         }
+
+        // Annotations only contribute to the limits when they are set to adjust.
+        x_lims.extend(self.annotations.iter().filter_map(|a| a.x_limits() ));
+        y_lims.extend(self.annotations.iter().filter_map(|a| a.y_limits() ));
         let min_x = x_lims.iter().min_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal) )?.0;
         let max_x = x_lims.iter().max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal) )?.1;
         let min_y = y_lims.iter().min_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal) )?.0;
@@ -1020,27 +1612,27 @@ impl Plot {
     pub fn update_mapping(
         &mut self,
         id : &str,
-        data : &Vec<Vec<f64>>
+        data : &[impl AsRef<[f64]>]
     ) -> Result<(), Box<dyn Error>> {
         if data.len() < 1 {
             return Err(Box::new(PlotError::InvalidData("Invalid data")))
         }
+        let kind = match id.parse::<usize>().ok().and_then(|ix| self.mappings.get(ix) ) {
+            Some(mapping) => mapping.mapping_type(),
+            None => return Err("Cannot recover mapping".into())
+        };
+        if data.len() != data_columns(&kind) || data.iter().any(|col| col.as_ref().len() != data[0].as_ref().len() ) {
+            return Err(format!("Mappings of kind {} require {} columns of the same length", kind, data_columns(&kind)).into());
+        }
         let (xmin, xmax, ymin, ymax) = self.mapper.data_extensions();
         if data.len() == 1 {
-            self.accomodate_dimension(&data[0][..], ymin, ymax, "y");
-        } else {
-            self.accomodate_dimension(&data[0][..], xmin, xmax, "x");
-            self.accomodate_dimension(&data[1][..], ymin, ymax, "y");
-        }
-        if let Some(mapping) = self.mappings.get_mut(id.parse::<usize>().unwrap()) {
-            mapping.update_data(data.clone());
-            Ok(())
+            self.accomodate_dimension(data[0].as_ref(), ymin, ymax, "y");
         } else {
-            Err(Box::new(std::io::Error::new(
-                ErrorKind::Other,
-                "Cannot recover mapping "
-            )))
+            self.accomodate_dimension(data[0].as_ref(), xmin, xmax, "x");
+            self.accomodate_dimension(data[1].as_ref(), ymin, ymax, "y");
         }
+        self.mappings[id.parse::<usize>()?].update_data(data.iter().map(|col| col.as_ref().to_vec() ).collect());
+        Ok(())
     }
 
     pub fn update_mapping_text(
@@ -1048,7 +1640,7 @@ impl Plot {
         id : &str,
         text : &Vec<String>
     ) -> Result<(), Box<dyn Error>> {
-        if let Some(mapping) = self.mappings.get_mut(id.parse::<usize>().unwrap()) {
+        if let Some(mapping) = id.parse::<usize>().ok().and_then(|ix| self.mappings.get_mut(ix) ) {
             mapping.update_extra_data(vec![text.clone()]);
             Ok(())
         } else {
@@ -1104,20 +1696,20 @@ impl Plot {
         }
     }
 
-    fn draw_background(&self, ctx : &Context, design : &PlotDesign) -> Result<(), Box<dyn Error>> {
+    fn draw_background(&self, ctx : &mut dyn DrawingBackend, design : &PlotDesign) -> Result<(), Box<dyn Error>> {
+        if design.transparent {
+            return Ok(());
+        }
         ctx.save()?;
         ctx.set_line_width(0.0);
-        ctx.set_source_rgb(
-            design.bg_color.red().into(),
-            design.bg_color.green().into(),
-            design.bg_color.blue().into()
-        );
-        ctx.rectangle(
-            context_mapper::REL_X_OFFSET*(self.mapper.w as f64), 
-            context_mapper::REL_Y_OFFSET*(self.mapper.h as f64),
-            context_mapper::REL_WIDTH*(self.mapper.w as f64), 
-            context_mapper::REL_HEIGHT*(self.mapper.h as f64)
-        );
+        ctx.set_color(&design.bg_color.with_alpha(1.0));
+        if self.mapper.polar {
+            let (cx, cy, r) = self.mapper.polar_area();
+            ctx.arc(cx, cy, r, 0.0, 2.0*std::f64::consts::PI);
+        } else {
+            let (area_x, area_y, area_w, area_h) = self.mapper.data_area();
+            ctx.rectangle(area_x, area_y, area_w, area_h);
+        }
         ctx.fill()?;
         ctx.restore()?;
         Ok(())
@@ -1125,17 +1717,13 @@ impl Plot {
 
     fn draw_grid_line(
         &self,
-        ctx : &Context,
+        ctx : &mut dyn DrawingBackend,
         design : &PlotDesign,
         from : Coord2D,
         to : Coord2D
     ) -> Result<(), Box<dyn Error>> {
         ctx.save()?;
-        ctx.set_source_rgb(
-            design.grid_color.red().into(),
-            design.grid_color.green().into(),
-            design.grid_color.blue().into()
-        );
+        ctx.set_color(&design.grid_color.with_alpha(1.0));
         ctx.move_to(from.x, from.y);
         ctx.line_to(to.x, to.y);
         ctx.stroke()?;
@@ -1147,7 +1735,7 @@ impl Plot {
     /// to center the x value (true for the x labels; false for the y labels).
     fn draw_grid_value(
         &self,
-        ctx : &Context,
+        ctx : &mut dyn DrawingBackend,
         design : &PlotDesign,
         value : &str,
         pos : Coord2D,
@@ -1155,10 +1743,10 @@ impl Plot {
         ext_off_x : f64,
         ext_off_y : f64
     ) -> Result<(), Box<dyn Error>> {
-        ctx.set_source_rgb(0.2666, 0.2666, 0.2666);
+        ctx.set_color(&label_color());
         text::draw_label(
-            &design.font.sf,
             ctx,
+            &design.font,
             &value[..],
             pos,
             false,
@@ -1180,18 +1768,21 @@ impl Plot {
 
     fn get_max_extent(
         &self,
+        ctx : &mut dyn DrawingBackend,
         design : &PlotDesign,
         labels : &Vec<String>
     ) -> f64 {
         labels.iter()
-            .map(|l| design.font.sf.text_extents(&l[..]).x_advance())
+            .map(|l| ctx.text_extents(&design.font, &l[..]).x_advance )
             .fold(0.0, |m, f| f64::max(m,f))
     }
 
-    fn draw_grid(&self, ctx : &Context, design : &PlotDesign) -> Result<(), Box<dyn Error>> {
-        ctx.save()?;
-        ctx.set_line_width(design.grid_width as f64);
-        design.font.set_font_into_context(&ctx);
+    /// Draws the grid lines, followed by the axis labels and scale names, so grid lines
+    /// and each axis are grouped separately.
+    fn draw_grid(&self, ctx : &mut dyn DrawingBackend, design : &PlotDesign, id : &str) -> Result<(), Box<dyn Error>> {
+        if self.mapper.polar {
+            return self.draw_polar_grid(ctx, design, id);
+        }
         let mut x_labels = Plot::steps_to_labels(
             &self.x.steps[..],
             self.x.precision as usize
@@ -1199,7 +1790,8 @@ impl Plot {
         if self.mapper.xinv {
             x_labels.reverse();
         }
-        for (x, x_label) in self.x.steps.iter().zip(x_labels.iter()) {
+        let mut x_lines = Vec::new();
+        for x in self.x.steps.iter() {
             let from = match (self.mapper.xinv, self.mapper.yinv) {
                 (false, false) => self.mapper.map(*x, self.mapper.ymin),
                 (false, true) => self.mapper.map(*x, self.mapper.ymax),
@@ -1212,11 +1804,7 @@ impl Plot {
                 (true, false) =>  self.mapper.map(self.mapper.xmin + self.mapper.xmax - *x, self.mapper.ymax),
                 (true, true) => self.mapper.map(self.mapper.xmin + self.mapper.xmax - *x, self.mapper.ymin)
             };
-            
-            self.draw_grid_line(ctx, design, from, to)?;
-            if self.x.guide {
-                self.draw_grid_value(ctx, design, x_label, from, true, 0.0, 1.5)?;
-            }
+            x_lines.push((from, to));
         }
 
         let mut y_labels = Plot::steps_to_labels(
@@ -1226,9 +1814,9 @@ impl Plot {
         if self.mapper.yinv {
             y_labels.reverse();
         }
-        let max_extent = self.get_max_extent(design, &y_labels);
-        for (y, y_label) in self.y.steps.iter().zip(y_labels.iter()) {
-            let mut from = match (self.mapper.xinv, self.mapper.yinv) {
+        let mut y_lines = Vec::new();
+        for y in self.y.steps.iter() {
+            let from = match (self.mapper.xinv, self.mapper.yinv) {
                 (false, false) => self.mapper.map(self.mapper.xmin, *y),
                 (false, true) => self.mapper.map(self.mapper.xmin, self.mapper.ymin + self.mapper.ymax - *y),
                 (true, false) => self.mapper.map(self.mapper.xmax, *y),
@@ -1240,29 +1828,113 @@ impl Plot {
                 (true, false) =>  self.mapper.map(self.mapper.xmin, *y),
                 (true, true) => self.mapper.map(self.mapper.xmin, self.mapper.ymin + self.mapper.ymax - *y)
             };
-            self.draw_grid_line(ctx, design, from, to)?;
-            //let mut y_label_coord = match self.mapper.yinv {
-            //    true => to,
-            //    false => from
-            //};
-            from.x -= 1.1*max_extent;
-            if self.y.guide {
-                self.draw_grid_value(ctx, design, y_label, from, false, 0.0, 0.0)?;
+            y_lines.push((from, to));
+        }
+
+        ctx.begin_group(&format!("{}-grid", id), "grid", None)?;
+        ctx.set_line_width(design.grid_width as f64);
+        for (from, to) in x_lines.iter().chain(y_lines.iter()) {
+            self.draw_grid_line(ctx, design, *from, *to)?;
+        }
+        ctx.end_group()?;
+
+        ctx.begin_group(&format!("{}-x-axis", id), "axis x", None)?;
+        ctx.set_color(&label_color());
+        if self.x.guide {
+            for ((from, _), x_label) in x_lines.iter().zip(x_labels.iter()) {
+                self.draw_grid_value(ctx, design, x_label, *from, true, 0.0, 1.5)?;
             }
         }
-        self.draw_scale_names(ctx, design)?;
-        ctx.restore()?;
+        self.draw_x_scale_name(ctx, design)?;
+        ctx.end_group()?;
+
+        ctx.begin_group(&format!("{}-y-axis", id), "axis y", None)?;
+        ctx.set_color(&label_color());
+        if self.y.guide {
+            let max_extent = self.get_max_extent(ctx, design, &y_labels);
+            for ((from, _), y_label) in y_lines.iter().zip(y_labels.iter()) {
+                let pos = Coord2D::new(from.x - 1.1*max_extent, from.y);
+                self.draw_grid_value(ctx, design, y_label, pos, false, 0.0, 0.0)?;
+            }
+        }
+        self.draw_y_scale_name(ctx, design)?;
+        ctx.end_group()?;
         Ok(())
     }
 
-    fn draw_scale_names(&self, ctx : &Context, design : &PlotDesign) -> Result<(), Box<dyn Error>> {
+    /// Draws circular gridlines at the radius (y) steps, and radial gridlines
+    /// at the angle (x) steps, with the angle labels outside the circle and
+    /// the radius labels along the vertical line above the center.
+    fn draw_polar_grid(&self, ctx : &mut dyn DrawingBackend, design : &PlotDesign, id : &str) -> Result<(), Box<dyn Error>> {
+        let (cx, cy, r) = self.mapper.polar_area();
+        let center = Coord2D::new(cx, cy);
+        let font_size = design.font.font_size as f64;
+
+        // The edge is the mapped point farthest from the center, which depends on y inversion.
+        let edges : Vec<Coord2D> = self.x.steps.iter().map(|x| {
+            let (a, b) = (self.mapper.map(*x, self.mapper.ymin), self.mapper.map(*x, self.mapper.ymax));
+            if center.distance(a) > center.distance(b) { a } else { b }
+        }).collect();
+        let radii : Vec<f64> = self.y.steps.iter()
+            .map(|y| center.distance(self.mapper.map(self.mapper.xmin, *y)) )
+            .collect();
+
+        ctx.begin_group(&format!("{}-grid", id), "grid", None)?;
+        ctx.set_line_width(design.grid_width as f64);
+        for edge in edges.iter() {
+            self.draw_grid_line(ctx, design, center, *edge)?;
+        }
+        ctx.set_color(&design.grid_color.with_alpha(1.0));
+        for radius in radii.iter() {
+            ctx.new_sub_path();
+            ctx.arc(cx, cy, *radius, 0.0, 2.0*std::f64::consts::PI);
+            ctx.stroke()?;
+        }
+        ctx.end_group()?;
+
+        ctx.begin_group(&format!("{}-x-axis", id), "axis x", None)?;
+        ctx.set_color(&label_color());
+        if self.x.guide && r > 0.0 {
+            let x_labels = Plot::steps_to_labels(&self.x.steps[..], self.x.precision as usize);
+            for (i, (edge, x_label)) in edges.iter().zip(x_labels.iter()).enumerate() {
+
+                // The angle scale wraps around, so the last step might fall over the first.
+                if i > 0 && edges[0].distance(*edge) < 1.0 {
+                    continue;
+                }
+                let ratio = (r + 1.5*font_size) / r;
+                let pos = Coord2D::new(cx + (edge.x - cx)*ratio, cy + (edge.y - cy)*ratio);
+                self.draw_grid_value(ctx, design, x_label, pos, true, 0.0, 0.0)?;
+            }
+        }
+        self.draw_x_scale_name(ctx, design)?;
+        ctx.end_group()?;
+
+        ctx.begin_group(&format!("{}-y-axis", id), "axis y", None)?;
+        ctx.set_color(&label_color());
+        if self.y.guide {
+            let y_labels = Plot::steps_to_labels(&self.y.steps[..], self.y.precision as usize);
+            for (radius, y_label) in radii.iter().zip(y_labels.iter()) {
+                let pos = Coord2D::new(cx + 0.25*font_size, cy - radius - 0.5*font_size);
+                self.draw_grid_value(ctx, design, y_label, pos, false, 0.0, 0.0)?;
+            }
+        }
+        self.draw_y_scale_name(ctx, design)?;
+        ctx.end_group()?;
+        Ok(())
+    }
+
+    fn draw_x_scale_name(&self, ctx : &mut dyn DrawingBackend, design : &PlotDesign) -> Result<(), Box<dyn Error>> {
+        if self.x.label.is_empty() {
+            return Ok(());
+        }
         let pos_x = Coord2D::new(
             self.mapper.w as f64 * 0.5,
-            self.mapper.h as f64 * 0.975
+            self.mapper.h as f64 * 0.975 - self.mapper.bottom_margin
         );
         text::draw_label(
-            &design.font.sf,
             ctx,
+            &design.font,
             &self.x.label[..],
             pos_x,
             false,
@@ -1270,15 +1942,22 @@ impl Plot {
             None,
             None
         )?;
-        
+        Ok(())
+    }
+
+    fn draw_y_scale_name(&self, ctx : &mut dyn DrawingBackend, design : &PlotDesign) -> Result<(), Box<dyn Error>> {
+        if self.y.label.is_empty() {
+            return Ok(());
+        }
         // export POS_X=0.1
+        let (_, area_y, _, area_h) = self.mapper.data_area();
         let pos_y = Coord2D::new(
             self.mapper.w as f64 * 0.005, // self.mapper.w as f64 * 0.025,
-            self.mapper.h as f64 * 0.5
+            area_y + area_h * 0.5
         );
         text::draw_label(
-            &design.font.sf,
             ctx,
+            &design.font,
             &self.y.label[..],
             pos_y,
             true,
@@ -1339,3 +2018,225 @@ impl Plot {
 
 }
 
+
+#[test]
+fn panel_round_trip() {
+    let scale = |label : &str, from : f64, to : f64| serde_json::json!({
+        "label" : label, "from" : from, "to" : to, "precision" : 2, "intervals" : 4, "log" : false,
+        "invert" : false, "offset" : 0, "adjust" : "off", "guide" : true
+    });
+    let def = serde_json::json!({
+        "plots" : [
+            {
+                "mappings" : [
+                    { "kind" : "line", "map" : { "x" : [0.0, 1.0, 2.0], "y" : [1.0, null, 3.0] }, "color" : "#4682b4", "width" : 2.0, "spacing" : 3.0, "closed" : false },
+                    { "kind" : "scatter", "map" : { "x" : [0.0, 1.0], "y" : [1.0, 2.0] }, "color" : "#ff000080", "radius" : 4.0 },
+                    { "kind" : "text", "map" : { "x" : [0.5], "y" : [2.5], "text" : ["peak"] }, "color" : "#000000", "font" : "Sans Bold Italic 12" }
+                ],
+                "x" : scale("x", 0.0, 2.0),
+                "y" : scale("y", 0.0, 4.0),
+                "annotations" : [
+                    { "kind" : "hline", "y" : 2.0, "color" : "#ff0000", "width" : 1.0, "spacing" : 2.0, "font" : "Monospace 10", "adjust" : false },
+                    { "kind" : "vspan", "x" : 0.5, "x_end" : 1.0, "color" : "#00000022", "width" : 1.0, "spacing" : 1.0, "font" : "Monospace 10", "adjust" : true },
+                    { "kind" : "arrow", "x" : 1.0, "y" : 2.0, "x_end" : 1.5, "y_end" : 3.0, "text" : "here", "color" : "#000000", "width" : 1.0, "spacing" : 1.0, "font" : "Monospace Bold 14", "adjust" : false }
+                ],
+                "title" : "Lines",
+                "subtitle" : "and points",
+                "caption" : "Source: none",
+                "coords" : "cartesian"
+            },
+            {
+                "mappings" : [
+                    { "kind" : "bar", "map" : { "x" : [1.0, 3.0, 2.0] }, "color" : "#808080", "width" : 0.5, "spacing" : 1.0, "vertical" : true, "center" : true, "origin" : 0.0 },
+                    { "kind" : "interval", "map" : { "x" : [0.0, 1.0], "y" : [0.5, 1.0], "z" : [1.5, 2.5] }, "color" : "#0000ff", "width" : 1.0, "spacing" : 1.0, "limits" : 0.1, "vertical" : true },
+                    { "kind" : "area", "map" : { "x" : [0.0, 1.0, 2.0], "y" : [0.0, 0.5, 0.0], "z" : [1.0, 1.5, 1.0] }, "color" : "#00ff0040" }
+                ],
+                "x" : scale("", -0.5, 2.5),
+                "y" : scale("", 0.0, 3.0),
+                "coords" : "cartesian"
+            },
+            {
+                "mappings" : [
+                    { "kind" : "wedge", "map" : { "x" : [1.0, 2.0, 3.0] }, "color" : "#ff0000", "color_final" : "#0000ff", "origin" : 0.5 }
+                ],
+                "x" : scale("", 0.0, 6.0),
+                "y" : scale("", 0.0, 1.0),
+                "coords" : "polar"
+            },
+            {
+                "mappings" : [
+                    { "kind" : "bar", "map" : { "x" : [2.0, 1.0] }, "color" : "#000000", "width" : 1.0, "spacing" : 0.5, "vertical" : false, "center" : false, "origin" : 1.0 }
+                ],
+                "x" : scale("", 0.0, 2.0),
+                "y" : scale("", 1.0, 2.0),
+                "coords" : "cartesian"
+            }
+        ],
+        "design" : {
+            "bgcolor" : "#ffffff", "fgcolor" : "#d3d7cf", "width" : 2, "font" : "Monospace Regular 22",
+            "panel_bgcolor" : "#eeeeee", "plot_bgcolor" : "#fafafa", "transparent" : false
+        },
+        "layout" : { "width" : 800.0, "height" : 600.0, "hratio" : 0.4, "vratio" : 0.6, "split" : "four", "dpi" : 192.0 },
+        "title" : "All mappings",
+        "tags" : true
+    });
+
+    // Fully-specified definitions are recovered exactly (missing values are written as null).
+    let model : crate::model::Panel = serde_json::from_value(def).unwrap();
+    let panel = Panel::new_from_model(model.clone()).unwrap();
+    assert_eq!(serde_json::to_value(panel.to_model()).unwrap(), serde_json::to_value(&model).unwrap());
+    let json = panel.to_json().unwrap();
+    assert_eq!(Panel::new_from_json(&json).unwrap().to_json().unwrap(), json);
+
+    // Physical units and resolution are kept, and resized panels are written in the same unit.
+    let mut model = model;
+    model.layout = Some(crate::model::Layout {
+        width : 210.0,
+        height : 297.0,
+        unit : Some(String::from("mm")),
+        dpi : Some(300.0),
+        ..model.layout.unwrap()
+    });
+    let panel = Panel::new_from_model(model.clone()).unwrap();
+    assert_eq!(serde_json::to_value(panel.to_model()).unwrap(), serde_json::to_value(&model).unwrap());
+    let layout = panel.dimensions(720, 360).to_model().layout.unwrap();
+    assert!((layout.width - 254.0).abs() < 1e-9 && (layout.height - 127.0).abs() < 1e-9);
+    assert_eq!((layout.unit.as_deref(), layout.dpi), (Some("mm"), Some(300.0)));
+
+    // Definitions relying on defaults and adjusted scales are stable after the first round trip,
+    // and carry the changes made to the panel.
+    let mut panel = Panel::new_from_json(r#"{"kind":"line","map":{"x":[0,1,2],"y":[0,1,4]}}"#).unwrap();
+    panel.update_mapping(0, "0", &[&[0.0, 1.0][..], &[5.0, 6.0][..]]).unwrap();
+    let json = panel.to_json().unwrap();
+    assert_eq!(Panel::new_from_json(&json).unwrap().to_json().unwrap(), json);
+    assert_eq!(panel.to_model().plots[0].mappings[0].map.y, Some(vec![5.0, 6.0]));
+}
+
+#[test]
+fn titles_and_tags() {
+    assert_eq!(plot_tag(0), "A");
+    assert_eq!(plot_tag(25), "Z");
+    assert_eq!(plot_tag(26), "AA");
+    assert_eq!(plot_tag(27), "AB");
+    assert_eq!(plot_tag(701), "ZZ");
+    assert_eq!(plot_tag(702), "AAA");
+
+    let plot = || {
+        let mapping = crate::model::Mapping {
+            kind : String::from("line"),
+            map : crate::model::Map { x : Some(vec![0.0, 1.0]), y : Some(vec![0.0, 1.0]), ..Default::default() },
+            ..Default::default()
+        };
+        Plot::new_from_model(crate::model::Plot { mappings : vec![mapping], ..Default::default() }).unwrap()
+    };
+    let first = plot().title("First").subtitle("Sub").caption("Cap");
+    let mut panel = Panel::pair(Orientation::Horizontal, first, plot()).title("Panel title").tags(true);
+    let svg = panel.semantic_svg().unwrap();
+    for text in [">First<", ">Sub<", ">Cap<", ">Panel title<", ">A<", ">B<"] {
+        assert!(svg.contains(text), "{} not found", text);
+    }
+
+    // Titles take space from the data area of their plot only.
+    let (_, y0, _, h0) = panel.plots[0].mapper.data_area();
+    let (_, y1, _, h1) = panel.plots[1].mapper.data_area();
+    assert!(y0 > y1 && h0 < h1);
+    assert!(panel.to_model().tags.unwrap());
+}
+
+#[cfg(feature="cairo-rs")]
+#[test]
+fn png_resolution() {
+    let mut panel = Panel::new_from_json(r#"{
+        "kind" : "line",
+        "map" : { "x" : [0, 1], "y" : [0, 1] }
+    }"#).unwrap();
+    let mut model = panel.to_model();
+    model.layout = Some(crate::model::Layout { width : 50.8, height : 25.4, unit : Some(String::from("mm")), dpi : Some(300.0), ..model.layout.unwrap() });
+    panel = Panel::new_from_model(model).unwrap();
+    assert_eq!(panel.raster_dimensions(), (600, 300));
+    assert_eq!(panel.dpi(), 300.0);
+
+    // 300 dpi are 11811 pixels per meter.
+    let png = panel.png().unwrap();
+    assert_eq!(&png[33..37], &9u32.to_be_bytes());
+    assert_eq!(&png[37..41], b"pHYs");
+    assert_eq!(&png[41..50], &[0, 0, 0x2e, 0x23, 0, 0, 0x2e, 0x23, 1]);
+    assert_eq!(&png[50..54], &0x78a5_3f76u32.to_be_bytes());
+    assert_eq!(&png[16..24], &[0, 0, 2, 0x58, 0, 0, 1, 0x2c]);
+}
+
+// Panel of 85 x 42.5 mm (241 x 120 points) at 600 dpi.
+#[cfg(test)]
+fn physical_panel() -> Panel {
+    let panel = Panel::new_from_json(r#"{"kind":"line","map":{"x":[0,1],"y":[0,1]}}"#).unwrap();
+    let mut model = panel.to_model();
+    model.layout = Some(crate::model::Layout { width : 85.0, height : 42.5, unit : Some(String::from("mm")), dpi : Some(600.0), ..model.layout.unwrap() });
+    Panel::new_from_model(model).unwrap()
+}
+
+#[test]
+fn svg_physical_size() {
+    let root = "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"241pt\" height=\"120pt\" viewBox=\"0 0 241 120\"";
+    let mut panel = physical_panel();
+    assert!(panel.semantic_svg().unwrap().contains(root));
+    assert!(panel.html_interactive().unwrap().contains(root));
+    #[cfg(not(feature="cairo-rs"))]
+    assert!(panel.svg().unwrap().contains(root));
+
+    // Pixel layouts keep unitless (pixel) sizes.
+    let mut panel = Panel::new_from_json(r#"{"kind":"line","map":{"x":[0,1],"y":[0,1]}}"#).unwrap().dimensions(300, 200);
+    assert!(panel.semantic_svg().unwrap().contains("width=\"300\" height=\"200\" viewBox=\"0 0 300 200\""));
+}
+
+// Both backends give physical layouts the same size.
+#[cfg(feature="cairo-rs")]
+#[test]
+fn backend_physical_size() {
+    let root_size = |svg : &str| -> (String, String) {
+        let root = &svg[svg.find("<svg").unwrap()..];
+        let root = &root[..root.find('>').unwrap()];
+        let attr = |name : &str| root.split(&format!(" {}=\"", name)[..]).nth(1).unwrap().split('"').next().unwrap().to_string();
+        (attr("width"), attr("height"))
+    };
+    let mut panel = physical_panel();
+    let cairo = root_size(&panel.svg().unwrap());
+    assert_eq!(cairo, (String::from("241pt"), String::from("120pt")));
+    assert_eq!(root_size(&panel.semantic_svg().unwrap()), cairo);
+}
+
+#[test]
+fn backgrounds() {
+    let mut panel = Panel::new_from_json(r#"{"kind":"line","map":{"x":[0,1],"y":[0,1]}}"#).unwrap();
+    let design = |panel_bgcolor : Option<&str>, transparent : bool| crate::model::Design {
+        bgcolor : String::from("#778899"),
+        panel_bgcolor : panel_bgcolor.map(String::from),
+        plot_bgcolor : Some(String::from("#445566")),
+        transparent : Some(transparent),
+        ..Default::default()
+    };
+    let fills = |panel : &mut Panel| {
+        let svg = panel.semantic_svg().unwrap();
+        ["#112233", "#445566", "#778899"].map(|c| svg.contains(&format!("fill=\"{}\"", c)) )
+    };
+
+    // Panel, plot and data area backgrounds are filled with their own colors.
+    panel.set_design(design(Some("#112233"), false)).unwrap();
+    assert_eq!(fills(&mut panel), [true, true, true]);
+
+    // The panel background defaults to the data area background.
+    panel.set_design(design(None, false)).unwrap();
+    assert_eq!(fills(&mut panel), [false, true, true]);
+    assert_eq!(panel.design.panel_bg_color.to_string(), "#778899");
+
+    // Transparent designs fill no backgrounds.
+    panel.set_design(design(Some("#112233"), true)).unwrap();
+    assert_eq!(fills(&mut panel), [false, false, false]);
+
+    #[cfg(feature="cairo-rs")]
+    {
+        let surf = panel.draw_to_image().unwrap();
+        let mut data = Vec::new();
+        surf.with_data(|d| data.extend_from_slice(d) ).unwrap();
+        assert_eq!(data[3], 0);
+    }
+}
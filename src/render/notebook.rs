@@ -0,0 +1,128 @@
+/*Copyright (c) 2022 Diego da Silva Lima. All rights reserved.
+
+This work is licensed under the terms of the MIT license.  
+For a copy, see <https://opensource.org/licenses/MIT>.*/
+
+/*! Display at evcxr Jupyter kernels. Panels, plots and panel definitions implement the
+evcxr display protocol (an evcxr_display method), so that they are shown inline when they are
+the last expression of a cell. The format and size of displayed images are global settings
+(see set_display_settings), applied to all displays. !*/
+
+use std::str::FromStr;
+use std::sync::RwLock;
+use super::{Panel, Plot};
+
+/// Format of the images shown at notebooks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisplayFormat {
+
+    Svg,
+
+    /// Requires the cairo-rs feature (SVG is shown otherwise).
+    Png
+}
+
+impl FromStr for DisplayFormat {
+
+    type Err = ();
+
+    fn from_str(s : &str) -> Result<Self, ()> {
+        match s {
+            "svg" => Ok(Self::Svg),
+            "png" => Ok(Self::Png),
+            _ => Err(())
+        }
+    }
+
+}
+
+/// Settings used by all notebook displays. Width and height (in pixels) override the
+/// dimensions of displayed panels when set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DisplaySettings {
+    pub format : DisplayFormat,
+    pub width : Option<u32>,
+    pub height : Option<u32>
+}
+
+impl Default for DisplaySettings {
+
+    fn default() -> Self {
+        DEFAULT_SETTINGS
+    }
+
+}
+
+const DEFAULT_SETTINGS : DisplaySettings = DisplaySettings { format : DisplayFormat::Svg, width : None, height : None };
+
+static SETTINGS : RwLock<DisplaySettings> = RwLock::new(DEFAULT_SETTINGS);
+
+pub fn display_settings() -> DisplaySettings {
+    SETTINGS.read().map(|s| *s ).unwrap_or_default()
+}
+
+pub fn set_display_settings(settings : DisplaySettings) {
+    if let Ok(mut s) = SETTINGS.write() {
+        *s = settings;
+    }
+}
+
+// Renders the panel with the current settings, wrapped in the delimiters of the evcxr protocol.
+fn display_content(panel : &Panel) -> String {
+    let settings = display_settings();
+    let mut panel = panel.clone();
+    if settings.width.is_some() || settings.height.is_some() {
+        let (w, h) = panel.get_dimensions();
+        panel = panel.dimensions(settings.width.unwrap_or(w as u32), settings.height.unwrap_or(h as u32));
+    }
+    let content = match settings.format {
+
+        #[cfg(feature="cairo-rs")]
+        DisplayFormat::Png => panel.png().map(|png| ("image/png", base64::encode(png)) ),
+
+        _ => panel.svg().map(|svg| ("image/svg+xml", svg) )
+    };
+    let (mime, data) = content.unwrap_or_else(|e| ("text/plain", format!("Unable to render panel: {}", e)) );
+    format!("EVCXR_BEGIN_CONTENT {}\n{}\nEVCXR_END_CONTENT", mime, data)
+}
+
+impl Panel {
+
+    /// Shows the panel at evcxr notebooks (see display_settings).
+    pub fn evcxr_display(&self) {
+        println!("{}", display_content(self));
+    }
+
+}
+
+impl Plot {
+
+    /// Shows the plot at evcxr notebooks (see display_settings).
+    pub fn evcxr_display(&self) {
+        println!("{}", display_content(&self.wrap()));
+    }
+
+}
+
+impl crate::model::Panel {
+
+    /// Shows the panel definition at evcxr notebooks (see display_settings).
+    pub fn evcxr_display(&self) {
+        match Panel::new_from_model(self.clone()) {
+            Ok(panel) => panel.evcxr_display(),
+            Err(e) => println!("EVCXR_BEGIN_CONTENT text/plain\nInvalid panel: {}\nEVCXR_END_CONTENT", e)
+        }
+    }
+
+}
+
+#[test]
+fn notebook_display() {
+    let panel = Panel::new_from_json(r#"{"kind":"line","map":{"x":[0,1],"y":[0,1]}}"#).unwrap();
+    let content = display_content(&panel);
+    assert!(content.starts_with("EVCXR_BEGIN_CONTENT image/svg+xml\n"));
+    assert!(content.ends_with("\nEVCXR_END_CONTENT"));
+    set_display_settings(DisplaySettings { width : Some(321), ..Default::default() });
+    assert!(display_content(&panel).contains("321"));
+    set_display_settings(DisplaySettings::default());
+}
@@ -3,7 +3,7 @@
 This work is licensed under the terms of the MIT license.  
 For a copy, see <https://opensource.org/licenses/MIT>.*/
 
-use gdk4::RGBA;
+use crate::color::Color;
 use std::error::Error;
 use super::text;
 use std::collections::HashMap;
@@ -13,20 +13,26 @@ use crate::model::DesignError;
 
 #[derive(Clone, Debug)]
 pub struct PlotDesign {
-    pub bg_color : RGBA,
-    pub grid_color : RGBA,
+    pub bg_color : Color,
+    pub grid_color : Color,
     pub grid_width : i32,
-    pub font : text::FontData
+    pub font : text::FontData,
+    pub panel_bg_color : Color,
+    pub plot_bg_color : Option<Color>,
+    pub transparent : bool
 }
 
 impl Default for PlotDesign {
 
     fn default() -> Self {
         Self {
-            bg_color : RGBA::from_str("#ffffff").unwrap(),
-            grid_color : RGBA::from_str("#d3d7cf").unwrap(),
+            bg_color : Color::from_str("#ffffff").unwrap(),
+            grid_color : Color::from_str("#d3d7cf").unwrap(),
             grid_width : 1,
-            font : FontData::new_from_string("Monospace Regular 22")
+            font : FontData::new_from_string("Monospace Regular 22"),
+            panel_bg_color : Color::from_str("#ffffff").unwrap(),
+            plot_bg_color : None,
+            transparent : false
         }
     }
 
@@ -38,21 +44,49 @@ impl PlotDesign {
         rep.validate()?;
         let bg_color = rep.bgcolor.parse().or(Err(DesignError::InvalidBackgroundColor))?;
         let grid_color = rep.fgcolor.parse().or(Err(DesignError::InvalidGridColor))?;
+        let panel_bg_color = match &rep.panel_bgcolor {
+            Some(color) => color.parse().or(Err(DesignError::InvalidPanelBackgroundColor))?,
+            None => bg_color
+        };
+        let plot_bg_color = match &rep.plot_bgcolor {
+            Some(color) => Some(color.parse().or(Err(DesignError::InvalidPlotBackgroundColor))?),
+            None => None
+        };
         let design = Self {
             bg_color,
             grid_color,
             grid_width : rep.width,
-            font : text::FontData::new_from_string(&rep.font)
+            font : text::FontData::new_from_string(&rep.font),
+            panel_bg_color,
+            plot_bg_color,
+            transparent : rep.transparent.unwrap_or(false)
         };
         Ok(design)
     }
 
+    pub fn to_model(&self) -> crate::model::Design {
+        crate::model::Design {
+            bgcolor : self.bg_color.to_string(),
+            fgcolor : self.grid_color.to_string(),
+            width : self.grid_width,
+            font : self.font.description(),
+            panel_bgcolor : Some(self.panel_bg_color.to_string()),
+            plot_bgcolor : self.plot_bg_color.map(|c| c.to_string() ),
+            transparent : Some(self.transparent)
+        }
+    }
+
     pub fn description(&self) -> HashMap<String, String> {
         let mut desc = HashMap::new();
         desc.insert("bg_color".into(), self.bg_color.to_string());
         desc.insert("grid_color".into(), self.grid_color.to_string());
         desc.insert("grid_width".into(), self.grid_width.to_string());
         desc.insert("font".into(), self.font.description());
+        desc.insert("panel_bg_color".into(), self.panel_bg_color.to_string());
+        if let Some(color) = &self.plot_bg_color {
+            desc.insert("plot_bg_color".into(), color.to_string());
+        }
+        desc.insert("transparent".into(), self.transparent.to_string());
         desc
     }
 
@@ -0,0 +1,173 @@
+/*Copyright (c) 2022 Diego da Silva Lima. All rights reserved.
+
+This work is licensed under the terms of the MIT license.  
+For a copy, see <https://opensource.org/licenses/MIT>.*/
+
+use cairo::{Context, ImageSurface, Format};
+use std::error::Error;
+use super::{Panel, FileError};
+
+/// How the color channels of raw pixel buffers relate to the alpha channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AlphaMode {
+
+    /// Color channels are multiplied by alpha (the cairo native representation).
+    #[default]
+    Premultiplied,
+
+    /// Color channels are independent of alpha.
+    Straight
+}
+
+/// Byte order of each pixel of raw pixel buffers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ByteOrder {
+
+    #[default]
+    Rgba,
+
+    Bgra
+}
+
+/// Pixel format of raw pixel buffers. The scale factor multiplies the number of
+/// pixels at each dimension (e.g. 2.0 for HiDPI displays), while geometry and fonts
+/// are scaled accordingly, so the output looks the same at a higher resolution.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RgbaOptions {
+    pub alpha : AlphaMode,
+    pub order : ByteOrder,
+    pub scale : f64
+}
+
+impl Default for RgbaOptions {
+
+    fn default() -> Self {
+        Self { alpha : AlphaMode::Premultiplied, order : ByteOrder::Rgba, scale : 1.0 }
+    }
+
+}
+
+impl RgbaOptions {
+
+    pub fn alpha(mut self, alpha : AlphaMode) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    pub fn order(mut self, order : ByteOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    pub fn scale(mut self, scale : f64) -> Self {
+        self.scale = scale;
+        self
+    }
+
+}
+
+impl Panel {
+
+    /// Renders the panel with the informed drawing size to a raw buffer of premultiplied RGBA
+    /// pixels, without any encoding. Returns the buffer and its stride (number of bytes per row).
+    pub fn render_rgba(&mut self, width : u32, height : u32) -> Result<(Vec<u8>, usize), Box<dyn Error>> {
+        self.render_rgba_with(width, height, RgbaOptions::default())
+    }
+
+    /// Renders the panel to a raw pixel buffer with the informed pixel format. The buffer
+    /// has (scale*width) x (scale*height) pixels with 4 bytes each, and rows are not padded.
+    pub fn render_rgba_with(
+        &mut self,
+        width : u32,
+        height : u32,
+        opts : RgbaOptions
+    ) -> Result<(Vec<u8>, usize), Box<dyn Error>> {
+        if !opts.scale.is_finite() || opts.scale <= 0.0 {
+            Err(FileError(format!("Invalid scale factor: {}", opts.scale)))?;
+        }
+        let px_w = (width as f64 * opts.scale).round();
+        let px_h = (height as f64 * opts.scale).round();
+        if px_w < 1.0 || px_h < 1.0 || px_w > i32::MAX as f64 || px_h > i32::MAX as f64 {
+            Err(FileError(format!("Invalid pixel size: {} x {}", px_w, px_h)))?;
+        }
+        let (px_w, px_h) = (px_w as i32, px_h as i32);
+        let mut surf = ImageSurface::create(Format::ARgb32, px_w, px_h)
+            .map_err(|e| FileError(format!("Error creating image surface: {}", e) ))?;
+        {
+            let ctx = Context::new(&surf)?;
+            ctx.scale(opts.scale, opts.scale);
+            self.draw_to_context(&ctx, 0, 0, width as i32, height as i32)?;
+        }
+        surf.flush();
+        let src_stride = surf.stride() as usize;
+        let stride = px_w as usize * 4;
+        let data = surf.data()
+            .map_err(|e| FileError(format!("Unable to access image data: {}", e) ))?;
+        let mut buf = Vec::with_capacity(stride * px_h as usize);
+        for row in data.chunks(src_stride).take(px_h as usize) {
+            for px in row[..stride].chunks_exact(4) {
+                buf.extend(convert_pixel([px[0], px[1], px[2], px[3]], &opts));
+            }
+        }
+        Ok((buf, stride))
+    }
+
+}
+
+// Cairo ARGB32 pixels are premultiplied 32-bit words in native endianness,
+// with alpha at the most significant byte.
+fn convert_pixel(px : [u8; 4], opts : &RgbaOptions) -> [u8; 4] {
+    let word = u32::from_ne_bytes(px);
+    let a = (word >> 24) as u8;
+    let mut rgb = [(word >> 16) as u8, (word >> 8) as u8, word as u8];
+    if opts.alpha == AlphaMode::Straight && a > 0 && a < 255 {
+        for c in rgb.iter_mut() {
+            *c = ((*c as u32 * 255 + a as u32 / 2) / a as u32).min(255) as u8;
+        }
+    }
+    match opts.order {
+        ByteOrder::Rgba => [rgb[0], rgb[1], rgb[2], a],
+        ByteOrder::Bgra => [rgb[2], rgb[1], rgb[0], a]
+    }
+}
+
+#[cfg(feature="gtk")]
+impl Panel {
+
+    /// Renders the panel at its current dimensions to a pixbuf, for display in GTK widgets.
+    pub fn pixbuf(&mut self) -> Result<gdk_pixbuf::Pixbuf, Box<dyn Error>> {
+        let (w, h) = self.raster_dimensions();
+        let opts = RgbaOptions::default().alpha(AlphaMode::Straight).scale(self.scale_factor);
+        let (buf, stride) = self.render_rgba_with(self.dimensions.0 as u32, self.dimensions.1 as u32, opts)?;
+        Ok(gdk_pixbuf::Pixbuf::from_mut_slice(buf, gdk_pixbuf::Colorspace::Rgb, true, 8, w, h, stride as i32))
+    }
+
+}
+
+#[test]
+fn convert_pixels() {
+    let opts = RgbaOptions::default();
+    let straight = opts.alpha(AlphaMode::Straight);
+
+    // Half-transparent red, as a premultiplied cairo word.
+    let px = 0x8080_0000u32.to_ne_bytes();
+    assert_eq!(convert_pixel(px, &opts), [128, 0, 0, 128]);
+    assert_eq!(convert_pixel(px, &straight), [255, 0, 0, 128]);
+    assert_eq!(convert_pixel(px, &straight.order(ByteOrder::Bgra)), [0, 0, 255, 128]);
+
+    // Opaque and fully transparent pixels are not affected by the alpha mode.
+    let px = 0xFF10_2030u32.to_ne_bytes();
+    assert_eq!(convert_pixel(px, &straight), [0x10, 0x20, 0x30, 255]);
+    assert_eq!(convert_pixel(px, &opts.order(ByteOrder::Bgra)), [0x30, 0x20, 0x10, 255]);
+    assert_eq!(convert_pixel([0; 4], &straight), [0; 4]);
+}
+
+#[test]
+fn invalid_pixel_sizes() {
+    let mut panel = Panel::new_from_json(r#"{"kind":"line","map":{"x":[0,1],"y":[0,1]}}"#).unwrap();
+    assert!(panel.render_rgba(0, 10).is_err());
+    assert!(panel.render_rgba_with(10, 10, RgbaOptions::default().scale(0.01)).is_err());
+    assert!(panel.render_rgba_with(10, 10, RgbaOptions::default().scale(f64::INFINITY)).is_err());
+    let (buf, stride) = panel.render_rgba_with(10, 5, RgbaOptions::default().scale(2.0)).unwrap();
+    assert_eq!((buf.len(), stride), (20 * 10 * 4, 20 * 4));
+}
@@ -167,6 +167,23 @@ impl Scale {
         Ok(scale)
     }
 
+    /// Returns the scale definition. The extension is the current (possibly adjusted) one,
+    /// which is the extension the adjustment yields again for the same data.
+    pub fn to_model(&self) -> crate::model::Scale {
+        crate::model::Scale {
+            label : self.label.clone(),
+            from : self.from,
+            to : self.to,
+            precision : Some(self.precision),
+            intervals : Some(self.n_intervals),
+            log : Some(self.log),
+            invert : Some(self.invert),
+            offset : Some(self.offset),
+            adjust : Some(self.adj.name().to_string()),
+            guide : Some(self.guide)
+        }
+    }
+
     pub fn new_full(
         label : String,
         precision : i32,
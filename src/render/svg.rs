@@ -0,0 +1,452 @@
+/*Copyright (c) 2022 Diego da Silva Lima. All rights reserved.
+
+This work is licensed under the terms of the MIT license.  
+For a copy, see <https://opensource.org/licenses/MIT>.*/
+
+use crate::color::Color;
+use std::error::Error;
+use std::fmt::Write;
+use std::f64::consts::PI;
+use super::backend::{DrawingBackend, TextExtents};
+use super::text::{FontData, FontWeight, FontSlant};
+use crate::model::Unit;
+
+// Drawing state pushed by save() and popped by restore().
+#[derive(Debug, Clone)]
+struct SvgState {
+    color : Color,
+    line_width : f64,
+    dashes : Vec<f64>,
+
+    // Number of <g> elements (transforms and clips) opened since the state was pushed.
+    open_groups : usize
+}
+
+impl Default for SvgState {
+
+    // Same defaults as a new cairo context.
+    fn default() -> Self {
+        Self { color : Color::BLACK, line_width : 2.0, dashes : Vec::new(), open_groups : 0 }
+    }
+
+}
+
+/// A drawing backend that writes SVG markup to a string, without depending on
+/// cairo. Since fonts are not available to measure text, text extents are
+/// estimated from average glyph widths, and text is written as <text> elements
+/// that are laid out by the SVG viewer. Logical groups are written as <g> elements
+/// with the informed id and class attributes, so the output can be styled and
+/// scripted by web pages.
+#[derive(Debug, Clone)]
+pub struct SvgWriter {
+    width : f64,
+    height : f64,
+
+    // Layout unit. With physical units, width and height are in points.
+    unit : Unit,
+    body : String,
+    defs : String,
+    path : String,
+    has_current_point : bool,
+    state : SvgState,
+    stack : Vec<SvgState>,
+    n_clips : usize,
+    title : Option<String>,
+    description : Option<String>,
+    data_points : bool,
+
+    // Nesting depth of the output, used for indentation.
+    depth : usize
+}
+
+impl SvgWriter {
+
+    /// Creates a writer for a drawing of the informed size, in pixels or (for physical
+    /// layout units) points, which is written as the size of the root element.
+    pub fn new(width : f64, height : f64, unit : Unit) -> Self {
+        Self {
+            width,
+            height,
+            unit,
+            body : String::new(),
+            defs : String::new(),
+            path : String::new(),
+            has_current_point : false,
+            state : SvgState::default(),
+            stack : Vec::new(),
+            n_clips : 0,
+            title : None,
+            description : None,
+            data_points : false,
+            depth : 1
+        }
+    }
+
+    /// Sets the document <title>, which is the accessible name of the image.
+    pub fn title(mut self, title : &str) -> Self {
+        self.title = Some(title.to_string());
+        self
+    }
+
+    /// Sets the document <desc>, which is the accessible description of the image.
+    pub fn description(mut self, description : &str) -> Self {
+        self.description = Some(description.to_string());
+        self
+    }
+
+    /// Writes the data points informed by the mappings as invisible <circle> elements
+    /// with class "datum", carrying the data values as data-* attributes (e.g. data-x="1.5").
+    pub fn data_points(mut self, data_points : bool) -> Self {
+        self.data_points = data_points;
+        self
+    }
+
+    /// Closes any group left open and returns the SVG document.
+    pub fn finish(mut self) -> String {
+        while self.restore().is_ok() { }
+        self.close_groups();
+        let mut svg = String::new();
+        svg += "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n";
+        let mut labelled_by = Vec::new();
+        if self.title.is_some() {
+            labelled_by.push("title");
+        }
+        if self.description.is_some() {
+            labelled_by.push("desc");
+        }
+        let aria = if labelled_by.is_empty() {
+            String::new()
+        } else {
+            format!(" role=\"img\" aria-labelledby=\"{}\"", labelled_by.join(" "))
+        };
+        // Unitless lengths are read as pixels, so physical sizes carry the pt unit, as cairo writes them.
+        let unit = if self.unit.is_physical() { "pt" } else { "" };
+        let _ = writeln!(
+            svg,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}{unit}\" height=\"{h}{unit}\" viewBox=\"0 0 {w} {h}\"{aria}>",
+            w = num(self.width),
+            h = num(self.height),
+            unit = unit,
+            aria = aria
+        );
+        if let Some(title) = &self.title {
+            let _ = writeln!(svg, "  <title id=\"title\">{}</title>", escape(title));
+        }
+        if let Some(description) = &self.description {
+            let _ = writeln!(svg, "  <desc id=\"desc\">{}</desc>", escape(description));
+        }
+        if !self.defs.is_empty() {
+            svg += "  <defs>\n";
+            svg += &self.defs;
+            svg += "  </defs>\n";
+        }
+        svg += &self.body;
+        svg += "</svg>\n";
+        svg
+    }
+
+    fn write_line(&mut self, line : &str) {
+        for _ in 0..self.depth {
+            self.body += "  ";
+        }
+        self.body += line;
+        self.body += "\n";
+    }
+
+    fn open_group(&mut self, attrs : &str) {
+        self.write_line(&format!("<g {}>", attrs));
+        self.depth += 1;
+        self.state.open_groups += 1;
+    }
+
+    fn close_groups(&mut self) {
+        for _ in 0..self.state.open_groups {
+            self.depth -= 1;
+            self.write_line("</g>");
+        }
+        self.state.open_groups = 0;
+    }
+
+    fn fill_attrs(&self) -> String {
+        let mut attrs = format!("fill=\"{}\"", rgb(&self.state.color));
+        if self.state.color.alpha() < 1.0 {
+            let _ = write!(attrs, " fill-opacity=\"{}\"", num(self.state.color.alpha() as f64));
+        }
+        attrs
+    }
+
+    fn stroke_attrs(&self) -> String {
+        let mut attrs = format!(
+            "fill=\"none\" stroke=\"{}\" stroke-width=\"{}\"",
+            rgb(&self.state.color),
+            num(self.state.line_width)
+        );
+        if self.state.color.alpha() < 1.0 {
+            let _ = write!(attrs, " stroke-opacity=\"{}\"", num(self.state.color.alpha() as f64));
+        }
+        if !self.state.dashes.is_empty() {
+            let dashes : Vec<String> = self.state.dashes.iter().map(|d| num(*d) ).collect();
+            let _ = write!(attrs, " stroke-dasharray=\"{}\"", dashes.join(","));
+        }
+        attrs
+    }
+
+    fn write_path(&mut self, attrs : &str) {
+        if !self.path.is_empty() {
+            let line = format!("<path d=\"{}\" {}/>", self.path.trim_end(), attrs);
+            self.write_line(&line);
+        }
+    }
+
+    fn clear_path(&mut self) {
+        self.path.clear();
+        self.has_current_point = false;
+    }
+
+}
+
+impl DrawingBackend for SvgWriter {
+
+    fn save(&mut self) -> Result<(), Box<dyn Error>> {
+        let mut state = self.state.clone();
+        state.open_groups = 0;
+        self.stack.push(std::mem::replace(&mut self.state, state));
+        Ok(())
+    }
+
+    fn restore(&mut self) -> Result<(), Box<dyn Error>> {
+        let prev = self.stack.pop().ok_or("SVG restore without matching save")?;
+        self.close_groups();
+        self.state = prev;
+        Ok(())
+    }
+
+    fn translate(&mut self, x : f64, y : f64) {
+        self.open_group(&format!("transform=\"translate({},{})\"", num(x), num(y)));
+    }
+
+    fn rotate(&mut self, angle : f64) {
+        self.open_group(&format!("transform=\"rotate({})\"", num(angle.to_degrees())));
+    }
+
+    fn scale(&mut self, sx : f64, sy : f64) {
+        self.open_group(&format!("transform=\"scale({},{})\"", num(sx), num(sy)));
+    }
+
+    fn set_color(&mut self, color : &Color) {
+        self.state.color = *color;
+    }
+
+    fn set_line_width(&mut self, width : f64) {
+        self.state.line_width = width;
+    }
+
+    fn set_dash(&mut self, dashes : &[f64]) {
+        self.state.dashes = dashes.to_vec();
+    }
+
+    fn move_to(&mut self, x : f64, y : f64) {
+        let _ = write!(self.path, "M{} {} ", num(x), num(y));
+        self.has_current_point = true;
+    }
+
+    fn line_to(&mut self, x : f64, y : f64) {
+        if self.has_current_point {
+            let _ = write!(self.path, "L{} {} ", num(x), num(y));
+        } else {
+            self.move_to(x, y);
+        }
+    }
+
+    fn arc(&mut self, xc : f64, yc : f64, radius : f64, angle1 : f64, mut angle2 : f64) {
+        while angle2 < angle1 {
+            angle2 += 2.0*PI;
+        }
+        let point = |a : f64| (xc + radius*a.cos(), yc + radius*a.sin());
+        let (x0, y0) = point(angle1);
+        self.line_to(x0, y0);
+
+        // SVG arcs cannot represent a full circle, so the arc is split in
+        // segments of at most half a turn.
+        let n_segments = ((angle2 - angle1) / PI).ceil().max(1.0) as usize;
+        for i in 1..=n_segments {
+            let (x, y) = point(angle1 + (angle2 - angle1) * i as f64 / n_segments as f64);
+            let _ = write!(self.path, "A{r} {r} 0 0 1 {} {} ", num(x), num(y), r = num(radius));
+        }
+    }
+
+    fn rectangle(&mut self, x : f64, y : f64, w : f64, h : f64) {
+        let _ = write!(self.path, "M{} {} h{} v{} h{} Z ", num(x), num(y), num(w), num(h), num(-w));
+        self.has_current_point = true;
+    }
+
+    fn close_path(&mut self) {
+        self.path += "Z ";
+    }
+
+    fn new_sub_path(&mut self) {
+        self.has_current_point = false;
+    }
+
+    fn fill(&mut self) -> Result<(), Box<dyn Error>> {
+        self.fill_preserve()?;
+        self.clear_path();
+        Ok(())
+    }
+
+    fn fill_preserve(&mut self) -> Result<(), Box<dyn Error>> {
+        let attrs = self.fill_attrs();
+        self.write_path(&attrs);
+        Ok(())
+    }
+
+    fn stroke(&mut self) -> Result<(), Box<dyn Error>> {
+        let attrs = self.stroke_attrs();
+        self.write_path(&attrs);
+        self.clear_path();
+        Ok(())
+    }
+
+    fn clip(&mut self) {
+        let id = format!("clip{}", self.n_clips);
+        self.n_clips += 1;
+        let _ = writeln!(self.defs, "    <clipPath id=\"{}\"><path d=\"{}\"/></clipPath>", id, self.path.trim_end());
+        self.clear_path();
+        self.open_group(&format!("clip-path=\"url(#{})\"", id));
+    }
+
+    fn text_extents(&mut self, font : &FontData, text : &str) -> TextExtents {
+        estimate_text_extents(font, text)
+    }
+
+    fn show_text(&mut self, font : &FontData, x : f64, y : f64, text : &str) -> Result<(), Box<dyn Error>> {
+        if text.is_empty() {
+            return Ok(());
+        }
+        let mut attrs = format!(
+            "x=\"{}\" y=\"{}\" font-family=\"{}\" font-size=\"{}\"",
+            num(x),
+            num(y),
+            escape(&font.font_family),
+            font.font_size
+        );
+        if font.font_weight == FontWeight::Bold {
+            attrs += " font-weight=\"bold\"";
+        }
+        match font.font_slant {
+            FontSlant::Italic => attrs += " font-style=\"italic\"",
+            FontSlant::Oblique => attrs += " font-style=\"oblique\"",
+            FontSlant::Normal => { }
+        }
+        let fill = self.fill_attrs();
+        self.write_line(&format!("<text {} {}>{}</text>", attrs, fill, escape(text)));
+        Ok(())
+    }
+
+    fn begin_group(&mut self, id : &str, class : &str, title : Option<&str>) -> Result<(), Box<dyn Error>> {
+        self.save()?;
+        self.open_group(&format!("id=\"{}\" class=\"{}\"", escape(id), escape(class)));
+        if let Some(title) = title {
+            self.write_line(&format!("<title>{}</title>", escape(title)));
+        }
+        Ok(())
+    }
+
+    fn end_group(&mut self) -> Result<(), Box<dyn Error>> {
+        self.restore()
+    }
+
+    fn data_point(&mut self, x : f64, y : f64, values : &[(&str, f64)]) {
+        if !self.data_points {
+            return;
+        }
+        let mut line = format!("<circle class=\"datum\" cx=\"{}\" cy=\"{}\" r=\"6\" fill-opacity=\"0\"", num(x), num(y));
+        for (name, value) in values {
+            let name : String = name.chars().filter(|c| c.is_ascii_alphanumeric() || *c == '-' ).collect();
+            let _ = write!(line, " data-{}=\"{}\"", name.to_lowercase(), value);
+        }
+        line += "/>";
+        self.write_line(&line);
+    }
+
+}
+
+/// Estimates text extents from average glyph widths (as a proportion of the font size)
+/// of common sans-serif and monospace fonts.
+pub fn estimate_text_extents(font : &FontData, text : &str) -> TextExtents {
+    let family = font.font_family.to_lowercase();
+    let monospace = family.contains("mono") || family.contains("courier");
+    let size = font.font_size as f64;
+    let x_advance : f64 = text.chars().map(|c| {
+        if monospace {
+            0.6
+        } else {
+            match c {
+                ' ' | '.' | ',' | ':' | ';' | '\'' | '!' | '|' | 'i' | 'j' | 'l' | 'I' => 0.28,
+                'f' | 't' | 'r' | '-' | '(' | ')' | '[' | ']' => 0.35,
+                'm' | 'w' | 'M' | 'W' => 0.85,
+                '0'..='9' | 'a'..='z' => 0.55,
+                'A'..='Z' => 0.68,
+                _ => 0.6
+            }
+        }
+    }).sum::<f64>() * size;
+    let weight = if font.font_weight == FontWeight::Bold { 1.05 } else { 1.0 };
+
+    // Ink height of capital letters and digits, plus descenders if present.
+    let descends = text.chars().any(|c| matches!(c, 'g' | 'j' | 'p' | 'q' | 'y' | ',' | '(' | ')') );
+    let height = if text.is_empty() { 0.0 } else if descends { 0.93*size } else { 0.72*size };
+    TextExtents { x_advance : x_advance * weight, height }
+}
+
+fn rgb(color : &Color) -> String {
+    let [r, g, b, _] = color.to_rgba8();
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+// Writes numbers with at most three decimal places, without trailing zeros.
+fn num(v : f64) -> String {
+    let s = format!("{:.3}", v);
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    if s == "-0" {
+        String::from("0")
+    } else {
+        s.to_string()
+    }
+}
+
+pub(crate) fn escape(text : &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out += "&amp;",
+            '<' => out += "&lt;",
+            '>' => out += "&gt;",
+            '"' => out += "&quot;",
+            '\'' => out += "&apos;",
+            _ => out.push(c)
+        }
+    }
+    out
+}
+
+#[test]
+fn write_groups() {
+    let mut writer = SvgWriter::new(100.0, 50.0, Unit::Px).title("A & B").description("Test");
+    writer.begin_group("plot-0", "plot", Some("<plot>")).unwrap();
+    writer.translate(10.0, 5.0);
+    writer.rectangle(0.0, 0.0, 10.0, 10.0);
+    writer.clip();
+    writer.move_to(0.0, 0.0);
+    writer.line_to(5.0, 5.0);
+    writer.stroke().unwrap();
+    writer.end_group().unwrap();
+    let svg = writer.finish();
+    assert!(svg.contains("<title id=\"title\">A &amp; B</title>"));
+    assert!(svg.contains("<g id=\"plot-0\" class=\"plot\">"));
+    assert!(svg.contains("<title>&lt;plot&gt;</title>"));
+    assert!(svg.contains("<clipPath id=\"clip0\"><path d=\"M0 0 h10 v10 h-10 Z\"/></clipPath>"));
+    assert!(svg.contains("<path d=\"M0 0 L5 5\" fill=\"none\" stroke=\"#000000\" stroke-width=\"2\"/>"));
+    assert_eq!(svg.matches("<g ").count(), 3);
+    assert_eq!(svg.matches("</g>").count(), 3);
+}
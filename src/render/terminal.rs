@@ -0,0 +1,507 @@
+/*Copyright (c) 2022 Diego da Silva Lima. All rights reserved.
+
+This work is licensed under the terms of the MIT license.  
+For a copy, see <https://opensource.org/licenses/MIT>.*/
+
+use crate::color::Color;
+use std::error::Error;
+use std::f64::consts::PI;
+use super::{Panel, FileError};
+use super::backend::{DrawingBackend, TextExtents};
+use super::text::FontData;
+
+// Each character cell holds a 2x4 grid of braille dots.
+const DOTS_X : usize = 2;
+
+const DOTS_Y : usize = 4;
+
+// Bit of each dot at the braille character, indexed by [column][row].
+const BRAILLE_BITS : [[u8; 4]; 2] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+
+// Elements drawn to the canvas are assigned to layers, according to the group they belong to.
+// Backgrounds are hidden (the terminal background is used instead), grid lines are
+// only shown at cells without data, and labels are written over everything.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Layer {
+    Hidden,
+    Grid,
+    Data
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Cell {
+    data : u8,
+    data_color : Option<Color>,
+    grid : u8,
+    text : Option<char>
+}
+
+#[derive(Debug, Clone)]
+struct TerminalState {
+
+    // Affine transformation (xx, yx, xy, yy, x0, y0) from user to panel coordinates.
+    matrix : [f64; 6],
+    color : Color,
+
+    // Clip rectangle (left, top, right, bottom) in panel coordinates.
+    clip : Option<(f64, f64, f64, f64)>,
+    layer : Layer
+}
+
+/// A drawing backend that rasterizes to a canvas of Unicode braille characters (2x4 dots
+/// per character cell), which is printed with ANSI colors. Lines are one dot wide, text is
+/// written with one character per cell, and the areas of the panel and plot backgrounds
+/// are left blank.
+#[derive(Debug, Clone)]
+pub struct TerminalCanvas {
+    cols : usize,
+    rows : usize,
+    width : f64,
+    height : f64,
+    colors : bool,
+    cells : Vec<Cell>,
+    state : TerminalState,
+    stack : Vec<TerminalState>,
+
+    // Sub-paths of the current path, in panel coordinates.
+    path : Vec<Vec<(f64, f64)>>,
+    current : Option<(f64, f64)>
+}
+
+impl TerminalCanvas {
+
+    /// Creates a canvas with the informed number of character columns and rows, which
+    /// is drawn to as a surface of width x height units.
+    pub fn new(cols : usize, rows : usize, width : f64, height : f64) -> Self {
+        Self {
+            cols,
+            rows,
+            width,
+            height,
+            colors : true,
+            cells : vec![Cell::default(); cols * rows],
+            state : TerminalState {
+                matrix : [1.0, 0.0, 0.0, 1.0, 0.0, 0.0],
+                color : Color::BLACK,
+                clip : None,
+                layer : Layer::Data
+            },
+            stack : Vec::new(),
+            path : Vec::new(),
+            current : None
+        }
+    }
+
+    /// Whether mappings are printed with ANSI colors (true by default). Grid lines are
+    /// always printed dimmed, and labels with the default terminal color.
+    pub fn colors(mut self, colors : bool) -> Self {
+        self.colors = colors;
+        self
+    }
+
+    /// Returns the canvas as lines of text, separated by newlines.
+    pub fn finish(self) -> String {
+        let mut out = String::new();
+        for row in self.cells.chunks(self.cols.max(1)).take(self.rows) {
+            let mut active : Option<String> = None;
+            for cell in row {
+                let (ch, style) = if let Some(ch) = cell.text {
+                    (ch, None)
+                } else if cell.data != 0 {
+                    let style = cell.data_color.map(|c| {
+                        let [r, g, b, _] = c.to_rgba8();
+                        format!("\x1b[38;2;{};{};{}m", r, g, b)
+                    });
+                    (braille(cell.data), style)
+                } else if cell.grid != 0 {
+                    (braille(cell.grid), Some(String::from("\x1b[2m")))
+                } else {
+                    (' ', None)
+                };
+                let style = style.filter(|_| self.colors );
+                if style != active {
+                    if active.is_some() {
+                        out += "\x1b[0m";
+                    }
+                    if let Some(s) = &style {
+                        out += s;
+                    }
+                    active = style;
+                }
+                out.push(ch);
+            }
+            if active.is_some() {
+                out += "\x1b[0m";
+            }
+            out += "\n";
+        }
+        out
+    }
+
+    fn transform(&self, x : f64, y : f64) -> (f64, f64) {
+        let m = &self.state.matrix;
+        (m[0]*x + m[2]*y + m[4], m[1]*x + m[3]*y + m[5])
+    }
+
+    // Size of a character cell in panel coordinates.
+    fn cell_size(&self) -> (f64, f64) {
+        (self.width / self.cols as f64, self.height / self.rows as f64)
+    }
+
+    fn to_dots(&self, pt : (f64, f64)) -> (f64, f64) {
+        (
+            pt.0 * (self.cols * DOTS_X) as f64 / self.width,
+            pt.1 * (self.rows * DOTS_Y) as f64 / self.height
+        )
+    }
+
+    fn visible(&self, dx : i64, dy : i64) -> bool {
+        if dx < 0 || dy < 0 || dx as usize >= self.cols * DOTS_X || dy as usize >= self.rows * DOTS_Y {
+            return false;
+        }
+        match self.state.clip {
+            Some((left, top, right, bottom)) => {
+                let (l, t) = self.to_dots((left, top));
+                let (r, b) = self.to_dots((right, bottom));
+                let (cx, cy) = (dx as f64 + 0.5, dy as f64 + 0.5);
+                cx >= l.floor() && cx <= r.ceil() && cy >= t.floor() && cy <= b.ceil()
+            },
+            None => true
+        }
+    }
+
+    fn set_dot(&mut self, dx : i64, dy : i64) {
+        if !self.visible(dx, dy) {
+            return;
+        }
+        let (dx, dy) = (dx as usize, dy as usize);
+        let bit = BRAILLE_BITS[dx % DOTS_X][dy % DOTS_Y];
+        let color = self.state.color;
+        let cell = &mut self.cells[(dy / DOTS_Y) * self.cols + dx / DOTS_X];
+        match self.state.layer {
+            Layer::Data => {
+                cell.data |= bit;
+                cell.data_color = Some(color);
+            },
+            Layer::Grid => cell.grid |= bit,
+            Layer::Hidden => { }
+        }
+    }
+
+    // Ink with the color of the plot background (e.g. white outlines separating wedges)
+    // is not drawn, since the terminal background is used instead.
+    fn paints(&self) -> bool {
+        let c = &self.state.color;
+        self.state.layer != Layer::Hidden && c.alpha() > 0.0 && (c.red().min(c.green()).min(c.blue()) < 0.97)
+    }
+
+    fn draw_segment(&mut self, from : (f64, f64), to : (f64, f64)) {
+        let (x0, y0) = self.to_dots(from);
+        let (x1, y1) = self.to_dots(to);
+        let n = (x1 - x0).abs().max((y1 - y0).abs()).ceil().max(1.0) as usize;
+
+        // Segments far outside the canvas (e.g. from points mapped to extreme values) are not drawn.
+        if n > 100_000 {
+            return;
+        }
+        for i in 0..=n {
+            let t = i as f64 / n as f64;
+            self.set_dot((x0 + (x1 - x0)*t).floor() as i64, (y0 + (y1 - y0)*t).floor() as i64);
+        }
+    }
+
+    // Fills the sub-paths (taken as closed polygons) with the even-odd rule, sampling
+    // the center of each dot.
+    fn fill_path(&mut self) {
+        let polys : Vec<Vec<(f64, f64)>> = self.path.iter()
+            .filter(|p| p.len() > 1 )
+            .map(|p| p.iter().map(|pt| self.to_dots(*pt) ).collect() )
+            .collect();
+        let (mut ymin, mut ymax) = (f64::INFINITY, f64::NEG_INFINITY);
+        let (mut xmin, mut xmax) = (f64::INFINITY, f64::NEG_INFINITY);
+        for (x, y) in polys.iter().flatten() {
+            xmin = xmin.min(*x);
+            xmax = xmax.max(*x);
+            ymin = ymin.min(*y);
+            ymax = ymax.max(*y);
+        }
+        if !ymin.is_finite() || !ymax.is_finite() {
+            return;
+        }
+        let max_dx = (self.cols * DOTS_X) as f64;
+        let max_dy = (self.rows * DOTS_Y) as f64;
+        let mut filled = false;
+        let mut dy = ymin.floor().max(0.0);
+        while dy <= ymax.min(max_dy) {
+            let cy = dy + 0.5;
+            let mut xs = Vec::new();
+            for poly in polys.iter() {
+                for (i, a) in poly.iter().enumerate() {
+                    let b = poly[(i + 1) % poly.len()];
+                    if (a.1 <= cy && b.1 > cy) || (b.1 <= cy && a.1 > cy) {
+                        xs.push(a.0 + (cy - a.1) / (b.1 - a.1) * (b.0 - a.0));
+                    }
+                }
+            }
+            xs.sort_by(|a, b| a.total_cmp(b) );
+
+            // Spans are limited to the canvas, since polygons might extend far beyond it.
+            for pair in xs.chunks_exact(2) {
+                let mut dx = (pair[0] - 0.5).ceil().max(0.0);
+                let end = pair[1].min(max_dx);
+                while dx + 0.5 <= end {
+                    self.set_dot(dx as i64, dy as i64);
+                    filled = true;
+                    dx += 1.0;
+                }
+            }
+            dy += 1.0;
+        }
+
+        // Shapes smaller than a dot (e.g. scatter points) are still shown as a single dot.
+        if !filled {
+            self.set_dot(((xmin + xmax) / 2.0).floor() as i64, ((ymin + ymax) / 2.0).floor() as i64);
+        }
+    }
+
+    fn clear_path(&mut self) {
+        self.path.clear();
+        self.current = None;
+    }
+
+    fn write_char(&mut self, col : i64, row : i64, ch : char) {
+        if col >= 0 && row >= 0 && (col as usize) < self.cols && (row as usize) < self.rows {
+            self.cells[row as usize * self.cols + col as usize].text = Some(ch);
+        }
+    }
+
+}
+
+fn braille(bits : u8) -> char {
+    char::from_u32(0x2800 + bits as u32).unwrap_or(' ')
+}
+
+impl DrawingBackend for TerminalCanvas {
+
+    fn save(&mut self) -> Result<(), Box<dyn Error>> {
+        self.stack.push(self.state.clone());
+        Ok(())
+    }
+
+    fn restore(&mut self) -> Result<(), Box<dyn Error>> {
+        self.state = self.stack.pop().ok_or("Terminal restore without matching save")?;
+        Ok(())
+    }
+
+    fn translate(&mut self, x : f64, y : f64) {
+        let m = &mut self.state.matrix;
+        m[4] += m[0]*x + m[2]*y;
+        m[5] += m[1]*x + m[3]*y;
+    }
+
+    fn rotate(&mut self, angle : f64) {
+        let (sin, cos) = angle.sin_cos();
+        let [xx, yx, xy, yy, x0, y0] = self.state.matrix;
+        self.state.matrix = [xx*cos + xy*sin, yx*cos + yy*sin, xy*cos - xx*sin, yy*cos - yx*sin, x0, y0];
+    }
+
+    fn scale(&mut self, sx : f64, sy : f64) {
+        let m = &mut self.state.matrix;
+        m[0] *= sx;
+        m[1] *= sx;
+        m[2] *= sy;
+        m[3] *= sy;
+    }
+
+    fn set_color(&mut self, color : &Color) {
+        self.state.color = *color;
+    }
+
+    fn set_line_width(&mut self, _width : f64) { }
+
+    fn set_dash(&mut self, _dashes : &[f64]) { }
+
+    fn move_to(&mut self, x : f64, y : f64) {
+        let pt = self.transform(x, y);
+        self.path.push(vec![pt]);
+        self.current = Some(pt);
+    }
+
+    fn line_to(&mut self, x : f64, y : f64) {
+        let pt = self.transform(x, y);
+        match (self.current, self.path.last_mut()) {
+            (Some(_), Some(sub)) => sub.push(pt),
+            _ => self.path.push(vec![pt])
+        }
+        self.current = Some(pt);
+    }
+
+    fn arc(&mut self, xc : f64, yc : f64, radius : f64, angle1 : f64, mut angle2 : f64) {
+        while angle2 < angle1 {
+            angle2 += 2.0*PI;
+        }
+        let n = ((angle2 - angle1) / (PI / 16.0)).ceil().max(1.0) as usize;
+        for i in 0..=n {
+            let a = angle1 + (angle2 - angle1) * i as f64 / n as f64;
+            self.line_to(xc + radius*a.cos(), yc + radius*a.sin());
+        }
+    }
+
+    fn rectangle(&mut self, x : f64, y : f64, w : f64, h : f64) {
+        self.move_to(x, y);
+        self.line_to(x + w, y);
+        self.line_to(x + w, y + h);
+        self.line_to(x, y + h);
+        self.close_path();
+    }
+
+    fn close_path(&mut self) {
+        if let Some(first) = self.path.last().and_then(|sub| sub.first().cloned() ) {
+            if let Some(sub) = self.path.last_mut() {
+                sub.push(first);
+            }
+            self.path.push(vec![first]);
+            self.current = Some(first);
+        }
+    }
+
+    fn new_sub_path(&mut self) {
+        self.current = None;
+    }
+
+    fn fill(&mut self) -> Result<(), Box<dyn Error>> {
+        self.fill_preserve()?;
+        self.clear_path();
+        Ok(())
+    }
+
+    fn fill_preserve(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.paints() {
+            self.fill_path();
+        }
+        Ok(())
+    }
+
+    fn stroke(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.paints() {
+            let path = std::mem::take(&mut self.path);
+            for sub in path.iter() {
+                for seg in sub.windows(2) {
+                    self.draw_segment(seg[0], seg[1]);
+                }
+            }
+        }
+        self.clear_path();
+        Ok(())
+    }
+
+    fn clip(&mut self) {
+        let pts = self.path.iter().flatten();
+        let (mut l, mut t, mut r, mut b) = (f64::INFINITY, f64::INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY);
+        for (x, y) in pts {
+            l = l.min(*x);
+            t = t.min(*y);
+            r = r.max(*x);
+            b = b.max(*y);
+        }
+        let clip = match self.state.clip {
+            Some((cl, ct, cr, cb)) => (l.max(cl), t.max(ct), r.min(cr), b.min(cb)),
+            None => (l, t, r, b)
+        };
+        self.state.clip = Some(clip);
+        self.clear_path();
+    }
+
+    fn text_extents(&mut self, _font : &FontData, text : &str) -> TextExtents {
+        let (cell_w, cell_h) = self.cell_size();
+        TextExtents { x_advance : text.chars().count() as f64 * cell_w, height : 0.7 * cell_h }
+    }
+
+    fn show_text(&mut self, font : &FontData, x : f64, y : f64, text : &str) -> Result<(), Box<dyn Error>> {
+        if self.state.layer == Layer::Hidden || text.is_empty() {
+            return Ok(());
+        }
+        let ext = self.text_extents(font, text);
+
+        // Text is positioned by the center of its extents, then laid out horizontally,
+        // or from top to bottom if rotated.
+        let center = self.transform(x + ext.x_advance / 2.0, y - ext.height / 2.0);
+        let advance = self.transform(1.0, 0.0);
+        let origin = self.transform(0.0, 0.0);
+        let vertical = (advance.1 - origin.1).abs() > (advance.0 - origin.0).abs();
+        let (cell_w, cell_h) = self.cell_size();
+        let n = text.chars().count() as i64;
+        let col = (center.0 / cell_w).floor() as i64;
+        let row = (center.1 / cell_h).floor() as i64;
+        for (i, ch) in text.chars().enumerate() {
+            if vertical {
+                self.write_char(col, row - n / 2 + i as i64, ch);
+            } else {
+                self.write_char(col - n / 2 + i as i64, row, ch);
+            }
+        }
+        Ok(())
+    }
+
+    fn begin_group(&mut self, _id : &str, class : &str, _title : Option<&str>) -> Result<(), Box<dyn Error>> {
+        self.save()?;
+        let classes : Vec<&str> = class.split_whitespace().collect();
+        if classes.contains(&"background") {
+            self.state.layer = Layer::Hidden;
+        } else if classes.contains(&"grid") {
+            self.state.layer = Layer::Grid;
+        }
+        Ok(())
+    }
+
+}
+
+impl Panel {
+
+    /// Renders the panel as text with the informed number of character columns and rows,
+    /// using Unicode braille characters and ANSI colors, for display at terminals.
+    pub fn to_terminal(&mut self, cols : usize, rows : usize) -> Result<String, Box<dyn Error>> {
+        if cols == 0 || rows == 0 {
+            Err(FileError(format!("Invalid terminal size: {}x{}", cols, rows)))?;
+        }
+        let (w, h) = (self.dimensions.0 as f64, self.dimensions.1 as f64);
+        let mut canvas = TerminalCanvas::new(cols, rows, w, h);
+        self.draw_to_backend(&mut canvas, 0, 0, w as i32, h as i32)?;
+        Ok(canvas.finish())
+    }
+
+}
+
+impl crate::model::Panel {
+
+    /// Renders the panel definition as text for display at terminals (see render::Panel::to_terminal).
+    pub fn to_terminal(&self, cols : usize, rows : usize) -> Result<String, Box<dyn Error>> {
+        let mut panel = Panel::new_from_model(self.clone())?;
+        panel.to_terminal(cols, rows)
+    }
+
+}
+
+#[test]
+fn draw_braille_canvas() {
+    let mut canvas = TerminalCanvas::new(4, 2, 40.0, 20.0).colors(false);
+    canvas.move_to(0.0, 0.5);
+    canvas.line_to(40.0, 0.5);
+    canvas.stroke().unwrap();
+    canvas.rectangle(20.0, 10.0, 20.0, 10.0);
+    canvas.fill().unwrap();
+    canvas.show_text(&FontData::default(), 0.0, 19.0, "ab").unwrap();
+    assert_eq!(canvas.finish(), "⠉⠉⠉⠉\nab⣿⣿\n");
+}
+
+#[test]
+fn fill_beyond_canvas() {
+    let mut canvas = TerminalCanvas::new(4, 2, 40.0, 20.0).colors(false);
+    canvas.move_to(-1.0e12, 10.0);
+    canvas.line_to(1.0e12, 10.0);
+    canvas.line_to(1.0e12, 1.0e12);
+    canvas.line_to(-1.0e12, 1.0e12);
+    canvas.close_path();
+    canvas.fill().unwrap();
+    assert_eq!(canvas.finish(), "    \n⣿⣿⣿⣿\n");
+}
@@ -3,21 +3,32 @@
 This work is licensed under the terms of the MIT license.  
 For a copy, see <https://opensource.org/licenses/MIT>.*/
 
-use cairo::Context;
-use cairo::{ScaledFont, FontWeight, FontSlant};
 use super::context_mapper::*;
+use super::backend::DrawingBackend;
 use std::f64::consts::PI;
 use regex::Regex;
 use std::default::Default;
 use std::error::Error;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FontWeight {
+    Normal,
+    Bold
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FontSlant {
+    Normal,
+    Italic,
+    Oblique
+}
+
 #[derive(Debug, Clone)]
 pub struct FontData {
     pub font_family : String,
     pub font_weight : FontWeight,
     pub font_slant : FontSlant,
-    pub font_size : i32,
-    pub sf : ScaledFont
+    pub font_size : i32
 }
 
 impl Default for FontData {
@@ -27,13 +38,11 @@ impl Default for FontData {
         let font_weight = FontWeight::Normal;
         let font_slant = FontSlant::Normal;
         let font_size = 12;
-        let sf = create_scaled_font(&font_family[..], font_slant, font_weight, font_size);
         Self {
             font_family,
             font_weight,
             font_slant,
-            font_size,
-            sf
+            font_size
         }
     }
 
@@ -46,13 +55,7 @@ impl FontData {
         let font_weight = FontWeight::Normal;
         let font_slant = FontSlant::Normal;
         let font_size = 12;
-        let sf = create_scaled_font(
-            &font_family[..],
-            font_slant,
-            font_weight,
-            font_size
-        );
-        Self{ font_family, font_weight, font_slant, font_size, sf }
+        Self{ font_family, font_weight, font_slant, font_size }
     }
 
     pub fn new_from_string(font : &str) -> Self {
@@ -86,65 +89,40 @@ impl FontData {
             prefix = &font[0..weight.start()];
         };
         let font_family = String::from(prefix.trim());
-        let sf = create_scaled_font(
-            &font_family[..],
-            font_slant,
-            font_weight,
-            font_size
-        );
         Self {
             font_family,
             font_weight,
             font_slant,
-            font_size,
-            sf
+            font_size
+        }
+    }
+
+    /// Returns a font of the same family and slant, with the informed size and weight.
+    pub fn resized(&self, font_size : i32, font_weight : FontWeight) -> Self {
+        Self {
+            font_family : self.font_family.clone(),
+            font_weight,
+            font_slant : self.font_slant,
+            font_size
         }
     }
 
+    /// Returns the font as a string that can be parsed back by new_from_string
+    /// (e.g. "Sans Bold Italic 12").
     pub fn description(&self) -> String {
         let mut font = self.font_family.to_string();
-        font = font + match self.font_slant {
+        font += match self.font_weight {
+            FontWeight::Normal => "",
+            FontWeight::Bold => " Bold"
+        };
+        font += match self.font_slant {
             FontSlant::Normal => "",
             FontSlant::Oblique => " Oblique",
-            FontSlant::Italic => " Italic",
-            _ => ""
+            FontSlant::Italic => " Italic"
         };
-        font = font + match self.font_weight {
-            FontWeight::Normal => "",
-            FontWeight::Bold => " Bold",
-            _ => ""
-        };
-        font = font + &self.font_size.to_string()[..];
+        font = font + " " + &self.font_size.to_string()[..];
         font
     }
-
-    pub fn set_font_into_context(&self, ctx : &Context) {
-        ctx.set_font_size(self.font_size as f64);
-        ctx.select_font_face(
-            &self.font_family,
-            self.font_slant,
-            self.font_weight
-        );
-    }
-}
-
-fn create_scaled_font(
-    family : &str,
-    slant : FontSlant,
-    weight : FontWeight,
-    size : i32
-) -> ScaledFont {
-    let mut font_m =  cairo::Matrix::identity();
-    let ctm = cairo::Matrix::identity();
-    font_m.scale(size as f64, size as f64);
-    let opts = cairo::FontOptions::new().unwrap();
-    // context.get_font_face()
-    let font_face = cairo::FontFace::toy_create(
-        family,
-        slant,
-        weight
-    ).unwrap();
-    ScaledFont::new(&font_face, &font_m, &ctm, &opts).unwrap()
 }
 
 /// Draw a text with horizontal and vertical extents centered
@@ -153,8 +131,8 @@ fn create_scaled_font(
 /// extent that shuold be used to re-position the text
 /// relative to the given coordinate.
 pub fn draw_label(
-    sf : &ScaledFont,
-    ctx : &Context,
+    ctx : &mut dyn DrawingBackend,
+    font : &FontData,
     label : &str,
     mut pos : Coord2D,
     rotate : bool,
@@ -163,9 +141,9 @@ pub fn draw_label(
     off_y : Option<f64>
 ) -> Result<(), Box<dyn Error>> {
     ctx.save()?;
-    let ext = sf.text_extents(label);
-    let xadv = ext.x_advance();
-    let height = ext.height();
+    let ext = ctx.text_extents(font, label);
+    let xadv = ext.x_advance;
+    let height = ext.height;
     let half_xadv = xadv / 2.0;
     let half_height = height / 2.0;
     let x_center_off = match center.0 {
@@ -180,7 +158,6 @@ pub fn draw_label(
     let ext_off_y = off_y.unwrap_or(0.0)*height + y_center_off;
     pos.x += ext_off_x;
     pos.y += ext_off_y;
-    let (glyphs, _) = sf.text_to_glyphs(pos.x, pos.y, label).unwrap();
     let radius = (pos.x.powf(2.0) + pos.y.powf(2.0)).sqrt();
     if rotate {
         // ctx.translate(-radius + height, radius);
@@ -189,7 +166,7 @@ pub fn draw_label(
         ctx.translate(-radius + 2. * height, radius);
         ctx.rotate(-PI/2.0);
     }
-    if let Err(e) = ctx.show_glyphs(&glyphs[..]) {
+    if let Err(e) = ctx.show_text(font, pos.x, pos.y, label) {
         println!("{}", e);
     }
     ctx.restore()?;
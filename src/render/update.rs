@@ -0,0 +1,290 @@
+/*Copyright (c) 2022 Diego da Silva Lima. All rights reserved.
+
+This work is licensed under the terms of the MIT license.  
+For a copy, see <https://opensource.org/licenses/MIT>.*/
+
+use std::str::FromStr;
+use serde::Deserialize;
+use serde_json::Value;
+use crate::model::Adjustment;
+use crate::color::Color;
+use super::*;
+
+/// Properties set by Panel::update_from_json. The layout and design apply to the panel, the x and y
+/// scales to the plot at the informed position (the first one by default), and the properties
+/// under set to the mapping at the informed position within this plot. Names follow the model
+/// (e.g. {"layout": {"width": 800}, "plot": 1, "x": {"label": "Time"}, "mapping": 0, "set": {"color": "red"}}).
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PanelUpdate {
+    plot : Option<usize>,
+    mapping : Option<usize>,
+    layout : Option<LayoutUpdate>,
+    design : Option<DesignUpdate>,
+    x : Option<ScaleUpdate>,
+    y : Option<ScaleUpdate>,
+    set : Option<serde_json::Map<String, Value>>
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LayoutUpdate {
+    width : Option<i32>,
+    height : Option<i32>,
+    hratio : Option<f64>,
+    vratio : Option<f64>,
+    split : Option<String>
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DesignUpdate {
+    bgcolor : Option<String>,
+    fgcolor : Option<String>,
+    width : Option<i32>,
+    font : Option<String>
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScaleUpdate {
+    label : Option<String>,
+    from : Option<f64>,
+    to : Option<f64>,
+    log : Option<bool>,
+    invert : Option<bool>,
+    offset : Option<i32>,
+    precision : Option<i32>,
+    intervals : Option<i32>,
+    guide : Option<bool>,
+    adjust : Option<String>
+}
+
+fn color(c : &str) -> Result<String, String> {
+    Color::from_str(c).map(|_| c.to_string() ).map_err(|_| format!("Invalid color: {}", c) )
+}
+
+// Fonts are informed as "Family [Bold|Italic...] Size".
+fn font(f : &str) -> Result<String, String> {
+    if f.trim_end() == f && f.ends_with(|c : char| c.is_ascii_digit() ) {
+        Ok(f.to_string())
+    } else {
+        Err(format!("Invalid font: {} (expected a family followed by a size)", f))
+    }
+}
+
+fn layout_properties(panel : &Panel, l : LayoutUpdate, props : &mut Vec<GroupProperty>) -> Result<(), String> {
+    if let Some(w) = l.width {
+        if w <= 0 {
+            return Err(String::from("'width' should be strictly positive"));
+        }
+        props.push(GroupProperty::Layout(LayoutProperty::Width(w)));
+    }
+    if let Some(h) = l.height {
+        if h <= 0 {
+            return Err(String::from("'height' should be strictly positive"));
+        }
+        props.push(GroupProperty::Layout(LayoutProperty::Height(h)));
+    }
+    for (name, ratio) in [("hratio", l.hratio), ("vratio", l.vratio)].iter() {
+        if let Some(r) = ratio {
+            if !(0.0..=1.0).contains(r) {
+                return Err(format!("'{}' should be in the interval 0.0 - 1.0", name));
+            }
+            let prop = if *name == "hratio" { LayoutProperty::HorizontalRatio(*r) } else { LayoutProperty::VerticalRatio(*r) };
+            props.push(GroupProperty::Layout(prop));
+        }
+    }
+    if let Some(split) = l.split {
+        let split = GroupSplit::from_str(&split).map_err(|_| format!("Invalid split: {}", split) )?;
+        let n = n_plots_for_split(&split);
+        if n != panel.n_plots() {
+            return Err(format!("Split requires {} plots, but panel has {}", n, panel.n_plots()));
+        }
+        props.push(GroupProperty::Layout(LayoutProperty::Split(split)));
+    }
+    Ok(())
+}
+
+fn design_properties(d : DesignUpdate, props : &mut Vec<GroupProperty>) -> Result<(), String> {
+    if let Some(c) = d.bgcolor {
+        props.push(GroupProperty::Design(DesignProperty::BackgroundColor(color(&c)?)));
+    }
+    if let Some(c) = d.fgcolor {
+        props.push(GroupProperty::Design(DesignProperty::GridColor(color(&c)?)));
+    }
+    if let Some(w) = d.width {
+        if !(0..=50).contains(&w) {
+            return Err(String::from("Invalid grid width"));
+        }
+        props.push(GroupProperty::Design(DesignProperty::GridWidth(w)));
+    }
+    if let Some(f) = d.font {
+        props.push(GroupProperty::Design(DesignProperty::Font(font(&f)?)));
+    }
+    Ok(())
+}
+
+fn scale_properties(plot : usize, mode : ScaleMode, s : ScaleUpdate, props : &mut Vec<GroupProperty>) -> Result<(), String> {
+    let mut push = |prop| props.push(GroupProperty::Plot(plot, PlotProperty::Scale(mode, prop)));
+    if let Some(label) = s.label {
+        push(ScaleProperty::Label(label));
+    }
+    if let (Some(from), Some(to)) = (s.from, s.to) {
+        if from > to {
+            return Err(String::from("Inverted range"));
+        }
+    }
+    if let Some(from) = s.from {
+        push(ScaleProperty::Min(from));
+    }
+    if let Some(to) = s.to {
+        push(ScaleProperty::Max(to));
+    }
+    if let Some(log) = s.log {
+        push(ScaleProperty::Log(log));
+    }
+    if let Some(invert) = s.invert {
+        push(ScaleProperty::Invert(invert));
+    }
+    if let Some(offset) = s.offset {
+        if !(0..=100).contains(&offset) {
+            return Err(String::from("Invalid offset"));
+        }
+        push(ScaleProperty::GridOffset(offset));
+    }
+    if let Some(precision) = s.precision {
+        push(ScaleProperty::Precision(precision));
+    }
+    if let Some(intervals) = s.intervals {
+        if intervals <= 0 {
+            return Err(String::from("Number of steps"));
+        }
+        push(ScaleProperty::NIntervals(intervals));
+    }
+    if let Some(guide) = s.guide {
+        push(ScaleProperty::Guide(guide));
+    }
+    if let Some(adjust) = s.adjust {
+        let adj = Adjustment::from_str(&adjust).map_err(|_| format!("Invalid adjustment: {}", adjust) )?;
+        push(ScaleProperty::Adjustment(adj));
+    }
+    Ok(())
+}
+
+fn field<T : serde::de::DeserializeOwned>(key : &str, value : &Value) -> Result<T, String> {
+    serde_json::from_value(value.clone()).map_err(|e| format!("Invalid value for '{}': {}", key, e) )
+}
+
+// Builds the properties of a mapping according to its kind, since each kind accepts a distinct set.
+fn mapping_property(kind : &str, key : &str, value : &Value) -> Result<MappingProperty, String> {
+    let invalid = || format!("Property '{}' cannot be updated for {} mappings", key, kind);
+    let prop = match kind {
+        "line" => MappingProperty::Line(match key {
+            "color" => LineProperty::Color(color(&field::<String>(key, value)?)?),
+            "width" => LineProperty::Width(field(key, value)?),
+            "dash" => LineProperty::Dash(field(key, value)?),
+            "x" => LineProperty::X(field(key, value)?),
+            "y" => LineProperty::Y(field(key, value)?),
+            _ => return Err(invalid())
+        }),
+        "scatter" => MappingProperty::Scatter(match key {
+            "color" => ScatterProperty::Color(color(&field::<String>(key, value)?)?),
+            "radius" => ScatterProperty::Radius(field(key, value)?),
+            "x" => ScatterProperty::X(field(key, value)?),
+            "y" => ScatterProperty::Y(field(key, value)?),
+            _ => return Err(invalid())
+        }),
+        "text" => MappingProperty::Text(match key {
+            "color" => TextProperty::Color(color(&field::<String>(key, value)?)?),
+            "font" => TextProperty::Font(font(&field::<String>(key, value)?)?),
+            "x" => TextProperty::X(field(key, value)?),
+            "y" => TextProperty::Y(field(key, value)?),
+            "text" => TextProperty::Text(field(key, value)?),
+            _ => return Err(invalid())
+        }),
+        "interval" => MappingProperty::Interval(match key {
+            "color" => IntervalProperty::Color(color(&field::<String>(key, value)?)?),
+            "width" => IntervalProperty::Width(field(key, value)?),
+            "dash" => IntervalProperty::Dash(field(key, value)?),
+            "x" => IntervalProperty::Center(field(key, value)?),
+            "y" => IntervalProperty::Lower(field(key, value)?),
+            "z" => IntervalProperty::Upper(field(key, value)?),
+            "limits" => IntervalProperty::Limit(field(key, value)?),
+            "vertical" => IntervalProperty::Vertical(field(key, value)?),
+            _ => return Err(invalid())
+        }),
+        _ => return Err(format!("Properties of {} mappings cannot be updated (use the data operation to replace their data)", kind))
+    };
+    Ok(prop)
+}
+
+fn mapping_index(panel : &Panel, plot : usize, mapping : usize) -> Result<String, String> {
+    let kinds = panel.mapping_kinds(plot);
+    kinds.get(mapping).cloned()
+        .ok_or_else(|| format!("Invalid mapping {} (plot {} has {} mappings)", mapping, plot, kinds.len()) )
+}
+
+fn plot_index(panel : &Panel, plot : Option<usize>) -> Result<usize, String> {
+    let plot = plot.unwrap_or(0);
+    if plot < panel.n_plots() {
+        Ok(plot)
+    } else {
+        Err(format!("Invalid plot {} (panel has {} plots)", plot, panel.n_plots()))
+    }
+}
+
+impl Panel {
+
+    /// Applies the properties of a JSON object to the panel (see PanelUpdate), returning how many
+    /// were set. All properties are checked before any of them is applied.
+    pub fn update_from_json(&mut self, update : Value) -> Result<usize, String> {
+        let update : PanelUpdate = serde_json::from_value(update).map_err(|e| format!("Invalid update: {}", e) )?;
+        let mut props = Vec::new();
+        if let Some(l) = update.layout {
+            layout_properties(self, l, &mut props)?;
+        }
+        if let Some(d) = update.design {
+            design_properties(d, &mut props)?;
+        }
+        if update.x.is_some() || update.y.is_some() || update.set.is_some() {
+            let plot = plot_index(self, update.plot)?;
+            if let Some(x) = update.x {
+                scale_properties(plot, ScaleMode::Horizontal, x, &mut props)?;
+            }
+            if let Some(y) = update.y {
+                scale_properties(plot, ScaleMode::Vertical, y, &mut props)?;
+            }
+            if let Some(set) = update.set {
+                let mapping = update.mapping.ok_or_else(|| String::from("Missing mapping position for 'set'") )?;
+                let kind = mapping_index(self, plot, mapping)?;
+                for (key, value) in set.iter() {
+                    let prop = mapping_property(&kind, key, value)?;
+                    props.push(GroupProperty::Plot(plot, PlotProperty::Mapping(mapping, prop)));
+                }
+            }
+        }
+        let n = props.len();
+        for prop in props {
+            self.update(prop);
+        }
+        Ok(n)
+    }
+
+}
+
+#[test]
+fn update_from_json() {
+    let mut panel = Panel::new_from_json(r#"{"kind":"line","map":{"x":[0,1,2],"y":[0,1,4]}}"#).unwrap();
+    let n = panel.update_from_json(serde_json::json!({
+        "layout" : { "width" : 400 },
+        "x" : { "label" : "Time", "from" : 0.0, "to" : 2.0 },
+        "mapping" : 0,
+        "set" : { "color" : "red", "width" : 2.0 }
+    })).unwrap();
+    assert_eq!(n, 6);
+    assert_eq!(panel.get_dimensions().0, 400);
+    assert!(panel.update_from_json(serde_json::json!({ "mapping" : 0, "set" : { "radius" : 2.0 } })).is_err());
+    assert!(panel.update_from_json(serde_json::json!({ "plot" : 1, "x" : { "label" : "" } })).is_err());
+    assert!(panel.update_from_json(serde_json::json!({ "design" : { "bgcolor" : "nocolor" } })).is_err());
+}
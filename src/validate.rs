@@ -0,0 +1,483 @@
+/*Copyright (c) 2022 Diego da Silva Lima. All rights reserved.
+
+This work is licensed under the terms of the MIT license.  
+For a copy, see <https://opensource.org/licenses/MIT>.*/
+
+/*! Validation of whole JSON definitions. While render::Panel::new_from_json stops at the
+first problem, validate_json checks every design, layout, scale, plot, mapping and annotation
+of the document, and locates each error by a JSON pointer (RFC 6901) and by the line and column
+of the offending value at the source text. !*/
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use crate::model::*;
+
+/// Whether a problem prevents the definition from being rendered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+
+    Error,
+
+    /// The definition is rendered, but probably not as intended (e.g. missing values are left out).
+    Warning
+
+}
+
+/// An error (or warning) found at a JSON definition.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ValidationError {
+
+    /// JSON pointer to the offending value (empty for the whole document).
+    pub pointer : String,
+
+    /// Line of the offending value at the source text, starting at 1.
+    pub line : usize,
+
+    /// Column (in characters) of the offending value at the source text, starting at 1.
+    pub column : usize,
+
+    pub message : String,
+
+    pub severity : Severity
+
+}
+
+impl ValidationError {
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+}
+
+impl fmt::Display for ValidationError {
+
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: ", self.line, self.column)?;
+        if !self.pointer.is_empty() {
+            write!(f, "{}: ", self.pointer)?;
+        }
+        if self.severity == Severity::Warning {
+            write!(f, "warning: ")?;
+        }
+        write!(f, "{}", self.message)
+    }
+
+}
+
+/// Validates a panel, plot or mapping definition, returning all errors and warnings found (in
+/// document order). If none of them is an error, render::Panel::new_from_json accepts the
+/// definition. Warnings report the number of missing values of each data column.
+pub fn validate_json(json : &str) -> Vec<ValidationError> {
+    let v : Value = match serde_json::from_str(json) {
+        Ok(v) => v,
+        Err(e) => {
+
+            // Syntax errors carry their own location.
+            let msg = format!("{}", e);
+            let msg = msg.split(" at line ").next().unwrap_or(&msg[..]).to_string();
+            return vec![ValidationError {
+                pointer : String::new(),
+                line : e.line(),
+                column : e.column().max(1),
+                message : msg,
+                severity : Severity::Error
+            }];
+        }
+    };
+
+    let mut errs = Errors::default();
+    let can_be_panel = v.get("plots").is_some();
+    let can_be_plot = v.get("mappings").is_some() && v.get("x").is_some() && v.get("y").is_some();
+    let can_be_mapping = v.get("kind").is_some() && v.get("map").is_some();
+    match (can_be_panel, can_be_plot, can_be_mapping) {
+        (true, false, false) => check_panel(&v, &mut errs),
+        (false, true, false) => check_plot(&v, "", &mut errs),
+        (false, false, true) => check_mapping(&v, "", &mut errs),
+        (false, false, false) => errs.push("", "Definition is neither a panel (with plots), a plot (with mappings, x and y) nor a mapping (with kind and map)"),
+        _ => errs.push("", "Ambiguous definition: only one of panel (plots), plot (mappings, x and y) or mapping (kind and map) keys should be present")
+    }
+
+    // Remaining checks happen when the panel is built (such as the number of plots).
+    if errs.0.is_empty() {
+        if let Err(e) = crate::render::Panel::new_from_json_value(v) {
+            errs.push("", e);
+        }
+    }
+
+    let locations = locate(json);
+    let errors = errs.0.into_iter().map(|e| (e, Severity::Error) );
+    let warnings = errs.1.into_iter().map(|w| (w, Severity::Warning) );
+    let mut errs : Vec<_> = errors.chain(warnings).map(|((pointer, message), severity)| {
+        let (line, column) = location(&locations, &pointer);
+        ValidationError { pointer, line, column, message, severity }
+    }).collect();
+    errs.sort_by_key(|e| (e.line, e.column) );
+    errs
+}
+
+// Holds the errors and the warnings found, as (pointer, message) pairs.
+#[derive(Default)]
+struct Errors(Vec<(String, String)>, Vec<(String, String)>);
+
+impl Errors {
+
+    fn push(&mut self, pointer : &str, msg : impl ToString) {
+        self.0.push((pointer.to_string(), msg.to_string()));
+    }
+
+    fn warn(&mut self, pointer : &str, msg : impl ToString) {
+        self.1.push((pointer.to_string(), msg.to_string()));
+    }
+
+}
+
+// Deserializes the value at key, reporting the error at its pointer when it fails.
+fn component<T : DeserializeOwned>(v : &Value, prefix : &str, key : &str, errs : &mut Errors) -> Option<(T, String)> {
+    let pointer = format!("{}/{}", prefix, escape(key));
+    match v.get(key) {
+        None | Some(Value::Null) => None,
+        Some(c) => match serde_json::from_value::<T>(c.clone()) {
+            Ok(t) => Some((t, pointer)),
+            Err(e) => {
+                errs.push(&pointer, e);
+                None
+            }
+        }
+    }
+}
+
+fn array<'a>(v : &'a Value, prefix : &str, key : &str, errs : &mut Errors) -> &'a [Value] {
+    match v.get(key) {
+        Some(Value::Array(items)) => &items[..],
+        None | Some(Value::Null) => &[],
+        Some(_) => {
+            errs.push(&format!("{}/{}", prefix, key), format!("'{}' should be an array", key));
+            &[]
+        }
+    }
+}
+
+fn check_design(v : &Value, prefix : &str, errs : &mut Errors) {
+    if let Some((design, pointer)) = component::<Design>(v, prefix, "design", errs) {
+        if let Err(e) = design.validate() {
+            let field = match e {
+                DesignError::InvalidGridWidth => "width",
+                DesignError::InvalidGridColor => "fgcolor",
+                DesignError::InvalidBackgroundColor => "bgcolor",
+                DesignError::InvalidPanelBackgroundColor => "panel_bgcolor",
+                DesignError::InvalidPlotBackgroundColor => "plot_bgcolor"
+            };
+            errs.push(&format!("{}/{}", pointer, field), e);
+        }
+    }
+}
+
+fn check_layout(v : &Value, prefix : &str, errs : &mut Errors) {
+    if let Some((layout, pointer)) = component::<Layout>(v, prefix, "layout", errs) {
+        if let Err(e) = layout.validate() {
+            let field = match e {
+                LayoutError::Width => "width",
+                LayoutError::Height => "height",
+                LayoutError::HRatio => "hratio",
+                LayoutError::VRatio => "vratio",
+                LayoutError::Split => "split",
+                LayoutError::Unit => "unit",
+                LayoutError::Dpi => "dpi"
+            };
+            errs.push(&format!("{}/{}", pointer, field), e);
+        }
+    }
+}
+
+fn check_panel(v : &Value, errs : &mut Errors) {
+    let before = errs.0.len();
+    check_design(v, "", errs);
+    check_layout(v, "", errs);
+    for (i, plot) in array(v, "", "plots", errs).iter().enumerate() {
+        check_plot(plot, &format!("/plots/{}", i), errs);
+    }
+    if errs.0.len() == before {
+        if let Err(e) = serde_json::from_value::<Panel>(v.clone()) {
+            errs.push("", e);
+        }
+    }
+}
+
+fn check_plot(v : &Value, prefix : &str, errs : &mut Errors) {
+    let before = errs.0.len();
+    check_design(v, prefix, errs);
+    check_layout(v, prefix, errs);
+    for axis in ["x", "y"] {
+        if let Some((scale, pointer)) = component::<Scale>(v, prefix, axis, errs) {
+            if let Err(e) = scale.validate() {
+                let field = match e {
+                    ScaleError::InvertedRange => "from",
+                    ScaleError::StepNumber => "intervals",
+                    ScaleError::InvalidOffset => "offset",
+                    ScaleError::InvalidAdjustment => "adjust"
+                };
+                errs.push(&format!("{}/{}", pointer, field), e);
+            }
+        }
+    }
+    for (i, m) in array(v, prefix, "mappings", errs).iter().enumerate() {
+        check_mapping(m, &format!("{}/mappings/{}", prefix, i), errs);
+    }
+    for (i, a) in array(v, prefix, "annotations", errs).iter().enumerate() {
+        check_annotation(a, &format!("{}/annotations/{}", prefix, i), errs);
+    }
+    if let Some(Value::String(coords)) = v.get("coords") {
+        if Coordinates::from_str(coords).is_err() {
+            errs.push(&format!("{}/coords", prefix), PlotError::InvalidCoordinates(coords.to_string()));
+        }
+    }
+
+    // Errors outside the components (such as missing scales or invalid titles).
+    if errs.0.len() == before {
+        if let Err(e) = serde_json::from_value::<Plot>(v.clone()) {
+            errs.push(prefix, e);
+        }
+    }
+}
+
+fn check_mapping(v : &Value, prefix : &str, errs : &mut Errors) {
+    match serde_json::from_value::<Mapping>(v.clone()) {
+        Ok(m) => {
+            for (column, count) in m.map.missing_values() {
+                errs.warn(&format!("{}/map/{}", prefix, column), MappingError::MissingValues { column, count });
+            }
+            if let Err(e) = m.validate() {
+                let field = match &e {
+                    MappingError::MissingColumn => String::from("map/x"),
+                    MappingError::InvalidKind(_) => String::from("kind"),
+                    MappingError::DataLength { column : "t", .. } => String::from("map/text"),
+                    MappingError::DataLength { column, .. } => format!("map/{}", column),
+                    MappingError::MissingValues { column, .. } => format!("map/{}", column),
+                    MappingError::DataMapping { .. } => String::from("map"),
+                    MappingError::InvalidColor if m.color.as_ref().map(|c| !validate_color(c) ).unwrap_or(false) => String::from("color"),
+                    MappingError::InvalidColor => String::from("color_final"),
+                    MappingError::InvalidProperty(name) => match &name[..] {
+                        "colorfinal" => String::from("color_final"),
+                        "zstart" => String::from("z_start"),
+                        "zend" => String::from("z_end"),
+                        other => other.to_string()
+                    }
+                };
+                errs.push(&format!("{}/{}", prefix, field), e);
+            }
+        },
+        Err(e) => {
+
+            // Locates invalid data columns, which are deserialized one at a time.
+            let before = errs.0.len();
+            if let Some(Value::Object(map)) = v.get("map") {
+                for (col, data) in map {
+                    let single = Value::Object(std::iter::once((col.clone(), data.clone())).collect());
+                    if let Err(e) = serde_json::from_value::<Map>(single) {
+                        errs.push(&format!("{}/map/{}", prefix, escape(col)), e);
+                    }
+                }
+            }
+            if errs.0.len() == before {
+                errs.push(prefix, e);
+            }
+        }
+    }
+}
+
+fn check_annotation(v : &Value, prefix : &str, errs : &mut Errors) {
+    match serde_json::from_value::<Annotation>(v.clone()) {
+        Ok(a) => {
+            if let Err(e) = a.validate() {
+                let field = match &e {
+                    AnnotationError::InvalidKind(_) => Some(String::from("kind")),
+                    AnnotationError::InvalidProperty(name) => Some(name.clone()),
+                    AnnotationError::InvalidColor => Some(String::from("color")),
+                    AnnotationError::MissingCoordinate { .. } => None
+                };
+                match field {
+                    Some(field) => errs.push(&format!("{}/{}", prefix, field), e),
+                    None => errs.push(prefix, e)
+                }
+            }
+        },
+        Err(e) => errs.push(prefix, e)
+    }
+}
+
+// Escapes a key as a JSON pointer reference token.
+fn escape(key : &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+// Uses the location of the closest ancestor for pointers to absent values.
+fn location(locations : &HashMap<String, (usize, usize)>, pointer : &str) -> (usize, usize) {
+    let mut pointer = pointer;
+    loop {
+        if let Some(loc) = locations.get(pointer) {
+            return *loc;
+        }
+        match pointer.rfind('/') {
+            Some(ix) => pointer = &pointer[..ix],
+            None => return (1, 1)
+        }
+    }
+}
+
+/// Maps the JSON pointer of every value at a (syntactically valid) JSON document
+/// to its line and column.
+pub fn locate(json : &str) -> HashMap<String, (usize, usize)> {
+    let mut scanner = Scanner { chars : json.chars().peekable(), line : 1, column : 1, locations : HashMap::new() };
+    scanner.value(String::new());
+    scanner.locations
+}
+
+struct Scanner<'a> {
+    chars : std::iter::Peekable<std::str::Chars<'a>>,
+    line : usize,
+    column : usize,
+    locations : HashMap<String, (usize, usize)>
+}
+
+impl<'a> Scanner<'a> {
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.peek().map(|c| c.is_whitespace() ).unwrap_or(false) {
+            self.next();
+        }
+    }
+
+    fn value(&mut self, pointer : String) {
+        self.skip_whitespace();
+        self.locations.insert(pointer.clone(), (self.line, self.column));
+        match self.chars.peek() {
+            Some('{') => {
+                self.next();
+                loop {
+                    self.skip_whitespace();
+                    match self.next() {
+                        Some('"') => {
+                            let key = self.string();
+                            self.skip_whitespace();
+                            self.next();
+                            self.value(format!("{}/{}", pointer, escape(&key)));
+                        },
+                        Some(',') => { },
+                        _ => break
+                    }
+                }
+            },
+            Some('[') => {
+                self.next();
+                let mut ix = 0;
+                loop {
+                    self.skip_whitespace();
+                    match self.chars.peek() {
+                        Some(']') | None => {
+                            self.next();
+                            break;
+                        },
+                        Some(',') => {
+                            self.next();
+                        },
+                        _ => {
+                            self.value(format!("{}/{}", pointer, ix));
+                            ix += 1;
+                        }
+                    }
+                }
+            },
+            Some('"') => {
+                self.next();
+                self.string();
+            },
+            _ => {
+                while self.chars.peek().map(|c| !(c.is_whitespace() || *c == ',' || *c == ']' || *c == '}') ).unwrap_or(false) {
+                    self.next();
+                }
+            }
+        }
+    }
+
+    // Reads the remaining of a string (after the opening quote), returning its contents.
+    fn string(&mut self) -> String {
+        let mut s = String::new();
+        while let Some(c) = self.next() {
+            match c {
+                '"' => break,
+                '\\' => match self.next() {
+                    Some('n') => s.push('\n'),
+                    Some('t') => s.push('\t'),
+                    Some('r') => s.push('\r'),
+                    Some('b') => s.push('\u{8}'),
+                    Some('f') => s.push('\u{c}'),
+                    Some('u') => {
+                        let hex : String = (0..4).filter_map(|_| self.next() ).collect();
+                        let c = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32);
+                        s.push(c.unwrap_or(char::REPLACEMENT_CHARACTER));
+                    },
+                    Some(other) => s.push(other),
+                    None => break
+                },
+                other => s.push(other)
+            }
+        }
+        s
+    }
+
+}
+
+#[test]
+fn validate_whole_document() {
+    let json = r##"{
+  "plots": [
+    {"mappings": [], "x": {"label": "", "from": 0.0, "to": 1.0}, "y": {"label": "", "from": 1.0, "to": 0.0}},
+    {
+      "mappings": [
+        {"kind": "line", "map": {"x": [1, 2], "y": [1]}},
+        {"kind": "scatter", "map": {"x": [1, 2], "y": [1, 2]}, "color": "nocolor"}
+      ],
+      "x": {"label": "", "from": 0.0, "to": 1.0},
+      "y": {"label": "", "from": 0.0, "to": 1.0}
+    }
+  ]
+}"##;
+    let errs = validate_json(json);
+    let found : Vec<_> = errs.iter().map(|e| (&e.pointer[..], e.line, e.column) ).collect();
+    assert_eq!(found, vec![
+        ("/plots/0/y/from", 3, 93),
+        ("/plots/1/mappings/0/map/y", 6, 52),
+        ("/plots/1/mappings/1/color", 7, 73)
+    ]);
+    assert!(validate_json("{\"plots\": [}").len() == 1);
+}
+
+#[test]
+fn validate_missing_values() {
+    let json = r#"{"kind": "line", "map": {"x": [1, null, 3], "y": [null, 2, null]}}"#;
+    let errs = validate_json(json);
+    let found : Vec<_> = errs.iter().map(|e| (&e.pointer[..], e.severity, &e.message[..]) ).collect();
+    assert_eq!(found, vec![
+        ("/map/x", Severity::Warning, "Found 1 missing value(s) at column x"),
+        ("/map/y", Severity::Warning, "Found 2 missing value(s) at column y")
+    ]);
+    assert_eq!(errs[0].to_string(), "1:31: /map/x: warning: Found 1 missing value(s) at column x");
+    assert!(validate_json(r#"{"kind": "line", "map": {"x": [1, null], "y": [1]}}"#).iter().any(|e| e.is_error() ));
+}